@@ -0,0 +1,190 @@
+//! Local throughput benchmark behind the hidden `--benchmark <N>` flag.
+//!
+//! Inserts N synthetic heartbeats into a throwaway queue and times each stage
+//! of the local pipeline, ending with a sync against an in-process server that
+//! accepts every batch, so the numbers exclude real network latency.
+
+use serde::Serialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::api::ApiClient;
+use crate::heartbeat::Heartbeat;
+use crate::queue::{Queue, QueueOps};
+use crate::sync::{ChronovaSyncManager, SyncConfig, SyncManager, SyncOperation};
+
+/// Synthetic heartbeats are spread over this many files
+const BENCH_FILES: usize = 50;
+
+/// Timing of a single benchmark stage
+#[derive(Debug, Clone, Serialize)]
+pub struct StageTiming {
+    pub name: String,
+    pub items: usize,
+    pub duration_ms: f64,
+    pub per_second: f64,
+}
+
+impl StageTiming {
+    fn new(name: &str, items: usize, elapsed: Duration) -> Self {
+        let secs = elapsed.as_secs_f64();
+        Self {
+            name: name.to_string(),
+            items,
+            duration_ms: secs * 1000.0,
+            // Stages faster than the clock resolution still report throughput
+            per_second: items as f64 / secs.max(f64::EPSILON),
+        }
+    }
+}
+
+/// Latency percentiles of one sync operation during the benchmark
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationLatency {
+    pub operation: String,
+    pub count: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+}
+
+/// Report printed by `--benchmark`
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub heartbeats: usize,
+    pub stages: Vec<StageTiming>,
+    pub latency: Vec<OperationLatency>,
+}
+
+impl BenchmarkReport {
+    /// Render one line per stage with its duration and throughput, followed by
+    /// the sync latency percentiles per operation
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![format!("Benchmark with {} heartbeats", self.heartbeats)];
+        lines.extend(self.stages.iter().map(|stage| {
+            format!(
+                "  {:<12} {:>8} items {:>10.2} ms {:>12.0} /s",
+                stage.name, stage.items, stage.duration_ms, stage.per_second
+            )
+        }));
+        if !self.latency.is_empty() {
+            lines.push("Sync latency".to_string());
+            lines.extend(self.latency.iter().map(|latency| {
+                format!(
+                    "  {:<14} {:>6} ops  p50 {:>6} ms  p95 {:>6} ms",
+                    latency.operation, latency.count, latency.p50_ms, latency.p95_ms
+                )
+            }));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Run every stage against a temporary queue and a local mock server
+pub async fn run(count: usize) -> anyhow::Result<BenchmarkReport> {
+    let temp_dir = tempfile::tempdir()?;
+    let db_path = temp_dir.path().join("benchmark.db");
+    let queue = Queue::with_path(db_path.clone())?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64();
+    let heartbeats: Vec<Heartbeat> = (0..count)
+        .map(|i| {
+            let entity = format!("/benchmark/src/file{}.rs", i % BENCH_FILES);
+            Heartbeat::builder(entity, now - (count - i) as f64)
+                .language("Rust".to_string())
+                .project("benchmark".to_string())
+                .build()
+        })
+        .collect();
+
+    let mut stages = Vec::new();
+
+    let started = Instant::now();
+    queue.add_batch(heartbeats)?;
+    stages.push(StageTiming::new("insert", count, started.elapsed()));
+
+    let started = Instant::now();
+    let pending = queue.get_pending(Some(count), None)?;
+    stages.push(StageTiming::new(
+        "get_pending",
+        pending.len(),
+        started.elapsed(),
+    ));
+
+    let started = Instant::now();
+    queue.deduplicate(1)?;
+    stages.push(StageTiming::new("dedup", pending.len(), started.elapsed()));
+    let remaining = queue.count()?;
+    drop(queue);
+
+    // Mock API server: answer every request with `201 Created`
+    let server = MockServer::start().await;
+    Mock::given(wiremock::matchers::any())
+        .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!([])))
+        .mount(&server)
+        .await;
+
+    let manager =
+        ChronovaSyncManager::with_config(SyncConfig::default(), ApiClient::new(server.uri()))
+            .with_queue_path(db_path);
+    let started = Instant::now();
+    let result = manager.sync_pending().await?;
+    stages.push(StageTiming::new(
+        "sync",
+        result.synced_count,
+        started.elapsed(),
+    ));
+    drop(server);
+
+    let latency = SyncOperation::ALL
+        .iter()
+        .filter_map(|operation| {
+            let histogram = manager.latency_histogram(*operation)?;
+            Some(OperationLatency {
+                operation: operation.as_str().to_string(),
+                count: histogram.count,
+                p50_ms: histogram.p50()?,
+                p95_ms: histogram.p95()?,
+            })
+        })
+        .collect();
+
+    if result.synced_count != remaining {
+        anyhow::bail!(
+            "mock sync accepted {} of {} heartbeats",
+            result.synced_count,
+            remaining
+        );
+    }
+
+    Ok(BenchmarkReport {
+        heartbeats: count,
+        stages,
+        latency,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_small_benchmark_reports_throughput() {
+        let report = run(200).await.unwrap();
+
+        let names: Vec<&str> = report.stages.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["insert", "get_pending", "dedup", "sync"]);
+        for stage in &report.stages {
+            assert!(stage.items > 0, "{} processed nothing", stage.name);
+            assert!(
+                stage.per_second > 0.0,
+                "{} reported no throughput",
+                stage.name
+            );
+        }
+        assert!(report
+            .latency
+            .iter()
+            .any(|latency| latency.operation == "sync_pending" && latency.count == 1));
+        assert!(report.to_text().contains("200 heartbeats"));
+    }
+}
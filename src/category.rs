@@ -0,0 +1,195 @@
+//! Category inference for heartbeats sent without `--category`.
+//!
+//! A rule pairs a file pattern with a category and can be limited to writes
+//! or reads. Rules come from the `[category_rules]` config section and are
+//! tried before the built-in ones; a heartbeat no rule matches is `coding`.
+//!
+//! ```ini
+//! [category_rules]
+//! write *.md = writing docs
+//! read *_test.rs = debugging
+//! */design/* = designing
+//! ```
+//!
+//! Patterns use `*` as a wildcard and are matched case-insensitively against
+//! the file name, or against the whole path when they contain a `/`.
+
+/// Category used when no rule matches
+pub const DEFAULT_CATEGORY: &str = "coding";
+
+/// Built-in rules, tried after the configured ones
+const BUILTIN_RULES: &[(&str, &str)] = &[
+    ("write readme*", "writing docs"),
+    ("write changelog*", "writing docs"),
+    ("write *.md", "writing docs"),
+    ("write *.mdx", "writing docs"),
+    ("write *.rst", "writing docs"),
+    ("write *.adoc", "writing docs"),
+    ("write */docs/*", "writing docs"),
+];
+
+/// A single `pattern = category` rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CategoryRule {
+    pattern: String,
+    /// `Some(true)` for write-only rules, `Some(false)` for read-only ones
+    is_write: Option<bool>,
+    category: String,
+}
+
+impl CategoryRule {
+    /// Parse a `[write |read ]<pattern>` key and its category
+    pub fn parse(key: &str, category: &str) -> Option<Self> {
+        let key = key.trim().to_lowercase();
+        let (is_write, pattern) = if let Some(pattern) = key.strip_prefix("write ") {
+            (Some(true), pattern)
+        } else if let Some(pattern) = key.strip_prefix("read ") {
+            (Some(false), pattern)
+        } else {
+            (None, key.as_str())
+        };
+
+        let pattern = pattern.trim();
+        let category = category.trim();
+        if pattern.is_empty() || category.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            pattern: pattern.to_string(),
+            is_write,
+            category: category.to_string(),
+        })
+    }
+
+    fn matches(&self, entity: &str, is_write: bool) -> bool {
+        if self.is_write.is_some_and(|write| write != is_write) {
+            return false;
+        }
+
+        let entity = entity.replace('\\', "/").to_lowercase();
+        let subject = if self.pattern.contains('/') {
+            entity.as_str()
+        } else {
+            entity.rsplit('/').next().unwrap_or(&entity)
+        };
+        wildcard_match(&self.pattern, subject)
+    }
+}
+
+/// Parse the `[category_rules]` section. Since INI sections are unordered,
+/// rules limited to writes or reads go first, then longer patterns; bad
+/// entries are skipped with a warning.
+pub fn parse_rules<'a>(entries: impl IntoIterator<Item = (&'a str, &'a str)>) -> Vec<CategoryRule> {
+    let mut rules: Vec<CategoryRule> = entries
+        .into_iter()
+        .filter_map(|(key, category)| {
+            let rule = CategoryRule::parse(key, category);
+            if rule.is_none() {
+                tracing::warn!("Ignoring invalid category rule: {} = {}", key, category);
+            }
+            rule
+        })
+        .collect();
+
+    rules.sort_by(|a, b| {
+        b.is_write
+            .is_some()
+            .cmp(&a.is_write.is_some())
+            .then(b.pattern.len().cmp(&a.pattern.len()))
+            .then(a.pattern.cmp(&b.pattern))
+    });
+    rules
+}
+
+/// Category for a file heartbeat: the first matching configured rule, then
+/// the first matching built-in rule, then [`DEFAULT_CATEGORY`]
+pub fn infer_category(rules: &[CategoryRule], entity: &str, is_write: bool) -> String {
+    if let Some(rule) = rules.iter().find(|rule| rule.matches(entity, is_write)) {
+        return rule.category.clone();
+    }
+
+    BUILTIN_RULES
+        .iter()
+        .filter_map(|(key, category)| CategoryRule::parse(key, category))
+        .find(|rule| rule.matches(entity, is_write))
+        .map(|rule| rule.category)
+        .unwrap_or_else(|| DEFAULT_CATEGORY.to_string())
+}
+
+/// Match `text` against `pattern`, where `*` matches any run of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text index it is currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readme_write_is_writing_docs() {
+        assert_eq!(infer_category(&[], "/repo/README.md", true), "writing docs");
+        assert_eq!(
+            infer_category(&[], "/repo/docs/guide/setup.txt", true),
+            "writing docs"
+        );
+        // Reading docs is still coding unless configured otherwise
+        assert_eq!(infer_category(&[], "/repo/README.md", false), "coding");
+    }
+
+    #[test]
+    fn test_rust_read_defaults_to_coding() {
+        assert_eq!(infer_category(&[], "/repo/src/main.rs", false), "coding");
+        assert_eq!(infer_category(&[], "/repo/src/main.rs", true), "coding");
+    }
+
+    #[test]
+    fn test_configured_rules_take_precedence() {
+        let rules = parse_rules([
+            ("*.rs", "coding"),
+            ("read *_test.rs", "debugging"),
+            ("write readme.md", "code reviewing"),
+            ("write", ""),
+        ]);
+        assert_eq!(rules.len(), 3);
+
+        assert_eq!(
+            infer_category(&rules, "/repo/src/queue_test.rs", false),
+            "debugging"
+        );
+        assert_eq!(
+            infer_category(&rules, "/repo/src/queue_test.rs", true),
+            "coding"
+        );
+        assert_eq!(
+            infer_category(&rules, "C:\\repo\\README.md", true),
+            "code reviewing"
+        );
+        assert_eq!(
+            infer_category(&rules, "/repo/CHANGELOG.md", true),
+            "writing docs"
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use clap::Parser;
 
 /// A high-performance, drop-in replacement for wakatime-cli
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(
     version,
     about,
@@ -14,6 +14,10 @@ pub struct Cli {
     #[arg(long, alias = "file")]
     pub entity: Option<String>,
 
+    /// Reads the entity path from STDIN instead of --entity, so paths with spaces or quotes need no shell quoting.
+    #[arg(long, conflicts_with_all = ["entity", "extra_heartbeats"])]
+    pub entity_from_stdin: bool,
+
     /// Your chronova api key; uses api_key from ~/.chronova.cfg by default.
     #[arg(long)]
     pub key: Option<String>,
@@ -42,7 +46,7 @@ pub struct Cli {
     #[arg(long)]
     pub category: Option<String>,
 
-    /// Override auto-detected project. Use --alternate-project to supply a fallback project if one can't be auto-detected.
+    /// Override auto-detected project. Use --alternate-project to supply a fallback project if one can't be auto-detected. With --today, only shows time for this project.
     #[arg(long)]
     pub project: Option<String>,
 
@@ -62,6 +66,14 @@ pub struct Cli {
     #[arg(long)]
     pub verbose: bool,
 
+    /// Suppresses informational output on stdout. Errors still go to stderr, and JSON output is still printed when requested with --output.
+    #[arg(long)]
+    pub quiet: bool,
+
+    /// Prints the heartbeat that would be sent as JSON without queueing or sending it. With --verbose, also explains how project, git and language were detected.
+    #[arg(long)]
+    pub dry_run: bool,
+
     /// When set, tells api this heartbeat was triggered from writing to a file. Accepts explicit true/false values for compatibility with clients that pass values.
     #[arg(long, value_parser = clap::value_parser!(bool), value_name = "true|false", num_args = 0..=1, default_missing_value = "true")]
     pub write: Option<bool>,
@@ -79,7 +91,7 @@ pub struct Cli {
     pub alternate_project: Option<String>,
 
     /// API base url used when sending heartbeats and fetching code stats. Defaults to https://chronova.dev/api/v1.
-    #[arg(long)]
+    #[arg(long, value_parser = parse_api_url)]
     pub api_url: Option<String>,
 
     /// Optional name of local machine. Defaults to local machine name read from system.
@@ -162,7 +174,7 @@ pub struct Cli {
     #[arg(long)]
     pub ssl_certs_file: Option<String>,
 
-    /// Format output. Can be "text", "json" or "raw-json". Defaults to "text".
+    /// Format output. Can be "text", "json" or "raw-json" (the server response, unmodified). Defaults to "text".
     #[arg(long)]
     pub output: Option<String>,
 
@@ -174,6 +186,10 @@ pub struct Cli {
     #[arg(long)]
     pub proxy: Option<String>,
 
+    /// Connect directly, ignoring the configured proxy and any HTTP(S)_PROXY environment variables.
+    #[arg(long, conflicts_with = "proxy")]
+    pub no_proxy: bool,
+
     /// When --verbose or debug enabled, also sends diagnostics on any error not just crashes.
     #[arg(long)]
     pub send_diagnostics_on_errors: bool,
@@ -186,6 +202,14 @@ pub struct Cli {
     #[arg(long)]
     pub sync_offline_activity: Option<i32>,
 
+    /// When used with --sync-offline-activity, only syncs heartbeats queued within this many days. Older heartbeats stay pending.
+    #[arg(long, value_name = "DAYS")]
+    pub sync_max_age: Option<u32>,
+
+    /// When used with --sync-offline-activity, uploads the backlog in large batches through the server's bulk endpoint, falling back to normal sync if it has none.
+    #[arg(long, requires = "sync_offline_activity")]
+    pub catch_up: bool,
+
     /// Force sync all offline heartbeats regardless of connectivity status.
     #[arg(long)]
     pub force_sync: bool,
@@ -194,12 +218,69 @@ pub struct Cli {
     #[arg(long)]
     pub offline_count: bool,
 
+    /// Prints offline queue status, including heartbeats stuck in syncing, then exits.
+    #[arg(long)]
+    pub status: bool,
+
+    /// Queues write heartbeats for every file changed by the current repository's HEAD commit, then exits.
+    #[arg(long)]
+    pub from_git_hook: bool,
+
+    /// Watches a directory and queues write heartbeats for files saved under it, for editors without a plugin. Runs until interrupted.
+    #[arg(long, value_name = "DIR")]
+    pub watch: Option<String>,
+
+    /// Installs a post-commit hook in the current repository that runs --from-git-hook, then exits.
+    #[arg(long)]
+    pub install_git_hook: bool,
+
+    /// Runs a read-only integrity check of the offline queue database and prints a report, then exits.
+    #[arg(long)]
+    pub queue_health: bool,
+
+    /// Writes a JSON bundle for bug reports to PATH: config with secrets masked, queue stats and latest rows, version and relevant environment variables, then exits.
+    #[arg(long, value_name = "PATH")]
+    pub debug_dump_queue: Option<String>,
+
+    /// Runs an offline self-test of config, data directory, queue and detection, then exits.
+    #[arg(long)]
+    pub selftest: bool,
+
+    /// Times queue inserts, reads, dedup and a mocked sync over N synthetic heartbeats, then exits.
+    #[arg(long, value_name = "N", hide = true)]
+    pub benchmark: Option<usize>,
+
     /// Reads extra heartbeats from STDIN as a JSON array until EOF.
     #[arg(long)]
     pub extra_heartbeats: bool,
 
-    /// Prints the top developer within a team for the given entity, then exits.
+    /// Writes all heartbeats in the offline db to the given file as a WakaTime bulk JSON array, then exits.
+    #[arg(long)]
+    pub export_heartbeats: Option<String>,
+
+    /// Reads a WakaTime bulk JSON array from the given file into the offline db, then exits.
     #[arg(long)]
+    pub import_heartbeats: Option<String>,
+
+    /// Shifts the time of every unsynced heartbeat in the offline db by --offset seconds, to repair heartbeats recorded with a wrong clock, then exits. With --dry-run, only prints the changes.
+    #[arg(long, requires = "offset")]
+    pub fix_timestamps: bool,
+
+    /// Seconds to add to heartbeat times for --fix-timestamps. Negative values move them back.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        allow_negative_numbers = true,
+        requires = "fix_timestamps"
+    )]
+    pub offset: Option<f64>,
+
+    /// Re-detects the project of unsynced heartbeats queued from inside a git worktree and names them after the main repository, for heartbeats queued before worktrees were resolved, then exits. With --dry-run, only prints the changes.
+    #[arg(long)]
+    pub repair_worktree_paths: bool,
+
+    /// Prints the top developer within a team for the given entity, then exits.
+    #[arg(long, requires = "entity")]
     pub file_experts: bool,
 
     /// Prints value for the given config key, then exits.
@@ -214,6 +295,18 @@ pub struct Cli {
     #[arg(long, num_args = 2)]
     pub config_write: Option<Vec<String>>,
 
+    /// Overrides a [settings] key for this run only, without editing the config file. Repeatable, e.g. -o api_url=http://localhost:8080 -o debug=true.
+    #[arg(short = 'o', long = "config-override", value_name = "KEY=VALUE")]
+    pub config_overrides: Vec<String>,
+
+    /// Checks that the config file parses without errors, then exits.
+    #[arg(long)]
+    pub config_validate: bool,
+
+    /// Prints the config file path in effect and whether it exists, with the resolved api url and a masked api key, then exits.
+    #[arg(long)]
+    pub print_config_path: bool,
+
     /// Optional internal config file. Defaults to '~/.wakatime/wakatime-internal.cfg'.
     #[arg(long)]
     pub internal_config: Option<String>,
@@ -226,6 +319,14 @@ pub struct Cli {
     #[arg(long)]
     pub print_offline_heartbeats: Option<i32>,
 
+    /// Prints today's total with the top languages, projects and editors, then exits.
+    #[arg(long)]
+    pub today_detailed: bool,
+
+    /// Prints today's total for the given team with a per-member breakdown, then exits. Ignored with a warning for accounts without team features.
+    #[arg(long, value_name = "TEAM_ID")]
+    pub today_team: Option<String>,
+
     /// Prints time for the given goal id today, then exits.
     #[arg(long)]
     pub today_goal: Option<String>,
@@ -234,6 +335,10 @@ pub struct Cli {
     #[arg(long)]
     pub today_hide_categories: bool,
 
+    /// When optionally included with --today, prints nothing when there is no activity instead of the today_empty_text placeholder.
+    #[arg(long)]
+    pub today_raw: bool,
+
     /// (internal) Prints the wakatime-cli useragent, as it will be sent to the api, then exits.
     #[arg(long)]
     pub user_agent: bool,
@@ -274,3 +379,121 @@ pub struct Cli {
     #[arg(long)]
     pub self_update: bool,
 }
+
+/// Convert raw process arguments to strings, replacing invalid UTF-8 (possible
+/// in Linux file names) with U+FFFD instead of aborting. Entities converted
+/// this way still produce a best-effort heartbeat.
+pub fn args_lossy<I>(args: I) -> Vec<String>
+where
+    I: IntoIterator<Item = std::ffi::OsString>,
+{
+    args.into_iter()
+        .map(|arg| {
+            arg.into_string()
+                .unwrap_or_else(|raw| raw.to_string_lossy().into_owned())
+        })
+        .collect()
+}
+
+/// Check an `--api-url` value has an http(s) scheme and a host, so a typo
+/// fails up front instead of queueing every heartbeat as undeliverable
+pub fn parse_api_url(value: &str) -> Result<String, String> {
+    const EXAMPLE: &str = "expected an http or https URL such as https://chronova.dev/api/v1";
+
+    let url = value.trim();
+    let Some((_, rest)) = url.split_once("://") else {
+        return Err(format!("missing scheme; {}", EXAMPLE));
+    };
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("{}; {}", e, EXAMPLE))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!(
+            "unsupported scheme '{}'; {}",
+            parsed.scheme(),
+            EXAMPLE
+        ));
+    }
+    // The URL parser reads `https:///api` as host `api`; treat it as missing
+    if rest.starts_with('/') || parsed.host_str().is_none_or(str::is_empty) {
+        return Err(format!("missing host; {}", EXAMPLE));
+    }
+    Ok(url.to_string())
+}
+
+/// Read the entity for `--entity-from-stdin`: the first line, minus its line
+/// break, without waiting for EOF. Fails when nothing was piped in.
+pub fn read_entity(mut reader: impl std::io::BufRead) -> std::io::Result<String> {
+    let mut input = String::new();
+    reader.read_line(&mut input)?;
+    let entity = input.trim_end_matches(['\r', '\n']);
+    if entity.trim().is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "no entity path on stdin",
+        ));
+    }
+    Ok(entity.to_string())
+}
+
+/// Expand an `@path` value of `--entity` (or its `--file` alias) into the
+/// lines of the file at `path`.
+///
+/// The first non-empty line becomes the entity and any further lines extra
+/// arguments, each taken verbatim, so paths with spaces or shell
+/// metacharacters need no quoting, e.g. `--entity @/tmp/entity.args`.
+/// `@@value` passes a literal `@value`, and `@path` is kept as-is when no such
+/// file exists. An argfile without a non-empty line is an error. `@` arguments
+/// anywhere else are left alone.
+pub fn expand_argfiles<I>(args: I) -> std::io::Result<Vec<String>>
+where
+    I: IntoIterator<Item = String>,
+{
+    const ENTITY_FLAGS: [&str; 2] = ["--entity", "--file"];
+
+    let mut expanded: Vec<String> = Vec::new();
+
+    for arg in args {
+        let inline = ENTITY_FLAGS.iter().find_map(|flag| {
+            arg.strip_prefix(flag)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(|value| (&arg[..flag.len() + 1], value))
+        });
+        let (prefix, value) = match inline {
+            Some(split) => split,
+            None if expanded
+                .last()
+                .is_some_and(|prev| ENTITY_FLAGS.contains(&prev.as_str())) =>
+            {
+                ("", arg.as_str())
+            }
+            None => {
+                expanded.push(arg);
+                continue;
+            }
+        };
+
+        if let Some(literal) = value.strip_prefix("@@") {
+            expanded.push(format!("{}@{}", prefix, literal));
+        } else if let Some(path) = value
+            .strip_prefix('@')
+            .filter(|p| std::path::Path::new(p).is_file())
+        {
+            let content = std::fs::read_to_string(path)?;
+            let mut lines = content
+                .lines()
+                .map(|line| line.trim_end_matches('\r'))
+                .filter(|line| !line.trim().is_empty());
+            let entity = lines.next().ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("argfile is empty: {}", path),
+                )
+            })?;
+            expanded.push(format!("{}{}", prefix, entity));
+            expanded.extend(lines.map(String::from));
+        } else {
+            expanded.push(arg);
+        }
+    }
+
+    Ok(expanded)
+}
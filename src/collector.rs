@@ -36,10 +36,13 @@
 //! }
 //! ```
 
-use git2::Repository;
+use crate::project_cache::ProjectCache;
+use git2::{Repository, RepositoryOpenFlags};
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Information about a detected project.
 ///
@@ -69,16 +72,78 @@ pub struct GitInfo {
     pub commit_author: Option<String>,
     /// The commit message of the current commit.
     pub commit_message: Option<String>,
-    /// The URL of the 'origin' remote, with credentials stripped.
+    /// The URL of the preferred remote (`origin` by default), with credentials stripped.
     pub repository_url: Option<String>,
 }
 
+/// Files or directories whose presence marks a project root, in priority order.
+/// `project_markers` adds to these.
+pub const PROJECT_MARKERS: &[&str] = &[
+    ".git",
+    ".wakatime-project",
+    "package.json",
+    "Cargo.toml",
+    "pyproject.toml",
+    "go.mod",
+];
+
+/// Remote used for the repository URL unless configured otherwise
+pub const DEFAULT_REMOTE: &str = "origin";
+
+/// Default cap on how much of a file content-based detectors may read
+pub const DEFAULT_MAX_DETECT_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Process names of editors, for detecting the editor when no plugin is given
+const EDITOR_PROCESSES: &[(&str, &str)] = &[
+    ("code", "VS Code"),
+    ("code-insiders", "VS Code"),
+    ("codium", "VSCodium"),
+    ("cursor", "Cursor"),
+    ("nvim", "Neovim"),
+    ("vim", "Vim"),
+    ("gvim", "Vim"),
+    ("emacs", "Emacs"),
+    ("idea", "IntelliJ IDEA"),
+    ("pycharm", "PyCharm"),
+    ("goland", "GoLand"),
+    ("webstorm", "WebStorm"),
+    ("clion", "CLion"),
+    ("rustrover", "RustRover"),
+    ("subl", "Sublime Text"),
+    ("sublime_text", "Sublime Text"),
+    ("zed", "Zed"),
+    ("hx", "Helix"),
+    ("helix", "Helix"),
+    ("kate", "Kate"),
+    ("nano", "Nano"),
+];
+
+/// Editors usually run the CLI through a shell or two, so a few ancestors are checked
+const MAX_PROCESS_ANCESTORS: usize = 4;
+
+/// Returns the names of this process's ancestors, nearest first
+pub type ProcessNamesProvider = fn() -> Vec<String>;
+
 /// Data collector for detecting project and git information.
 ///
 /// The `DataCollector` provides methods to analyze file paths and extract
 /// relevant project and git metadata. It supports both regular repositories
 /// and Git worktrees.
-pub struct DataCollector;
+#[derive(Debug, Clone)]
+pub struct DataCollector {
+    /// Files larger than this skip content-based heuristics entirely
+    max_detect_file_bytes: u64,
+    /// Persistent marker-based project results shared across invocations
+    project_cache: Option<ProjectCache>,
+    /// Source of ancestor process names for editor detection
+    process_names: ProcessNamesProvider,
+    /// Remote whose URL becomes the repository URL
+    preferred_remote: String,
+    /// [`PROJECT_MARKERS`] followed by any configured ones
+    project_markers: Vec<String>,
+    /// Project detection doesn't look above this directory for files inside it
+    search_ceiling: Option<PathBuf>,
+}
 
 impl Default for DataCollector {
     fn default() -> Self {
@@ -88,24 +153,208 @@ impl Default for DataCollector {
 
 impl DataCollector {
     pub fn new() -> Self {
-        Self
+        Self {
+            max_detect_file_bytes: DEFAULT_MAX_DETECT_FILE_BYTES,
+            project_cache: None,
+            process_names: ancestor_process_names,
+            preferred_remote: DEFAULT_REMOTE.to_string(),
+            project_markers: PROJECT_MARKERS.iter().map(|m| m.to_string()).collect(),
+            search_ceiling: dirs::home_dir(),
+        }
+    }
+
+    /// Stop project detection at `ceiling` rather than the home directory.
+    /// Files outside the ceiling are searched all the way up as before.
+    pub fn with_search_ceiling(mut self, ceiling: &str) -> Self {
+        self.search_ceiling = expand_home(ceiling);
+        self
+    }
+
+    /// The search ceiling, if `path` lies inside it
+    fn ceiling_for(&self, path: &Path) -> Option<&Path> {
+        self.search_ceiling
+            .as_deref()
+            .filter(|ceiling| path.starts_with(ceiling))
+    }
+
+    /// [`Repository::discover`] without looking above the search ceiling
+    fn discover_repository(&self, path: &Path) -> Option<Repository> {
+        // libgit2 never searches a ceiling directory itself, so give it the parent
+        let ceiling_dirs = self.ceiling_for(path).and_then(Path::parent);
+        Repository::open_ext(path, RepositoryOpenFlags::empty(), ceiling_dirs).ok()
+    }
+
+    /// Also treat `markers` as project markers, after the built-in ones
+    pub fn with_project_markers(mut self, markers: &[String]) -> Self {
+        for marker in markers {
+            if !self.project_markers.contains(marker) {
+                self.project_markers.push(marker.clone());
+            }
+        }
+        self
+    }
+
+    /// Take the repository URL from remote `name`, falling back to the first
+    /// remote when the repository has no such remote
+    pub fn with_preferred_remote(mut self, name: impl Into<String>) -> Self {
+        self.preferred_remote = name.into();
+        self
+    }
+
+    /// Read ancestor process names from `provider` instead of the OS
+    pub fn with_process_names(mut self, provider: ProcessNamesProvider) -> Self {
+        self.process_names = provider;
+        self
+    }
+
+    /// Best-effort editor name from the nearest ancestor process that is a
+    /// known editor, for heartbeats sent without `--plugin`
+    pub fn detect_editor_from_process(&self) -> Option<String> {
+        let editor = (self.process_names)()
+            .iter()
+            .find_map(|name| editor_from_process_name(name))?;
+        tracing::debug!("Detected editor {} from parent process", editor);
+        Some(editor.to_string())
+    }
+
+    /// Consult and update `cache` for projects found through a marker file
+    pub fn with_project_cache(mut self, cache: ProjectCache) -> Self {
+        self.project_cache = Some(cache);
+        self
+    }
+
+    /// Limit content-based detection to files of at most `bytes`
+    pub fn with_max_detect_file_bytes(mut self, bytes: u64) -> Self {
+        self.max_detect_file_bytes = bytes;
+        self
+    }
+
+    /// Read `path` as text if it is no larger than the detection cap. Oversized
+    /// files return `None` so callers fall back to name-based heuristics; at
+    /// most the cap is read even if the file grows in the meantime.
+    fn read_capped(&self, path: &Path) -> Option<String> {
+        let bytes = self.read_capped_bytes(path)?;
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Raw bytes of `path`, under the same cap as [`read_capped`](Self::read_capped)
+    fn read_capped_bytes(&self, path: &Path) -> Option<Vec<u8>> {
+        use std::io::Read;
+
+        let file = std::fs::File::open(path).ok()?;
+        let metadata = file.metadata().ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        if metadata.len() > self.max_detect_file_bytes {
+            tracing::debug!(
+                "Skipping content detection for {}: larger than {} bytes",
+                path.display(),
+                self.max_detect_file_bytes
+            );
+            return None;
+        }
+
+        let mut bytes = Vec::new();
+        file.take(self.max_detect_file_bytes)
+            .read_to_end(&mut bytes)
+            .ok()?;
+        Some(bytes)
+    }
+
+    /// Total number of lines in the text file at `path`. Binary files (those
+    /// containing a NUL byte) and files over the detection cap yield `None`.
+    pub fn count_lines(&self, path: &Path) -> Option<i32> {
+        let bytes = self.read_capped_bytes(path)?;
+        if bytes.contains(&0) {
+            return None;
+        }
+
+        let newlines = bytes.iter().filter(|&&b| b == b'\n').count();
+        let unterminated = usize::from(bytes.last().is_some_and(|&b| b != b'\n'));
+        i32::try_from(newlines + unterminated).ok()
+    }
+
+    /// Language named by a `#!` interpreter line, e.g. `#!/usr/bin/env python3`
+    fn detect_language_from_shebang(&self, path: &Path) -> Option<String> {
+        let content = self.read_capped(path)?;
+        let line = content.lines().next()?.strip_prefix("#!")?;
+
+        let mut parts = line.split_whitespace();
+        let mut interpreter = parts.next()?.rsplit('/').next()?;
+        if interpreter == "env" {
+            interpreter = parts.find(|arg| !arg.starts_with('-'))?;
+        }
+        let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+        SHEBANG_MAP.get(interpreter).cloned()
+    }
+
+    /// Run project, git and language detection concurrently under one time budget.
+    ///
+    /// Detection hits the filesystem and libgit2, so each part runs on the blocking
+    /// pool. Whatever finishes before the deadline is returned; a part that times
+    /// out yields `None` without discarding the others.
+    pub async fn detect_all(
+        &self,
+        entity_path: &str,
+        budget: Duration,
+    ) -> (Option<ProjectInfo>, Option<GitInfo>, Option<String>) {
+        let project_entity = entity_path.to_string();
+        let project_collector = self.clone();
+        let project = async move {
+            tokio::task::spawn_blocking(move || {
+                let handle = tokio::runtime::Handle::current();
+                handle.block_on(project_collector.detect_project(&project_entity))
+            })
+            .await
+            .ok()
+            .flatten()
+        };
+
+        let git_entity = entity_path.to_string();
+        let git_collector = self.clone();
+        let git = async move {
+            tokio::task::spawn_blocking(move || {
+                let handle = tokio::runtime::Handle::current();
+                handle.block_on(git_collector.detect_git_info(&git_entity))
+            })
+            .await
+            .ok()
+            .flatten()
+        };
+
+        join_with_deadline(project, git, self.detect_language(entity_path), budget).await
     }
 
     pub async fn detect_project(&self, entity_path: &str) -> Option<ProjectInfo> {
-        let path = Path::new(entity_path);
+        let entity_path = normalize_entity_path(entity_path);
+        let path = Path::new(&entity_path);
+
+        // 0) A cached result whose marker file hasn't changed since
+        let cache_dir = path.parent().filter(|_| self.project_cache.is_some());
+        if let (Some(cache), Some(dir)) = (&self.project_cache, cache_dir) {
+            if let Some(project) = cache.get(dir) {
+                return Some(project);
+            }
+        }
 
         // 1) Prefer explicit project markers (git, Cargo.toml, package.json, etc.)
         // But respect worktree boundaries - if we're in a worktree, use the main repo path.
-        if let Some(root) = self.find_project_root(path) {
+        if let Some((marker_root, marker)) = self.find_project_marker(path) {
             // Check if we're in a worktree and resolve to main repo if so
-            let root = self.get_project_root_respecting_worktree(&root);
+            let root = self.get_project_root_respecting_worktree(&marker_root);
             let name = self.extract_project_name(&root);
-            return Some(ProjectInfo { name, root });
+            let project = ProjectInfo { name, root };
+            if let (Some(cache), Some(dir)) = (&self.project_cache, cache_dir) {
+                cache.insert(dir, &project, &marker_root.join(marker));
+            }
+            return Some(project);
         }
 
-        // 2) Try to discover a git repository root via libgit2; Repository::discover climbs parents.
+        // 2) Try to discover a git repository root via libgit2, climbing parents up to the ceiling.
         // Use get_project_root_respecting_worktree to return the main repo path when in a worktree.
-        if Repository::discover(path).is_ok() {
+        if self.discover_repository(path).is_some() {
             let root = self.get_project_root_respecting_worktree(path);
             let name = self.extract_project_name(&root);
             return Some(ProjectInfo { name, root });
@@ -124,14 +373,14 @@ impl DataCollector {
             "build",
             "tests",
         ];
+        let ceiling = self.ceiling_for(path);
         let mut current = path.parent();
         while let Some(dir) = current {
             // Prefer explicit markers if present on this ancestor
-            if dir.join("package.json").exists()
-                || dir.join("Cargo.toml").exists()
-                || dir.join("pyproject.toml").exists()
-                || dir.join(".wakatime-project").exists()
-                || dir.join(".git").exists()
+            if self
+                .project_markers
+                .iter()
+                .any(|marker| dir.join(marker).exists())
             {
                 let root = dir.to_path_buf();
                 let name = self.extract_project_name(&root);
@@ -140,7 +389,7 @@ impl DataCollector {
 
             // If the directory name is a common code-folder, skip upwards
             if let Some(dir_name) = dir.file_name().and_then(|n| n.to_str()) {
-                if ignored_dirs.iter().any(|s| s == &dir_name) {
+                if ignored_dirs.iter().any(|s| s == &dir_name) && Some(dir) != ceiling {
                     current = dir.parent();
                     continue;
                 } else {
@@ -160,7 +409,7 @@ impl DataCollector {
 
             // If the immediate parent is a common code-folder, try its parent instead
             if let Some(parent_name) = root.file_name().and_then(|n| n.to_str()) {
-                if ignored_dirs.iter().any(|s| s == &parent_name) {
+                if ignored_dirs.iter().any(|s| s == &parent_name) && Some(parent) != ceiling {
                     if let Some(grand) = root.parent() {
                         let grand_root = grand.to_path_buf();
                         let name = self.extract_project_name(&grand_root);
@@ -180,7 +429,8 @@ impl DataCollector {
     }
 
     pub async fn detect_git_info(&self, entity_path: &str) -> Option<GitInfo> {
-        let path = Path::new(entity_path);
+        let entity_path = normalize_entity_path(entity_path);
+        let path = Path::new(&entity_path);
 
         // Resolve the main repository path, respecting worktree boundaries.
         // This ensures commit info comes from the main repo while branch detection
@@ -231,33 +481,15 @@ impl DataCollector {
             .as_ref()
             .and_then(|c| c.message().ok().map(|s| s.to_string()));
 
-        let repository_url = repo.find_remote("origin").ok().and_then(|r| {
-            r.url().ok().map(|s| {
-                // sanitize remote URL to remove sensitive userinfo (user:pass or token before '@')
-                let raw = s.to_string();
-
-                // If scheme exists (e.g., "https://"), strip userinfo from the authority portion only
-                if let Some(scheme_sep) = raw.find("://") {
-                    let (scheme, rest) = raw.split_at(scheme_sep + 3); // include "://"
-                                                                       // isolate authority (up to first '/') and path
-                    let auth_end = rest.find('/').unwrap_or(rest.len());
-                    let (authority, path) = rest.split_at(auth_end);
-                    if let Some(at_pos) = authority.find('@') {
-                        // remove userinfo (up to and including '@') from authority
-                        let without_user = &authority[at_pos + 1..];
-                        return format!("{}{}{}", scheme, without_user, path);
-                    }
-                    return raw;
-                }
-
-                // No scheme: handle scp-like "user@host:owner/repo.git" or "user@host/..."
-                if let Some(at_pos) = raw.find('@') {
-                    return raw[at_pos + 1..].to_string();
-                }
-
-                raw
-            })
+        // The preferred remote, else the first one the repository has
+        let remote = repo.find_remote(&self.preferred_remote).ok().or_else(|| {
+            let names = repo.remotes().ok()?;
+            names
+                .iter()
+                .filter_map(|name| name.ok().flatten())
+                .find_map(|name| repo.find_remote(name).ok())
         });
+        let repository_url = remote.and_then(|r| r.url().ok().map(sanitize_remote_url));
 
         Some(GitInfo {
             branch,
@@ -273,7 +505,8 @@ impl DataCollector {
         // 1) Try special filename matches (Dockerfile, Makefile, .gitignore, etc.)
         // 2) Try multi-part extensions first (e.g., .tar.gz, .log.gz)
         // 3) Try single final extension (including dot) and dot-only filenames (e.g., ".env")
-        let entity = entity_path;
+        let normalized = normalize_entity_path(entity_path);
+        let entity = normalized.as_str();
         let lower = entity.to_lowercase();
 
         // basename (filename)
@@ -282,9 +515,9 @@ impl DataCollector {
             None => entity,
         };
 
-        // 1) Exact filename matches (case-insensitive)
-        if let Some(lang) = FILENAME_MAP.get(&filename.to_lowercase().as_str()) {
-            return Some(lang.clone());
+        // 1) Exact filename matches and filename variants (case-insensitive)
+        if let Some(lang) = language_from_filename(&filename.to_lowercase()) {
+            return Some(lang);
         }
 
         // If filename starts with a dot and has no other dots, treat it as an extension-only entry (e.g., ".env")
@@ -316,22 +549,27 @@ impl DataCollector {
             }
         }
 
-        None
+        // 4) Content sniffing, skipped for files over the detection cap
+        self.detect_language_from_shebang(Path::new(entity))
     }
 
-    fn find_project_root(&self, path: &Path) -> Option<PathBuf> {
+    /// Walk up from `path` and return the first directory containing a project
+    /// marker, together with the marker that matched.
+    fn find_project_marker(&self, path: &Path) -> Option<(PathBuf, &str)> {
+        let ceiling = self.ceiling_for(path);
         let mut current = path.parent()?;
 
         while current.parent().is_some() {
             // Check for common project markers
-            if current.join(".git").exists()
-                || current.join(".wakatime-project").exists()
-                || current.join("package.json").exists()
-                || current.join("Cargo.toml").exists()
-                || current.join("pyproject.toml").exists()
-                || current.join("go.mod").exists()
+            if let Some(marker) = self
+                .project_markers
+                .iter()
+                .find(|marker| current.join(marker).exists())
             {
-                return Some(current.to_path_buf());
+                return Some((current.to_path_buf(), marker));
+            }
+            if Some(current) == ceiling {
+                break;
             }
             current = current.parent()?;
         }
@@ -339,6 +577,124 @@ impl DataCollector {
         None
     }
 
+    /// Whether `entity_path` sits under a project marker or a git repository
+    pub fn has_project_marker(&self, entity_path: &str) -> bool {
+        let entity_path = normalize_entity_path(entity_path);
+        let path = Path::new(&entity_path);
+        self.find_project_marker(path).is_some() || self.discover_repository(path).is_some()
+    }
+
+    /// Name a project from the `depth`-th directory below `projects_root`.
+    ///
+    /// With `projects_root = ~/code` and `depth = 2`, `~/code/acme/widget/src/lib.rs`
+    /// yields `widget`. Returns `None` when the entity is outside the root or not
+    /// nested deeply enough for that segment to be a directory.
+    pub fn project_from_path_depth(
+        &self,
+        entity_path: &str,
+        projects_root: &str,
+        depth: usize,
+    ) -> Option<String> {
+        if depth == 0 {
+            return None;
+        }
+
+        let root = expand_home(projects_root)?;
+        let entity_path = normalize_entity_path(entity_path);
+        let relative = Path::new(&entity_path).strip_prefix(&root).ok()?;
+        let segments: Vec<_> = relative.components().collect();
+
+        // The last segment is the file itself, never a project directory
+        if segments.len() <= depth {
+            return None;
+        }
+
+        segments[depth - 1]
+            .as_os_str()
+            .to_str()
+            .map(|s| s.to_string())
+    }
+
+    /// Explain how project, git and language detection resolve for `entity_path`.
+    ///
+    /// Each line describes which rule produced a field, so `--dry-run --verbose`
+    /// can show why a heartbeat got the project or language it did.
+    pub fn detection_diagnostics(&self, entity_path: &str) -> Vec<String> {
+        let normalized = normalize_entity_path(entity_path);
+        let entity_path = normalized.as_str();
+        let path = Path::new(entity_path);
+        let mut lines = Vec::new();
+
+        match self.find_project_marker(path) {
+            Some((root, marker)) => {
+                lines.push(format!(
+                    "project: matched marker {} in {}",
+                    marker,
+                    root.display()
+                ));
+                let resolved = self.get_project_root_respecting_worktree(&root);
+                if resolved != root {
+                    lines.push(format!(
+                        "project: worktree resolved to main repository {}",
+                        resolved.display()
+                    ));
+                }
+                lines.push(format!(
+                    "project: name \"{}\"",
+                    self.extract_project_name(&resolved)
+                ));
+            }
+            None => lines.push(format!(
+                "project: no marker ({}) found, using directory heuristics",
+                self.project_markers.join(", ")
+            )),
+        }
+
+        match Repository::discover(path) {
+            Ok(repo) => {
+                let location = repo.workdir().unwrap_or_else(|| repo.path());
+                let worktree = if repo.is_worktree() {
+                    " (worktree)"
+                } else {
+                    ""
+                };
+                lines.push(format!(
+                    "git: discovered repository at {}{}",
+                    location.display(),
+                    worktree
+                ));
+            }
+            Err(e) => lines.push(format!("git: discovery failed: {}", e.message())),
+        }
+
+        let filename = entity_path.rsplit('/').next().unwrap_or(entity_path);
+        let lower = filename.to_lowercase();
+        if let Some(lang) = language_from_filename(&lower) {
+            lines.push(format!(
+                "language: filename {} mapped to {}",
+                filename, lang
+            ));
+        } else if let Some((ext, lang)) = EXTENSION_MAP
+            .iter()
+            .filter(|(ext, _)| lower.ends_with(**ext))
+            .max_by_key(|(ext, _)| ext.len())
+        {
+            lines.push(format!("language: extension {} mapped to {}", ext, lang));
+        } else if let Some(pos) = filename.rfind('.') {
+            lines.push(format!(
+                "language: no mapping for extension {}",
+                &lower[pos..]
+            ));
+        } else {
+            lines.push(format!(
+                "language: {} has no extension and no filename mapping",
+                filename
+            ));
+        }
+
+        lines
+    }
+
     #[allow(dead_code)]
     fn find_git_root(&self, path: &Path) -> Option<PathBuf> {
         let mut current = path.parent()?;
@@ -355,12 +711,12 @@ impl DataCollector {
 
     fn extract_project_name(&self, root: &Path) -> String {
         // Try to get name from .wakatime-project file
-        if let Ok(content) = std::fs::read_to_string(root.join(".wakatime-project")) {
+        if let Some(content) = self.read_capped(&root.join(".wakatime-project")) {
             return content.trim().to_string();
         }
 
         // Try to get name from package.json
-        if let Ok(content) = std::fs::read_to_string(root.join("package.json")) {
+        if let Some(content) = self.read_capped(&root.join("package.json")) {
             if let Ok(package) = serde_json::from_str::<serde_json::Value>(&content) {
                 if let Some(name) = package.get("name").and_then(|n| n.as_str()) {
                     return name.to_string();
@@ -369,7 +725,7 @@ impl DataCollector {
         }
 
         // Try to get name from Cargo.toml
-        if let Ok(content) = std::fs::read_to_string(root.join("Cargo.toml")) {
+        if let Some(content) = self.read_capped(&root.join("Cargo.toml")) {
             for line in content.lines() {
                 if line.trim().starts_with("name =") {
                     if let Some(name) = line.split('=').nth(1) {
@@ -438,6 +794,16 @@ impl DataCollector {
         Some(main_repo_root.to_path_buf())
     }
 
+    /// Working directory of the git worktree `path` is in, or `None` when
+    /// `path` is not inside a worktree
+    pub fn worktree_root(&self, path: &Path) -> Option<PathBuf> {
+        let repo = Repository::discover(path).ok()?;
+        if !repo.is_worktree() {
+            return None;
+        }
+        repo.workdir().map(Path::to_path_buf)
+    }
+
     /// Gets the project root path, respecting worktree boundaries.
     ///
     /// When inside a git worktree, this returns the main repository's root path
@@ -468,7 +834,95 @@ impl DataCollector {
     }
 }
 
+/// `path` with a leading `~` or `~/` resolved against the home directory
+fn expand_home(path: &str) -> Option<PathBuf> {
+    match path.strip_prefix('~') {
+        Some("") => dirs::home_dir(),
+        Some(rest) if rest.starts_with('/') => Some(dirs::home_dir()?.join(&rest[1..])),
+        _ => Some(PathBuf::from(path)),
+    }
+}
+
+/// Path used for detection of `entity`: Windows long-path (`\\?\`) and device
+/// (`\\.\`) prefixes are stripped and backslashes become `/`. The reported
+/// entity keeps its original form; this only feeds the detectors.
+///
+/// Backslashes are legal in Unix file names, so outside Windows they are only
+/// rewritten for paths that look like Windows paths (drive letter or UNC).
+pub fn normalize_entity_path(entity: &str) -> String {
+    let stripped = if let Some(rest) = entity.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", rest)
+    } else if let Some(rest) = entity
+        .strip_prefix(r"\\?\")
+        .or_else(|| entity.strip_prefix(r"\\.\"))
+    {
+        rest.to_string()
+    } else {
+        entity.to_string()
+    };
+
+    let bytes = stripped.as_bytes();
+    let has_drive = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/');
+    let is_unc = stripped.starts_with(r"\\");
+
+    if cfg!(windows) || has_drive || is_unc {
+        stripped.replace('\\', "/")
+    } else {
+        stripped
+    }
+}
+
+/// Await the three detections concurrently, dropping any that miss the shared deadline.
+async fn join_with_deadline<P, G, L>(
+    project: P,
+    git: G,
+    language: L,
+    budget: Duration,
+) -> (Option<ProjectInfo>, Option<GitInfo>, Option<String>)
+where
+    P: Future<Output = Option<ProjectInfo>>,
+    G: Future<Output = Option<GitInfo>>,
+    L: Future<Output = Option<String>>,
+{
+    let deadline = tokio::time::Instant::now() + budget;
+    let (project, git, language) = tokio::join!(
+        tokio::time::timeout_at(deadline, project),
+        tokio::time::timeout_at(deadline, git),
+        tokio::time::timeout_at(deadline, language),
+    );
+
+    if git.is_err() {
+        tracing::debug!("Git detection exceeded {:?} budget", budget);
+    }
+    if project.is_err() {
+        tracing::debug!("Project detection exceeded {:?} budget", budget);
+    }
+
+    (
+        project.ok().flatten(),
+        git.ok().flatten(),
+        language.ok().flatten(),
+    )
+}
+
 lazy_static! {
+    // Interpreter names from `#!` lines, with any version suffix stripped
+    static ref SHEBANG_MAP: HashMap<&'static str, String> = {
+        let mut m = HashMap::new();
+        m.insert("python", "Python".to_string());
+        m.insert("node", "JavaScript".to_string());
+        m.insert("ruby", "Ruby".to_string());
+        m.insert("perl", "Perl".to_string());
+        m.insert("php", "PHP".to_string());
+        m.insert("lua", "Lua".to_string());
+        m.insert("bash", "Bash".to_string());
+        m.insert("sh", "Bash".to_string());
+        m
+    };
+
     // Map keys mirror the heartbeat-detection LANGUAGE_MAPPING.ts which uses leading dots for extensions
     static ref EXTENSION_MAP: HashMap<&'static str, String> = {
         let mut m = HashMap::new();
@@ -582,8 +1036,116 @@ lazy_static! {
         m.insert("procfile", "Config".to_string());
         m.insert("package", "JSON".to_string());
         m.insert("dockerfile.dev", "Dockerfile".to_string());
+        m.insert("containerfile", "Dockerfile".to_string());
+        m.insert("makefile.inc", "Makefile".to_string());
+        m.insert("gnumakefile", "Makefile".to_string());
+        m.insert("cmakelists.txt", "CMake".to_string());
+        m.insert("requirements.txt", "Pip Requirements".to_string());
+        m.insert("requirements-dev.txt", "Pip Requirements".to_string());
+        m.insert("go.mod", "Go Module".to_string());
+        m.insert("go.sum", "Go Checksums".to_string());
+        m.insert("cargo.toml", "TOML".to_string());
+        m.insert("cargo.lock", "TOML".to_string());
+        m.insert("pipfile", "TOML".to_string());
+        m.insert("vagrantfile", "Ruby".to_string());
+        m.insert("jenkinsfile", "Groovy".to_string());
         m
     };
+
+    /// Shell startup and rc dotfiles, matched by exact basename
+    static ref SHELL_RC_FILES: HashSet<&'static str> = [
+        ".bashrc",
+        ".bash_profile",
+        ".bash_login",
+        ".bash_logout",
+        ".bash_aliases",
+        ".profile",
+        ".zshrc",
+        ".zshenv",
+        ".zprofile",
+        ".zlogin",
+        ".zlogout",
+        ".kshrc",
+        ".mkshrc",
+    ]
+    .into_iter()
+    .collect();
+}
+
+/// Remove sensitive userinfo (user:pass or a token before '@') from a remote URL
+fn sanitize_remote_url(raw: &str) -> String {
+    // If scheme exists (e.g., "https://"), strip userinfo from the authority portion only
+    if let Some(scheme_sep) = raw.find("://") {
+        let (scheme, rest) = raw.split_at(scheme_sep + 3); // include "://"
+                                                           // isolate authority (up to first '/') and path
+        let auth_end = rest.find('/').unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(auth_end);
+        if let Some(at_pos) = authority.find('@') {
+            // remove userinfo (up to and including '@') from authority
+            let without_user = &authority[at_pos + 1..];
+            return format!("{}{}{}", scheme, without_user, path);
+        }
+        return raw.to_string();
+    }
+
+    // No scheme: handle scp-like "user@host:owner/repo.git" or "user@host/..."
+    if let Some(at_pos) = raw.find('@') {
+        return raw[at_pos + 1..].to_string();
+    }
+
+    raw.to_string()
+}
+
+/// Editor for a process name such as `code`, `nvim.exe` or `idea64`
+fn editor_from_process_name(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    let name = name.strip_suffix(".sh").unwrap_or(name);
+    let name = name.strip_suffix("64").unwrap_or(name);
+    EDITOR_PROCESSES
+        .iter()
+        .find(|(process, _)| *process == name)
+        .map(|(_, editor)| *editor)
+}
+
+/// Names of up to `MAX_PROCESS_ANCESTORS` ancestors of the current process
+fn ancestor_process_names() -> Vec<String> {
+    use sysinfo::{ProcessesToUpdate, System};
+
+    let mut names = Vec::new();
+    let Ok(mut pid) = sysinfo::get_current_pid() else {
+        return names;
+    };
+
+    let mut system = System::new();
+    for _ in 0..MAX_PROCESS_ANCESTORS {
+        system.refresh_processes(ProcessesToUpdate::Some(&[pid]), false);
+        let Some(parent) = system.process(pid).and_then(|p| p.parent()) else {
+            break;
+        };
+        system.refresh_processes(ProcessesToUpdate::Some(&[parent]), false);
+        let Some(process) = system.process(parent) else {
+            break;
+        };
+        names.push(process.name().to_string_lossy().into_owned());
+        pid = parent;
+    }
+    names
+}
+
+/// Look up a lowercased basename in the filename map, then fall back to
+/// `Dockerfile.*` / `*.dockerfile` variants and shell rc dotfiles.
+fn language_from_filename(lower: &str) -> Option<String> {
+    if let Some(lang) = FILENAME_MAP.get(lower) {
+        return Some(lang.clone());
+    }
+    if lower.starts_with("dockerfile.") || lower.ends_with(".dockerfile") {
+        return Some("Dockerfile".to_string());
+    }
+    if SHELL_RC_FILES.contains(lower) {
+        return Some("Shell".to_string());
+    }
+    None
 }
 
 #[cfg(test)]
@@ -610,6 +1172,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_editor_process_names() {
+        assert_eq!(editor_from_process_name("code"), Some("VS Code"));
+        assert_eq!(
+            editor_from_process_name("idea64.exe"),
+            Some("IntelliJ IDEA")
+        );
+        assert_eq!(editor_from_process_name("NVIM"), Some("Neovim"));
+        assert_eq!(editor_from_process_name("bash"), None);
+
+        let collector = DataCollector::new()
+            .with_process_names(|| vec!["zsh".to_string(), "emacs".to_string()]);
+        assert_eq!(
+            collector.detect_editor_from_process().as_deref(),
+            Some("Emacs")
+        );
+    }
+
+    #[test]
+    fn test_compound_and_dotfile_filenames() {
+        let collector = DataCollector::new();
+        let detect = |path: &str| tokio_test::block_on(collector.detect_language(path));
+
+        assert_eq!(detect("/src/CMakeLists.txt"), Some("CMake".to_string()));
+        assert_eq!(detect("/home/me/.zshrc"), Some("Shell".to_string()));
+        assert_eq!(
+            detect("/app/requirements.txt"),
+            Some("Pip Requirements".to_string())
+        );
+        assert_eq!(
+            detect("/app/Dockerfile.prod"),
+            Some("Dockerfile".to_string())
+        );
+        assert_eq!(detect("/app/MAKEFILE.INC"), Some("Makefile".to_string()));
+        assert_eq!(detect("/app/notes.txt"), Some("Plain Text".to_string()));
+    }
+
     #[test]
     fn test_project_detection() {
         let temp_dir = TempDir::new().unwrap();
@@ -635,6 +1234,42 @@ mod tests {
         assert_eq!(project_info.root, project_dir);
     }
 
+    #[test]
+    fn test_project_cache_reused_until_marker_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("cached-project");
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        let manifest = project_dir.join("Cargo.toml");
+        fs::write(&manifest, "[package]\nname = \"real-name\"\n").unwrap();
+        let entity = project_dir.join("src").join("main.rs");
+        fs::write(&entity, "fn main() {}\n").unwrap();
+        let entity = entity.to_str().unwrap();
+
+        let cache_path = temp_dir.path().join("project_cache.json");
+        let collector =
+            DataCollector::new().with_project_cache(ProjectCache::new(cache_path.clone()));
+        let first = tokio_test::block_on(collector.detect_project(entity)).unwrap();
+        assert_eq!(first.name, "real-name");
+
+        // Tamper with the cached name: a second run that still reports it never walked the tree
+        let cached = fs::read_to_string(&cache_path).unwrap();
+        fs::write(&cache_path, cached.replace("real-name", "from-cache")).unwrap();
+        let second = tokio_test::block_on(collector.detect_project(entity)).unwrap();
+        assert_eq!(second.name, "from-cache");
+        assert_eq!(second.root, project_dir);
+
+        // Touching the marker invalidates the entry
+        let later = fs::metadata(&manifest).unwrap().modified().unwrap() + Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&manifest)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let third = tokio_test::block_on(collector.detect_project(entity)).unwrap();
+        assert_eq!(third.name, "real-name");
+    }
+
     #[test]
     fn test_project_detection_fallback_to_parent_name() {
         let temp_dir = TempDir::new().unwrap();
@@ -651,6 +1286,256 @@ mod tests {
         assert_eq!(project_info.root, project_dir);
     }
 
+    #[test]
+    fn test_count_lines_respects_cap_and_skips_binary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let collector = DataCollector::new().with_max_detect_file_bytes(64);
+
+        let text = temp_dir.path().join("main.rs");
+        std::fs::write(&text, "fn main() {\n    println!(\"hi\");\n}").unwrap();
+        assert_eq!(collector.count_lines(&text), Some(3));
+
+        let empty = temp_dir.path().join("empty.rs");
+        std::fs::write(&empty, "").unwrap();
+        assert_eq!(collector.count_lines(&empty), Some(0));
+
+        let binary = temp_dir.path().join("image.png");
+        std::fs::write(&binary, b"\x89PNG\r\n\x00\x00").unwrap();
+        assert_eq!(collector.count_lines(&binary), None);
+
+        let large = temp_dir.path().join("large.rs");
+        std::fs::write(&large, "x\n".repeat(100)).unwrap();
+        assert_eq!(collector.count_lines(&large), None);
+        assert_eq!(collector.count_lines(temp_dir.path()), None);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_file_uses_extension_only() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let collector = DataCollector::new().with_max_detect_file_bytes(64);
+
+        let script = temp_dir.path().join("deploy");
+        std::fs::write(&script, "#!/usr/bin/env python3\nprint('hi')\n").unwrap();
+        assert_eq!(
+            collector.detect_language(script.to_str().unwrap()).await,
+            Some("Python".to_string())
+        );
+
+        // Same shebang, but past the cap: content sniffing is skipped
+        let large_script = temp_dir.path().join("generated");
+        let mut content = "#!/usr/bin/env python3\n".to_string();
+        content.push_str(&"x = 1\n".repeat(100));
+        std::fs::write(&large_script, &content).unwrap();
+        assert_eq!(
+            collector
+                .detect_language(large_script.to_str().unwrap())
+                .await,
+            None
+        );
+
+        // Extension mapping still applies to oversized files
+        let large_source = temp_dir.path().join("generated.py");
+        std::fs::write(&large_source, &content).unwrap();
+        assert_eq!(
+            collector
+                .detect_language(large_source.to_str().unwrap())
+                .await,
+            Some("Python".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detection_stops_at_search_ceiling() {
+        let temp_dir = TempDir::new().unwrap();
+        // A stray marker above the home directory must not claim files inside it
+        fs::write(temp_dir.path().join("package.json"), "{}").unwrap();
+        let home = temp_dir.path().join("alice");
+        fs::create_dir_all(home.join("src")).unwrap();
+
+        let unbounded = DataCollector::new().with_search_ceiling("/nonexistent");
+        let notes = home.join("notes.txt");
+        let project = unbounded
+            .detect_project(notes.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(project.root, temp_dir.path());
+
+        let collector = DataCollector::new().with_search_ceiling(home.to_str().unwrap());
+        let project = collector
+            .detect_project(notes.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(project.root, home);
+        assert_eq!(project.name, "alice");
+
+        // Code folders are skipped upwards, but never past the ceiling
+        let source = home.join("src").join("main.rs");
+        let project = collector
+            .detect_project(source.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(project.root, home);
+    }
+
+    #[tokio::test]
+    async fn test_windows_paths() {
+        let collector = DataCollector::new();
+
+        for entity in [r"C:\Users\me\proj\x.rs", r"\\?\C:\Users\me\proj\src\x.rs"] {
+            assert_eq!(
+                collector.detect_language(entity).await,
+                Some("Rust".to_string()),
+                "{}",
+                entity
+            );
+            let project = collector.detect_project(entity).await.unwrap();
+            assert_eq!(project.name, "proj", "{}", entity);
+        }
+
+        assert_eq!(
+            normalize_entity_path(r"\\?\UNC\server\share\x.rs"),
+            "//server/share/x.rs"
+        );
+        assert_eq!(
+            normalize_entity_path("/home/me/a\\b.rs"),
+            "/home/me/a\\b.rs"
+        );
+    }
+
+    #[test]
+    fn test_project_from_path_depth() {
+        let collector = DataCollector::new();
+        let home = dirs::home_dir().unwrap();
+        let entity = home.join("code/acme/widget/src/deep/nested/lib.rs");
+        let entity = entity.to_str().unwrap();
+
+        assert_eq!(
+            collector.project_from_path_depth(entity, "~/code", 2),
+            Some("widget".to_string())
+        );
+        assert_eq!(
+            collector.project_from_path_depth(entity, "~/code", 1),
+            Some("acme".to_string())
+        );
+        // Too shallow: the segment at that depth is the file name
+        assert_eq!(collector.project_from_path_depth(entity, "~/code", 6), None);
+        // Outside the configured root
+        assert_eq!(
+            collector.project_from_path_depth("/srv/other/widget/lib.rs", "~/code", 2),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detect_all_populates_every_field() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo_dir = temp_dir.path().join("joined");
+        fs::create_dir_all(&repo_dir).unwrap();
+        let repo = Repository::init(&repo_dir).unwrap();
+        let file_path = repo_dir.join("lib.rs");
+        fs::write(&file_path, "// test").unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("lib.rs")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Tester", "tester@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap();
+
+        let collector = DataCollector::new();
+        let (project, git, language) = collector
+            .detect_all(file_path.to_str().unwrap(), Duration::from_secs(5))
+            .await;
+
+        assert_eq!(project.unwrap().name, "joined");
+        assert!(git.unwrap().commit_hash.is_some());
+        assert_eq!(language, Some("Rust".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_slow_git_detection_does_not_block_others() {
+        let slow_git = async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Some(GitInfo {
+                branch: None,
+                commit_hash: None,
+                commit_author: None,
+                commit_message: None,
+                repository_url: None,
+            })
+        };
+        let project = async {
+            Some(ProjectInfo {
+                name: "fast".to_string(),
+                root: PathBuf::from("/fast"),
+            })
+        };
+        let language = async { Some("Rust".to_string()) };
+
+        let started = std::time::Instant::now();
+        let (project, git, language) =
+            join_with_deadline(project, slow_git, language, Duration::from_millis(100)).await;
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(project.unwrap().name, "fast");
+        assert!(git.is_none());
+        assert_eq!(language, Some("Rust".to_string()));
+    }
+
+    #[test]
+    fn test_detection_diagnostics_names_marker() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("diag-project");
+        fs::create_dir_all(project_dir.join("src")).unwrap();
+        fs::write(
+            project_dir.join("Cargo.toml"),
+            "[package]\nname = \"diag\"\n",
+        )
+        .unwrap();
+        let file_path = project_dir.join("src").join("main.rs");
+        fs::write(&file_path, "fn main() {}").unwrap();
+
+        let collector = DataCollector::new();
+        let lines = collector.detection_diagnostics(file_path.to_str().unwrap());
+
+        assert!(lines
+            .iter()
+            .any(|l| l.contains("matched marker Cargo.toml")));
+        assert!(lines.iter().any(|l| l.contains("name \"diag\"")));
+        assert!(lines
+            .iter()
+            .any(|l| l.contains("extension .rs mapped to Rust")));
+    }
+
+    #[tokio::test]
+    async fn test_custom_project_marker_finds_bazel_workspace() {
+        let temp_dir = TempDir::new().unwrap();
+        let workspace = temp_dir.path().join("monorepo");
+        let package_dir = workspace.join("services").join("billing");
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(
+            workspace.join("WORKSPACE"),
+            "workspace(name = \"monorepo\")\n",
+        )
+        .unwrap();
+        let file_path = package_dir.join("main.cc");
+        fs::write(&file_path, "int main() {}").unwrap();
+        let entity = file_path.to_str().unwrap();
+
+        // Without the marker the nearest directory wins
+        let project = DataCollector::new().detect_project(entity).await.unwrap();
+        assert_eq!(project.root, package_dir);
+
+        let collector = DataCollector::new().with_project_markers(&["WORKSPACE".to_string()]);
+        let project = collector.detect_project(entity).await.unwrap();
+        assert_eq!(project.root, workspace);
+        assert_eq!(project.name, "monorepo");
+        assert!(collector
+            .detection_diagnostics(entity)
+            .iter()
+            .any(|l| l.contains("matched marker WORKSPACE")));
+    }
+
     #[test]
     fn test_extract_project_name() {
         let temp_dir = TempDir::new().unwrap();
@@ -796,6 +1681,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_repository_url_uses_preferred_remote() {
+        use git2::{Repository, Signature};
+        let temp_dir = TempDir::new().unwrap();
+        let repo_dir = temp_dir.path().join("forked");
+        fs::create_dir_all(&repo_dir).unwrap();
+
+        let repo = Repository::init(&repo_dir).expect("init repo");
+        let file_path = repo_dir.join("README.md");
+        fs::write(&file_path, "hello").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("README.md")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("Test Author", "author@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "initial commit", &tree, &[])
+            .unwrap();
+
+        repo.remote("origin", "https://github.com/me/repo.git")
+            .unwrap();
+        repo.remote("upstream", "https://token@github.com/owner/repo.git")
+            .unwrap();
+        let entity = file_path.to_str().unwrap();
+
+        let info = tokio_test::block_on(DataCollector::new().detect_git_info(entity)).unwrap();
+        assert_eq!(
+            info.repository_url.as_deref(),
+            Some("https://github.com/me/repo.git")
+        );
+
+        let upstream = DataCollector::new().with_preferred_remote("upstream");
+        let info = tokio_test::block_on(upstream.detect_git_info(entity)).unwrap();
+        assert_eq!(
+            info.repository_url.as_deref(),
+            Some("https://github.com/owner/repo.git")
+        );
+
+        // Without the preferred remote, another one is used
+        repo.remote_delete("origin").unwrap();
+        let info = tokio_test::block_on(DataCollector::new().detect_git_info(entity)).unwrap();
+        assert_eq!(
+            info.repository_url.as_deref(),
+            Some("https://github.com/owner/repo.git")
+        );
+    }
+
     #[test]
     fn test_extract_project_name_edge_cases() {
         let temp_dir = TempDir::new().unwrap();
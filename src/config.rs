@@ -1,10 +1,165 @@
 use configparser::ini::Ini;
 use dirs::home_dir;
+use std::collections::HashMap;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+use crate::category::CategoryRule;
+use crate::collector::DEFAULT_MAX_DETECT_FILE_BYTES;
+use crate::project_name::ProjectNameRewrite;
 use crate::sync::SyncConfig;
 
+type IniMap = HashMap<String, HashMap<String, Option<String>>>;
+
+/// Placeholder `--today` prints when there is no activity, unless `--today-raw` is set
+pub const DEFAULT_TODAY_EMPTY_TEXT: &str = "0 secs";
+
+/// Default cap on the commit message sent with heartbeats, in characters
+pub const DEFAULT_MAX_COMMIT_MESSAGE_LEN: usize = 256;
+
+/// How far ahead of the local clock a heartbeat's time may be by default
+pub const DEFAULT_MAX_FUTURE_SKEW_SECONDS: u64 = 5 * 60;
+
+/// Patterns `exclude` starts from: commit message buffers, VCS internals and
+/// editor swap, backup and lock files. Disabled with `exclude_defaults = false`.
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &[
+    "COMMIT_EDITMSG$",
+    "PULLREQ_EDITMSG$",
+    "MERGE_MSG$",
+    "TAG_EDITMSG$",
+    "/.git/",
+    "\\.git\\",
+    "*.swp",
+    "*.swo",
+    "~$",
+    "/.#",
+    "\\.#",
+    "___jb_tmp___$",
+    "___jb_old___$",
+];
+
+/// Keys understood in the `[settings]` section; `-o key=value` overrides must name one
+pub const KNOWN_SETTINGS: &[&str] = &[
+    "api_key",
+    "api_url",
+    "debug",
+    "proxy",
+    "hide_file_names",
+    "hide_project_names",
+    "hide_branch_names",
+    "hide_commit_hash",
+    "hide_commit_author",
+    "hide_commit_message",
+    "hide_repository_url",
+    "disable_git_info",
+    "hide_project_folder",
+    "exclude_unknown_project",
+    "offline",
+    "guess_language",
+    "hostname",
+    "log_file",
+    "no_ssl_verify",
+    "ssl_certs_file",
+    "tls_builtin_roots",
+    "metrics",
+    "include_only_with_project_file",
+    "skip_empty_files",
+    "resolve_symlinks",
+    "project_from_path_depth",
+    "projects_root",
+    "project_search_ceiling",
+    "today_empty_text",
+    "default_language",
+    "project_markers",
+    "pre_send_hook",
+    "max_detect_file_bytes",
+    "payload_format",
+    "send_idempotency_key",
+    "dispatch_mode",
+    "max_commit_message_len",
+    "full_commit_message",
+    "project_cache",
+    "heartbeat_ttl_days",
+    "compress_queue",
+    "write_rate_limit_seconds",
+    "sample_rate",
+    "max_future_skew_seconds",
+    "detect_editor_from_process",
+    "single_instance",
+    "project_name_rewrites",
+    "preferred_remote",
+    "allow_header_overrides",
+    "auto_update",
+    "exclude",
+    "exclude_defaults",
+    "include",
+    "assume_online",
+    "sync_enabled",
+    "sync_max_queue_size",
+    "sync_batch_size",
+    "sync_interval",
+    "sync_max_retries",
+    "sync_retry_base_delay",
+    "sync_retry_max_delay",
+    "sync_retry_use_jitter",
+    "sync_retention_days",
+    "sync_background",
+    "sync_rate_limit_pause",
+    "sync_max_age",
+    "sync_retry_scan_limit",
+    "sync_idle_backoff_after",
+    "sync_idle_backoff_multiplier",
+    "sync_idle_backoff_max",
+];
+
+/// Shape of heartbeat batch request bodies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadFormat {
+    /// A bare JSON array of heartbeats
+    #[default]
+    JsonArray,
+    /// An object wrapping the array: `{"heartbeats": [...]}`
+    BulkWrapper,
+}
+
+impl std::str::FromStr for PayloadFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "json_array" => Ok(PayloadFormat::JsonArray),
+            "bulk_wrapper" => Ok(PayloadFormat::BulkWrapper),
+            other => Err(format!("unknown payload_format: {}", other)),
+        }
+    }
+}
+
+/// How `process` gets a heartbeat to the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DispatchMode {
+    /// Send right away, retrying briefly; queue only if that keeps failing
+    Immediate,
+    /// Queue only and leave syncing to the background sync or a later run
+    Queued,
+    /// Queue, then drain the queue inline
+    #[default]
+    Hybrid,
+}
+
+impl std::str::FromStr for DispatchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "immediate" => Ok(DispatchMode::Immediate),
+            "queued" => Ok(DispatchMode::Queued),
+            "hybrid" => Ok(DispatchMode::Hybrid),
+            other => Err(format!("unknown dispatch_mode: {}", other)),
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum ConfigError {
     #[error("Failed to parse config file: {0}")]
@@ -13,6 +168,8 @@ pub enum ConfigError {
     NotFound(String),
     #[error("Invalid config path: {0}")]
     InvalidPath(String),
+    #[error("Invalid config override: {0}")]
+    InvalidOverride(String),
 }
 
 #[derive(Debug, Clone)]
@@ -21,6 +178,8 @@ pub struct Config {
     pub api_url: Option<String>,
     pub debug: bool,
     pub proxy: Option<String>,
+    /// Connect directly even when a proxy is configured in the environment; set by `--no-proxy`
+    pub no_proxy: bool,
     pub ignore_patterns: Vec<String>,
     pub hide_file_names: bool,
     pub hide_project_names: bool,
@@ -39,34 +198,193 @@ pub struct Config {
     pub log_file: Option<String>,
     pub no_ssl_verify: bool,
     pub ssl_certs_file: Option<String>,
+    pub tls_builtin_roots: bool,
     pub metrics: bool,
     pub include_only_with_project_file: bool,
+    pub skip_empty_files: bool,
+    /// Canonicalize symlinked entities before detection and report the resolved path
+    pub resolve_symlinks: bool,
+    /// Fallback project naming for unmarked trees: the Nth directory below `projects_root`
+    pub project_from_path_depth: Option<usize>,
+    pub projects_root: Option<String>,
+    /// Highest directory project detection climbs to; the home directory when unset
+    pub project_search_ceiling: Option<String>,
+    /// Text `--today` prints when there is no activity yet
+    pub today_empty_text: String,
+    /// Language sent for files whose language is neither given nor detected
+    pub default_language: Option<String>,
+    /// Extra files or directories marking a project root, one per line, checked
+    /// after the built-in markers
+    pub project_markers: Vec<String>,
+    /// Shell command each heartbeat is piped through before it is queued or
+    /// sent, see [`crate::hook`]
+    pub pre_send_hook: Option<String>,
+    /// Files larger than this are only matched by name during language and project detection
+    pub max_detect_file_bytes: u64,
+    /// How heartbeat batches are encoded: `json_array` (default) or `bulk_wrapper`
+    pub payload_format: PayloadFormat,
+    /// Send an `Idempotency-Key` with each heartbeat batch so the server can
+    /// drop a retried batch it already counted
+    pub send_idempotency_key: bool,
+    /// Whether heartbeats are sent immediately, only queued, or queued and
+    /// synced inline (`hybrid`, the default)
+    pub dispatch_mode: DispatchMode,
+    /// Longest commit message sent with a heartbeat; longer ones end in an ellipsis
+    pub max_commit_message_len: usize,
+    /// Send the whole commit message instead of only its subject line
+    pub full_commit_message: bool,
+    /// Remember marker-based project detection in `~/.chronova/project_cache.json`
+    pub project_cache: bool,
+    /// Discard queued heartbeats this many days after they were queued, even if
+    /// they never synced. Separate from `sync_retention_days` for synced ones.
+    pub heartbeat_ttl_days: Option<u32>,
+    /// Store queued heartbeats zstd-compressed; rows queued before it was
+    /// turned on are compressed on the next run
+    pub compress_queue: bool,
+    /// Coalesce writes to the same file closer together than this into one
+    /// queued heartbeat carrying the latest time (0 = off)
+    pub write_rate_limit_seconds: u64,
+    /// Fraction of non-write heartbeats kept, from 0.0 to 1.0 (all). Writes are
+    /// always kept.
+    pub sample_rate: f64,
+    /// Heartbeats timed further than this ahead of now are dropped, so a
+    /// misconfigured editor clock can't push activity into the future
+    pub max_future_skew_seconds: u64,
+    /// Without `--plugin`, name the editor after the nearest ancestor process
+    /// that is a known editor
+    pub detect_editor_from_process: bool,
+    /// Only the invocation holding `~/.chronova/cli.lock` syncs; others just
+    /// queue their heartbeat and exit
+    pub single_instance: bool,
+    /// Extra HTTP headers from the `[headers]` section, sent with every API request
+    pub headers: HashMap<String, String>,
+    /// Rules from the `[category_rules]` section for heartbeats without a category
+    pub category_rules: Vec<CategoryRule>,
+    /// `regex => replacement` rules applied to detected project names
+    pub project_name_rewrites: Vec<ProjectNameRewrite>,
+    /// Git remote whose URL is sent as the repository URL
+    pub preferred_remote: String,
+    pub allow_header_overrides: bool,
     pub auto_update: bool,
     pub sync_config: SyncConfig,
 }
 
+/// `secret` with all but its last four characters starred out; short values
+/// are starred out entirely
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    let shown = if chars.len() > 8 { 4 } else { 0 };
+    let tail: String = chars[chars.len() - shown..].iter().collect();
+    format!("{}{}", "*".repeat(chars.len() - shown), tail)
+}
+
 impl Config {
+    /// Load the config leniently: lines the INI parser rejects are skipped with a
+    /// warning so one stray line doesn't stop heartbeat tracking.
     pub fn load(config_path: &str) -> Result<Self, ConfigError> {
-        let config_path = Self::resolve_config_path(config_path)?;
+        let (config, warnings) = Self::load_with_warnings(config_path)?;
+        for warning in &warnings {
+            tracing::warn!("Config: {}", warning);
+        }
+        Ok(config)
+    }
+
+    /// Lenient load that also returns a description of every skipped line
+    pub fn load_with_warnings(config_path: &str) -> Result<(Self, Vec<String>), ConfigError> {
+        Self::load_with_mode(config_path, false, &[])
+    }
+
+    /// Load the config, failing on any parse error. Used by `--config-validate`.
+    pub fn load_strict(config_path: &str) -> Result<Self, ConfigError> {
+        Self::load_with_mode(config_path, true, &[]).map(|(config, _)| config)
+    }
 
-        if !config_path.exists() {
-            return Ok(Self::default());
+    /// Lenient load with `key=value` overrides from `-o` layered over `[settings]`.
+    /// Override keys must be one of [`KNOWN_SETTINGS`].
+    pub fn load_with_overrides(
+        config_path: &str,
+        overrides: &[String],
+    ) -> Result<Self, ConfigError> {
+        let (config, warnings) = Self::load_with_mode(config_path, false, overrides)?;
+        for warning in &warnings {
+            tracing::warn!("Config: {}", warning);
         }
+        Ok(config)
+    }
 
-        let mut ini = Ini::new();
-        ini.set_multiline(true);
+    /// Split and validate `key=value` overrides
+    fn parse_overrides(overrides: &[String]) -> Result<Vec<(String, String)>, ConfigError> {
+        overrides
+            .iter()
+            .map(|entry| {
+                let (key, value) = entry.split_once('=').ok_or_else(|| {
+                    ConfigError::InvalidOverride(format!("{} (expected key=value)", entry))
+                })?;
+                let key = key.trim().to_lowercase();
+                if !KNOWN_SETTINGS.contains(&key.as_str()) {
+                    return Err(ConfigError::InvalidOverride(format!(
+                        "unknown setting {}",
+                        key
+                    )));
+                }
+                Ok((key, value.trim().to_string()))
+            })
+            .collect()
+    }
 
-        let config_map = ini.load(&config_path).map_err(|e| {
-            ConfigError::ParseError(format!(
-                "Failed to load config from {}: {}",
-                config_path.display(),
-                e
-            ))
-        })?;
+    fn load_with_mode(
+        config_path: &str,
+        strict: bool,
+        overrides: &[String],
+    ) -> Result<(Self, Vec<String>), ConfigError> {
+        let overrides = Self::parse_overrides(overrides)?;
+        let config_path = Self::resolve_config_path(config_path)?;
 
-        let settings = config_map.get("settings").cloned().unwrap_or_default();
+        if !config_path.exists() && overrides.is_empty() {
+            return Ok((Self::default(), Vec::new()));
+        }
+
+        let (config_map, warnings) = if config_path.exists() {
+            Self::read_ini(&config_path, strict)?
+        } else {
+            (IniMap::new(), Vec::new())
+        };
 
-        Ok(Config {
+        let mut settings = config_map.get("settings").cloned().unwrap_or_default();
+        for (key, value) in overrides {
+            settings.insert(key, Some(value));
+        }
+        let headers = config_map
+            .get("headers")
+            .map(|section| {
+                section
+                    .iter()
+                    .filter_map(|(k, v)| v.as_ref().map(|v| (k.clone(), v.clone())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let category_rules = config_map
+            .get("category_rules")
+            .map(|section| {
+                crate::category::parse_rules(
+                    section
+                        .iter()
+                        .filter_map(|(k, v)| v.as_deref().map(|v| (k.as_str(), v))),
+                )
+            })
+            .unwrap_or_default();
+        // User `exclude` patterns extend the defaults unless they are turned off
+        let default_ignores: &[&str] = if settings
+            .get("exclude_defaults")
+            .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+            .unwrap_or(true)
+        {
+            DEFAULT_IGNORE_PATTERNS
+        } else {
+            &[]
+        };
+
+        let config = Config {
             api_key: settings.get("api_key").and_then(|v| v.clone()),
             api_url: settings.get("api_url").and_then(|v| v.clone()),
             debug: settings
@@ -74,6 +392,7 @@ impl Config {
                 .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
                 .unwrap_or(false),
             proxy: settings.get("proxy").and_then(|v| v.clone()),
+            no_proxy: false,
             hide_file_names: settings
                 .get("hide_file_names")
                 .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
@@ -130,6 +449,10 @@ impl Config {
                 .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
                 .unwrap_or(false),
             ssl_certs_file: settings.get("ssl_certs_file").and_then(|v| v.clone()),
+            tls_builtin_roots: settings
+                .get("tls_builtin_roots")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(true),
             metrics: settings
                 .get("metrics")
                 .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
@@ -138,13 +461,32 @@ impl Config {
                 .get("include_only_with_project_file")
                 .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
                 .unwrap_or(false),
-            auto_update: settings
-                .get("auto_update")
+            skip_empty_files: settings
+                .get("skip_empty_files")
                 .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
                 .unwrap_or(false),
-            sync_config: Self::parse_sync_config(&settings),
-            ignore_patterns: settings
-                .get("exclude")
+            resolve_symlinks: settings
+                .get("resolve_symlinks")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(false),
+            project_from_path_depth: settings
+                .get("project_from_path_depth")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok())),
+            projects_root: settings.get("projects_root").and_then(|v| v.clone()),
+            project_search_ceiling: settings
+                .get("project_search_ceiling")
+                .and_then(|v| v.as_deref())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string),
+            default_language: settings
+                .get("default_language")
+                .and_then(|v| v.as_deref())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string),
+            project_markers: settings
+                .get("project_markers")
                 .and_then(|s| s.as_ref())
                 .map(|s| {
                     s.split('\n')
@@ -153,6 +495,109 @@ impl Config {
                         .collect()
                 })
                 .unwrap_or_default(),
+            pre_send_hook: settings
+                .get("pre_send_hook")
+                .and_then(|v| v.as_deref())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string),
+            today_empty_text: settings
+                .get("today_empty_text")
+                .and_then(|v| v.clone())
+                .unwrap_or_else(|| DEFAULT_TODAY_EMPTY_TEXT.to_string()),
+            max_detect_file_bytes: settings
+                .get("max_detect_file_bytes")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(DEFAULT_MAX_DETECT_FILE_BYTES),
+            payload_format: settings
+                .get("payload_format")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or_default(),
+            send_idempotency_key: settings
+                .get("send_idempotency_key")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(false),
+            dispatch_mode: settings
+                .get("dispatch_mode")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or_default(),
+            max_commit_message_len: settings
+                .get("max_commit_message_len")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(DEFAULT_MAX_COMMIT_MESSAGE_LEN),
+            full_commit_message: settings
+                .get("full_commit_message")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(false),
+            project_cache: settings
+                .get("project_cache")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(false),
+            heartbeat_ttl_days: settings
+                .get("heartbeat_ttl_days")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok())),
+            compress_queue: settings
+                .get("compress_queue")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(false),
+            write_rate_limit_seconds: settings
+                .get("write_rate_limit_seconds")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(0),
+            sample_rate: settings
+                .get("sample_rate")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse::<f64>().ok()))
+                .filter(|rate| rate.is_finite())
+                .map(|rate| rate.clamp(0.0, 1.0))
+                .unwrap_or(1.0),
+            max_future_skew_seconds: settings
+                .get("max_future_skew_seconds")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(DEFAULT_MAX_FUTURE_SKEW_SECONDS),
+            detect_editor_from_process: settings
+                .get("detect_editor_from_process")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(false),
+            single_instance: settings
+                .get("single_instance")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(false),
+            headers,
+            category_rules,
+            project_name_rewrites: settings
+                .get("project_name_rewrites")
+                .and_then(|s| s.as_deref())
+                .map(crate::project_name::parse_rewrites)
+                .unwrap_or_default(),
+            preferred_remote: settings
+                .get("preferred_remote")
+                .and_then(|s| s.as_deref())
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .unwrap_or(crate::collector::DEFAULT_REMOTE)
+                .to_string(),
+            allow_header_overrides: settings
+                .get("allow_header_overrides")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(false),
+            auto_update: settings
+                .get("auto_update")
+                .and_then(|s| s.as_ref().and_then(|v| v.parse().ok()))
+                .unwrap_or(false),
+            sync_config: Self::parse_sync_config(&settings),
+            ignore_patterns: default_ignores
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .chain(
+                    settings
+                        .get("exclude")
+                        .and_then(|s| s.as_ref())
+                        .into_iter()
+                        .flat_map(|s| s.split('\n'))
+                        .map(|line| line.trim().to_string())
+                        .filter(|line| !line.is_empty()),
+                )
+                .collect(),
             include_patterns: settings
                 .get("include")
                 .and_then(|s| s.as_ref())
@@ -163,7 +608,63 @@ impl Config {
                         .collect()
                 })
                 .unwrap_or_default(),
-        })
+        };
+
+        Ok((config, warnings))
+    }
+
+    /// Parse the INI file at `path`. In lenient mode each line the parser rejects
+    /// is blanked out and reported as a warning, and parsing is retried.
+    fn read_ini(path: &Path, strict: bool) -> Result<(IniMap, Vec<String>), ConfigError> {
+        let parse_error = |e: String| {
+            ConfigError::ParseError(format!(
+                "Failed to load config from {}: {}",
+                path.display(),
+                e
+            ))
+        };
+
+        let mut warnings = Vec::new();
+        let bytes = std::fs::read(path).map_err(|e| parse_error(e.to_string()))?;
+        let content = match String::from_utf8(bytes) {
+            Ok(content) => content,
+            Err(e) if strict => return Err(parse_error(e.to_string())),
+            Err(e) => {
+                warnings.push("replaced invalid UTF-8 bytes".to_string());
+                String::from_utf8_lossy(e.as_bytes()).into_owned()
+            }
+        };
+        let mut lines: Vec<String> = content.lines().map(String::from).collect();
+
+        loop {
+            let mut ini = Ini::new();
+            ini.set_multiline(true);
+
+            let error = match ini.read(lines.join("\n")) {
+                Ok(map) => return Ok((map, warnings)),
+                Err(e) if strict => return Err(parse_error(e)),
+                Err(e) => e,
+            };
+
+            // Parser errors start with the zero-based line number, e.g. "line 3: ..."
+            let line = error
+                .strip_prefix("line ")
+                .and_then(|rest| rest.split(|c: char| !c.is_ascii_digit()).next())
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|n| *n < lines.len() && lines[*n] != "#");
+            let Some(line) = line else {
+                return Err(parse_error(error));
+            };
+
+            let reason = error.rsplit_once(": ").map_or(error.as_str(), |(_, r)| r);
+            warnings.push(format!(
+                "ignored line {} `{}`: {}",
+                line + 1,
+                lines[line].trim(),
+                reason
+            ));
+            lines[line] = "#".to_string();
+        }
     }
 
     pub fn resolve_config_path(config_path: &str) -> Result<PathBuf, ConfigError> {
@@ -201,10 +702,75 @@ impl Config {
         Err(ConfigError::InvalidPath(config_path.to_string()))
     }
 
+    /// Replace the config file at `path` with `contents`. The new contents go
+    /// to a temp file in the same directory that is renamed over the target,
+    /// so a crash never leaves a truncated config behind. Existing permissions
+    /// are kept, and a symlinked config is written through to its target.
+    pub fn write_file(path: &Path, contents: &str) -> std::io::Result<()> {
+        Self::write_file_with(path, |file| file.write_all(contents.as_bytes()))
+    }
+
+    fn write_file_with(
+        path: &Path,
+        write: impl FnOnce(&mut std::fs::File) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let target = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let dir = target
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+
+        let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+        write(temp.as_file_mut())?;
+        temp.as_file().sync_all()?;
+        if let Ok(metadata) = std::fs::metadata(&target) {
+            temp.as_file().set_permissions(metadata.permissions())?;
+        }
+        temp.persist(&target).map_err(|e| e.error)?;
+        Ok(())
+    }
+
     pub fn get_api_key(&self, cli_key: Option<&String>) -> Option<String> {
         cli_key.cloned().or_else(|| self.api_key.clone())
     }
 
+    /// Apply `--proxy` / `--no-proxy` for this run; either takes precedence over
+    /// the `proxy` setting
+    pub fn with_cli_proxy(mut self, cli_proxy: Option<&str>, no_proxy: bool) -> Self {
+        if no_proxy {
+            self.proxy = None;
+            self.no_proxy = true;
+        } else if let Some(proxy) = cli_proxy {
+            self.proxy = Some(proxy.to_string());
+        }
+        self
+    }
+
+    /// API key for display, showing only its last four characters
+    pub fn masked_api_key(&self, cli_key: Option<&String>) -> Option<String> {
+        self.get_api_key(cli_key).map(|key| mask_secret(&key))
+    }
+
+    /// Every section of the config file as written, for diagnostics. Lines
+    /// the parser rejects are skipped as in a lenient load.
+    pub fn read_sections(
+        config_path: &str,
+    ) -> Result<
+        std::collections::BTreeMap<String, std::collections::BTreeMap<String, Option<String>>>,
+        ConfigError,
+    > {
+        let path = Self::resolve_config_path(config_path)?;
+        if !path.exists() {
+            return Ok(Default::default());
+        }
+        let (sections, _) = Self::read_ini(&path, false)?;
+        Ok(sections
+            .into_iter()
+            .map(|(name, section)| (name, section.into_iter().collect()))
+            .collect())
+    }
+
     pub fn get_api_url(&self) -> String {
         self.api_url
             .clone()
@@ -232,6 +798,16 @@ impl Config {
             }
         }
 
+        if let Some(batch_size) = settings.get("sync_batch_size") {
+            if let Some(value) = batch_size.as_ref() {
+                if let Ok(parsed) = value.parse::<usize>() {
+                    if parsed > 0 {
+                        sync_config.batch_size = parsed;
+                    }
+                }
+            }
+        }
+
         if let Some(sync_interval) = settings.get("sync_interval") {
             if let Some(value) = sync_interval.as_ref() {
                 if let Ok(parsed) = value.parse::<u64>() {
@@ -288,6 +864,62 @@ impl Config {
             }
         }
 
+        if let Some(rate_limit_pause) = settings.get("sync_rate_limit_pause") {
+            if let Some(value) = rate_limit_pause.as_ref() {
+                if let Ok(parsed) = value.parse::<u64>() {
+                    sync_config.rate_limit_pause_seconds = parsed;
+                }
+            }
+        }
+
+        if let Some(assume_online) = settings.get("assume_online") {
+            if let Some(value) = assume_online.as_ref() {
+                if let Ok(parsed) = value.parse::<bool>() {
+                    sync_config.assume_online = parsed;
+                }
+            }
+        }
+
+        if let Some(max_age) = settings.get("sync_max_age") {
+            if let Some(value) = max_age.as_ref() {
+                if let Ok(parsed) = value.parse::<u32>() {
+                    sync_config.max_age_days = Some(parsed);
+                }
+            }
+        }
+
+        if let Some(retry_scan_limit) = settings.get("sync_retry_scan_limit") {
+            if let Some(value) = retry_scan_limit.as_ref() {
+                if let Ok(parsed) = value.parse::<usize>() {
+                    sync_config.retry_scan_limit = parsed;
+                }
+            }
+        }
+
+        if let Some(idle_backoff_after) = settings.get("sync_idle_backoff_after") {
+            if let Some(value) = idle_backoff_after.as_ref() {
+                if let Ok(parsed) = value.parse::<u32>() {
+                    sync_config.idle_backoff_after_cycles = parsed;
+                }
+            }
+        }
+
+        if let Some(idle_backoff_multiplier) = settings.get("sync_idle_backoff_multiplier") {
+            if let Some(value) = idle_backoff_multiplier.as_ref() {
+                if let Ok(parsed) = value.parse::<u32>() {
+                    sync_config.idle_backoff_multiplier = parsed;
+                }
+            }
+        }
+
+        if let Some(idle_backoff_max) = settings.get("sync_idle_backoff_max") {
+            if let Some(value) = idle_backoff_max.as_ref() {
+                if let Ok(parsed) = value.parse::<u64>() {
+                    sync_config.idle_backoff_max_seconds = parsed;
+                }
+            }
+        }
+
         sync_config
     }
 }
@@ -299,12 +931,11 @@ impl Default for Config {
             api_url: Some("https://chronova.dev/api/v1".to_string()),
             debug: false,
             proxy: None,
-            ignore_patterns: vec![
-                "COMMIT_EDITMSG$".to_string(),
-                "PULLREQ_EDITMSG$".to_string(),
-                "MERGE_MSG$".to_string(),
-                "TAG_EDITMSG$".to_string(),
-            ],
+            no_proxy: false,
+            ignore_patterns: DEFAULT_IGNORE_PATTERNS
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
             include_patterns: vec![],
             hide_file_names: false,
             hide_project_names: false,
@@ -323,8 +954,37 @@ impl Default for Config {
             auto_update: false,
             no_ssl_verify: false,
             ssl_certs_file: None,
+            tls_builtin_roots: true,
             metrics: false,
             include_only_with_project_file: false,
+            skip_empty_files: false,
+            resolve_symlinks: false,
+            project_from_path_depth: None,
+            projects_root: None,
+            project_search_ceiling: None,
+            today_empty_text: DEFAULT_TODAY_EMPTY_TEXT.to_string(),
+            default_language: None,
+            project_markers: Vec::new(),
+            pre_send_hook: None,
+            max_detect_file_bytes: DEFAULT_MAX_DETECT_FILE_BYTES,
+            payload_format: PayloadFormat::default(),
+            send_idempotency_key: false,
+            dispatch_mode: DispatchMode::default(),
+            max_commit_message_len: DEFAULT_MAX_COMMIT_MESSAGE_LEN,
+            full_commit_message: false,
+            project_cache: false,
+            heartbeat_ttl_days: None,
+            compress_queue: false,
+            write_rate_limit_seconds: 0,
+            sample_rate: 1.0,
+            max_future_skew_seconds: DEFAULT_MAX_FUTURE_SKEW_SECONDS,
+            detect_editor_from_process: false,
+            single_instance: false,
+            headers: HashMap::new(),
+            category_rules: Vec::new(),
+            project_name_rewrites: Vec::new(),
+            preferred_remote: crate::collector::DEFAULT_REMOTE.to_string(),
+            allow_header_overrides: false,
             sync_config: SyncConfig::default(),
         }
     }
@@ -370,6 +1030,189 @@ exclude =
         assert!(config.ignore_patterns.contains(&"*.log".to_string()));
     }
 
+    #[test]
+    fn test_exclude_defaults_can_be_disabled() {
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), "[settings]\nexclude =\n    *.log\n").unwrap();
+        let config = Config::load(temp_file.path().to_str().unwrap()).unwrap();
+        assert!(config.ignore_patterns.contains(&"/.git/".to_string()));
+        assert!(config.ignore_patterns.contains(&"*.log".to_string()));
+
+        fs::write(
+            temp_file.path(),
+            "[settings]\nexclude_defaults = false\nexclude =\n    *.log\n",
+        )
+        .unwrap();
+        let config = Config::load(temp_file.path().to_str().unwrap()).unwrap();
+        assert_eq!(config.ignore_patterns, vec!["*.log".to_string()]);
+    }
+
+    #[test]
+    fn test_interrupted_config_write_keeps_old_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(".chronova.cfg");
+        let original = "[settings]\napi_key = old_key\n";
+        fs::write(&path, original).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        // Fail halfway through writing the new contents
+        let result = Config::write_file_with(&path, |file| {
+            file.write_all(b"[settings]\napi_")?;
+            Err(std::io::Error::other("interrupted"))
+        });
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        Config::write_file(&path, "[settings]\napi_key = new_key\n").unwrap();
+        let config = Config::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(config.api_key.as_deref(), Some("new_key"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+    }
+
+    #[test]
+    fn test_lenient_load_skips_malformed_lines() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[settings]
+api_key = good_key
+= orphan value
+debug = true
+[broken
+hostname = box
+"#;
+        fs::write(temp_file.path(), config_content).unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let (config, warnings) = Config::load_with_warnings(path).unwrap();
+        assert_eq!(config.api_key, Some("good_key".to_string()));
+        assert!(config.debug);
+        assert_eq!(config.hostname, Some("box".to_string()));
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("line 4"), "{:?}", warnings);
+        assert!(warnings[1].contains("[broken"), "{:?}", warnings);
+
+        // Validation keeps the hard failure
+        assert!(matches!(
+            Config::load_strict(path),
+            Err(ConfigError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_cli_override_beats_file_value() {
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(
+            temp_file.path(),
+            "[settings]\napi_url = https://chronova.dev/api/v1\ndebug = false\n",
+        )
+        .unwrap();
+        let path = temp_file.path().to_str().unwrap();
+
+        let overrides = vec![
+            "api_url=http://localhost:8080".to_string(),
+            "debug=true".to_string(),
+        ];
+        let config = Config::load_with_overrides(path, &overrides).unwrap();
+        assert_eq!(config.api_url, Some("http://localhost:8080".to_string()));
+        assert!(config.debug);
+
+        assert!(matches!(
+            Config::load_with_overrides(path, &["no_such_key=1".to_string()]),
+            Err(ConfigError::InvalidOverride(_))
+        ));
+    }
+
+    #[test]
+    fn test_cli_proxy_overrides_config() {
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(
+            temp_file.path(),
+            "[settings]\nproxy = http://config-proxy:3128\n",
+        )
+        .unwrap();
+        let config = Config::load(temp_file.path().to_str().unwrap()).unwrap();
+        assert_eq!(config.proxy.as_deref(), Some("http://config-proxy:3128"));
+
+        let from_cli = config
+            .clone()
+            .with_cli_proxy(Some("socks5://cli-proxy:1080"), false);
+        assert_eq!(from_cli.proxy.as_deref(), Some("socks5://cli-proxy:1080"));
+
+        let unchanged = config.clone().with_cli_proxy(None, false);
+        assert_eq!(unchanged.proxy.as_deref(), Some("http://config-proxy:3128"));
+
+        let direct = config.with_cli_proxy(None, true);
+        assert_eq!(direct.proxy, None);
+        assert!(direct.no_proxy);
+    }
+
+    #[test]
+    fn test_load_headers_section() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[settings]
+api_key = test_key
+
+[headers]
+X-Tenant-Id = acme
+"#;
+        fs::write(temp_file.path(), config_content).unwrap();
+
+        let config = Config::load(temp_file.path().to_str().unwrap()).unwrap();
+        assert_eq!(config.headers.get("x-tenant-id"), Some(&"acme".to_string()));
+        assert!(!config.allow_header_overrides);
+    }
+
+    #[test]
+    fn test_load_category_rules_section() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[settings]
+api_key = test_key
+
+[category_rules]
+read *_test.rs = debugging
+"#;
+        fs::write(temp_file.path(), config_content).unwrap();
+
+        let config = Config::load(temp_file.path().to_str().unwrap()).unwrap();
+        assert_eq!(config.category_rules.len(), 1);
+        assert_eq!(
+            crate::category::infer_category(&config.category_rules, "/src/a_test.rs", false),
+            "debugging"
+        );
+    }
+
+    #[test]
+    fn test_load_project_name_rewrites() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let config_content = r#"
+[settings]
+api_key = test_key
+project_name_rewrites =
+    -(main|master)$ =>
+    ^Acme-(.*)$ => $1
+"#;
+        fs::write(temp_file.path(), config_content).unwrap();
+
+        let config = Config::load(temp_file.path().to_str().unwrap()).unwrap();
+        assert_eq!(config.project_name_rewrites.len(), 2);
+        assert_eq!(
+            crate::project_name::sanitize("Acme-Billing-main", &config.project_name_rewrites),
+            "Billing"
+        );
+    }
+
     #[test]
     fn test_get_api_key_precedence() {
         let config = Config {
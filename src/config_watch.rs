@@ -0,0 +1,137 @@
+//! Live reload of sync settings for long-running background sync.
+//!
+//! The config file's directory is watched rather than the file itself, so
+//! editors that save by writing a temporary file and renaming it over
+//! `.chronova.cfg` are noticed too. Only [`SyncConfig`] is reloaded; the API
+//! key, URL and other settings keep the values the process started with.
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::sync::SyncConfig;
+
+/// Watches a config file and reloads its sync settings after it changes
+pub struct SyncConfigWatcher {
+    // Dropping the watcher stops the event stream
+    _watcher: RecommendedWatcher,
+    path: PathBuf,
+    changed: Arc<AtomicBool>,
+}
+
+impl SyncConfigWatcher {
+    /// Start watching the config file at `path`
+    pub fn new(path: &Path) -> notify::Result<Self> {
+        let changed = Arc::new(AtomicBool::new(false));
+        let file_name = path.file_name().map(|name| name.to_os_string());
+        let flag = Arc::clone(&changed);
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let touches_config = event.is_ok_and(|event| {
+                event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name().map(|n| n.to_os_string()) == file_name)
+            });
+            if touches_config {
+                flag.store(true, Ordering::SeqCst);
+            }
+        })?;
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            path: path.to_path_buf(),
+            changed,
+        })
+    }
+
+    /// Sync settings from the config file if it changed since the last call.
+    /// A file that is missing or fails to load is skipped with a warning, so
+    /// the running settings stay in effect.
+    pub fn take_update(&self) -> Option<SyncConfig> {
+        if !self.changed.swap(false, Ordering::SeqCst) || !self.path.exists() {
+            return None;
+        }
+        match Config::load(&self.path.to_string_lossy()) {
+            Ok(config) => Some(config.sync_config),
+            Err(e) => {
+                tracing::warn!(
+                    "Keeping current sync settings; failed to reload {}: {}",
+                    self.path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Describe each setting that differs between `old` and `new`, as
+/// `name: old -> new`
+pub fn sync_config_changes(old: &SyncConfig, new: &SyncConfig) -> Vec<String> {
+    let mut changes = Vec::new();
+    macro_rules! compare {
+        ($($field:ident),* $(,)?) => {
+            $(
+                if old.$field != new.$field {
+                    changes.push(format!(
+                        "{}: {:?} -> {:?}",
+                        stringify!($field),
+                        old.$field,
+                        new.$field
+                    ));
+                }
+            )*
+        };
+    }
+    compare!(
+        enabled,
+        max_queue_size,
+        batch_size,
+        sync_interval_seconds,
+        max_retry_attempts,
+        retry_base_delay_seconds,
+        retry_max_delay_seconds,
+        retry_use_jitter,
+        retention_days,
+        background_sync,
+        idle_backoff_after_cycles,
+        idle_backoff_multiplier,
+        idle_backoff_max_seconds,
+        max_age_days,
+        rate_limit_pause_seconds,
+        assume_online,
+        retry_scan_limit,
+    );
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_config_changes_lists_changed_settings() {
+        let old = SyncConfig::default();
+        let new = SyncConfig {
+            batch_size: 100,
+            sync_interval_seconds: 30,
+            ..SyncConfig::default()
+        };
+
+        assert_eq!(
+            sync_config_changes(&old, &new),
+            vec![
+                "batch_size: 50 -> 100".to_string(),
+                "sync_interval_seconds: 300 -> 30".to_string(),
+            ]
+        );
+        assert!(sync_config_changes(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,111 @@
+//! Process exit codes.
+//!
+//! Editor integrations key off these to react to specific failures, e.g.
+//! prompting for an API key only on [`ExitCode::Auth`].
+
+use std::error::Error as StdError;
+
+use crate::api::ApiError;
+use crate::config::ConfigError;
+use crate::queue::QueueError;
+use crate::sync::SyncError;
+
+/// Exit status of a `chronova-cli` invocation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// 0: the command succeeded
+    Success = 0,
+    /// 1: any failure not covered below
+    General = 1,
+    /// 2: unreadable or invalid configuration, or invalid command-line usage
+    Config = 2,
+    /// 3: missing, invalid or rejected API key
+    Auth = 3,
+    /// 4: the API could not be reached or rate limited the request
+    Network = 4,
+    /// 5: the offline queue database could not be read or written
+    Queue = 5,
+}
+
+impl ExitCode {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Terminate the process with this code
+    pub fn exit(self) -> ! {
+        std::process::exit(self.code())
+    }
+
+    /// Classify an error by the first recognised error type in its source chain
+    pub fn for_error(error: &(dyn StdError + 'static)) -> Self {
+        let mut current = Some(error);
+        while let Some(err) = current {
+            if let Some(code) = Self::classify(err) {
+                return code;
+            }
+            current = err.source();
+        }
+        ExitCode::General
+    }
+
+    fn classify(err: &(dyn StdError + 'static)) -> Option<Self> {
+        if let Some(err) = err.downcast_ref::<ApiError>() {
+            return match err {
+                ApiError::Auth(_) => Some(ExitCode::Auth),
+                ApiError::Network(_) | ApiError::RateLimit(..) => Some(ExitCode::Network),
+                ApiError::Tls(_) => Some(ExitCode::Config),
+                ApiError::Api { .. } | ApiError::NotFound(_) | ApiError::Validation(_) => {
+                    Some(ExitCode::General)
+                }
+            };
+        }
+        if let Some(err) = err.downcast_ref::<SyncError>() {
+            return match err {
+                SyncError::Auth(_) => Some(ExitCode::Auth),
+                SyncError::Network(_) | SyncError::RateLimit(_) => Some(ExitCode::Network),
+                SyncError::Database(_) => Some(ExitCode::Queue),
+                SyncError::Config(_) => Some(ExitCode::Config),
+                SyncError::Rejected(..) => Some(ExitCode::General),
+                SyncError::Serialization(_) | SyncError::Unknown(_) => None,
+            };
+        }
+        if err.is::<ConfigError>() {
+            return Some(ExitCode::Config);
+        }
+        if err.is::<QueueError>() || err.is::<rusqlite::Error>() {
+            return Some(ExitCode::Queue);
+        }
+        if err.is::<reqwest::Error>() {
+            return Some(ExitCode::Network);
+        }
+        None
+    }
+}
+
+impl From<&anyhow::Error> for ExitCode {
+    fn from(error: &anyhow::Error) -> Self {
+        ExitCode::for_error(error.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auth_error_maps_to_auth_exit_code() {
+        let error = anyhow::Error::from(ApiError::Auth("Invalid API key".to_string()))
+            .context("Error fetching today's activity");
+
+        assert_eq!(ExitCode::from(&error), ExitCode::Auth);
+        assert_eq!(ExitCode::from(&error).code(), 3);
+
+        let error = anyhow::Error::from(QueueError::QueueFull);
+        assert_eq!(ExitCode::from(&error), ExitCode::Queue);
+        assert_eq!(
+            ExitCode::from(&anyhow::anyhow!("something else")),
+            ExitCode::General
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Git hook integration: read the files touched by the latest commit and
+//! install a `post-commit` hook that reports them via `--from-git-hook`.
+
+use anyhow::{anyhow, Result};
+use git2::{Delta, Repository};
+use std::path::{Path, PathBuf};
+
+/// Marker line identifying hooks written by `--install-git-hook`
+const HOOK_MARKER: &str = "# Installed by chronova-cli";
+
+/// Files changed by the HEAD commit of the repository containing `path`
+#[derive(Debug, Clone)]
+pub struct CommitFiles {
+    /// Absolute paths of added or modified files; deletions are skipped
+    pub files: Vec<PathBuf>,
+    /// Commit time as a Unix timestamp
+    pub time: f64,
+}
+
+/// Collect the files changed by HEAD relative to its first parent.
+/// A root commit reports every file it adds.
+pub fn head_commit_files(path: &Path) -> Result<CommitFiles> {
+    let repo = Repository::discover(path)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository at {} has no working tree", path.display()))?
+        .to_path_buf();
+
+    let commit = repo.head()?.peel_to_commit()?;
+    let tree = commit.tree()?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+    let files = diff
+        .deltas()
+        .filter(|delta| delta.status() != Delta::Deleted)
+        .filter_map(|delta| delta.new_file().path().map(|p| workdir.join(p)))
+        .collect();
+
+    Ok(CommitFiles {
+        files,
+        time: commit.time().seconds() as f64,
+    })
+}
+
+/// Write a `post-commit` hook into the repository containing `path` that runs
+/// `exe --from-git-hook` in the background. An existing hook is only replaced
+/// when it was written by us. Returns the hook path.
+pub fn install_post_commit_hook(path: &Path, exe: &Path) -> Result<PathBuf> {
+    let repo = Repository::discover(path)?;
+    let hooks_dir = repo.path().join("hooks");
+    std::fs::create_dir_all(&hooks_dir)?;
+    let hook_path = hooks_dir.join("post-commit");
+
+    if let Ok(existing) = std::fs::read_to_string(&hook_path) {
+        if !existing.contains(HOOK_MARKER) {
+            return Err(anyhow!(
+                "{} already exists and was not installed by chronova-cli",
+                hook_path.display()
+            ));
+        }
+    }
+
+    let script = format!(
+        "#!/bin/sh\n{}\n\"{}\" --from-git-hook >/dev/null 2>&1 &\n",
+        HOOK_MARKER,
+        exe.display()
+    );
+    std::fs::write(&hook_path, script)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(hook_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_refuses_foreign_hook() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(temp_dir.path()).unwrap();
+        let exe = Path::new("/usr/local/bin/chronova-cli");
+
+        let hook_path = install_post_commit_hook(temp_dir.path(), exe).unwrap();
+        let script = std::fs::read_to_string(&hook_path).unwrap();
+        assert!(script.contains("--from-git-hook"));
+
+        // Reinstalling over our own hook is fine
+        install_post_commit_hook(temp_dir.path(), exe).unwrap();
+
+        std::fs::write(
+            repo.path().join("hooks/post-commit"),
+            "#!/bin/sh\nmake lint\n",
+        )
+        .unwrap();
+        assert!(install_post_commit_hook(temp_dir.path(), exe).is_err());
+    }
+}
@@ -6,11 +6,159 @@ use uuid::Uuid;
 use crate::api::ApiClient;
 use crate::cli::Cli;
 use crate::collector::DataCollector;
-use crate::config::Config;
-use crate::queue::{Queue, QueueOps};
+use crate::config::{Config, DispatchMode};
+use crate::queue::{Queue, QueueOps, SharedQueue};
 use crate::sync::{SyncResult, SyncStatusSummary};
-use crate::user_agent::generate_user_agent;
+use crate::user_agent::{generate_user_agent, parse_plugin};
 use anyhow::Result;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing::Instrument;
+
+/// Overall time budget for project, git and language detection per heartbeat
+const DETECTION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Send attempts for `dispatch_mode = immediate` before falling back to the queue
+const IMMEDIATE_SEND_ATTEMPTS: u32 = 3;
+
+/// Longest rate-limit backoff slept through during a sync run; a longer
+/// `Retry-After` ends the run and leaves the queue for the next one
+const MAX_INLINE_BACKOFF: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// With sampling on, a heartbeat this long after the previous one starts a
+/// new session and is always kept
+const SAMPLE_SESSION_GAP_SECONDS: f64 = 15.0 * 60.0;
+
+/// Machine name of last resort when neither a hostname nor a machine id is available
+const UNKNOWN_HOST: &str = "unknown-host";
+
+/// Files holding a stable per-machine id, tried in order when the hostname is unusable
+const MACHINE_ID_FILES: &[&str] = &["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
+/// File name sent in place of the real one with `hide_file_names`
+const HIDDEN_FILE_NAME: &str = "HIDDEN";
+
+/// Times at or above this are taken to be milliseconds rather than seconds.
+/// In seconds it is the year 5138; in milliseconds, early 1973.
+const MILLIS_THRESHOLD: f64 = 1e11;
+
+/// Normalize a heartbeat time to Unix seconds with millisecond precision.
+/// Editors disagree on units, so a value that looks like milliseconds is
+/// divided down and the correction is logged.
+pub fn normalize_heartbeat_time(time: f64) -> f64 {
+    let seconds = if time.abs() >= MILLIS_THRESHOLD {
+        let corrected = time / 1000.0;
+        tracing::info!(
+            "Heartbeat time {} looks like milliseconds, using {} seconds",
+            time,
+            corrected
+        );
+        corrected
+    } else {
+        time
+    };
+    (seconds * 1000.0).round() / 1000.0
+}
+
+/// Trim a commit message for sending: only the subject line unless `full` is
+/// set, and at most `max_len` characters with a trailing ellipsis when cut.
+pub fn shorten_commit_message(message: &str, max_len: usize, full: bool) -> String {
+    let message = if full {
+        message.trim()
+    } else {
+        message.lines().next().unwrap_or_default().trim()
+    };
+
+    if message.chars().count() <= max_len {
+        return message.to_string();
+    }
+    let mut shortened: String = message.chars().take(max_len.saturating_sub(1)).collect();
+    shortened.push('…');
+    shortened
+}
+
+/// Ignore/include pattern matching: `suffix$` matches the end of the entity,
+/// `*.ext` its extension, and anything else a substring.
+fn entity_matches_pattern(entity: &str, pattern: &str) -> bool {
+    if let Some(base_pattern) = pattern.strip_suffix('$') {
+        entity.ends_with(base_pattern)
+    } else if let Some(extension) = pattern.strip_prefix("*.") {
+        entity.ends_with(extension)
+    } else {
+        entity.contains(pattern)
+    }
+}
+
+/// Deterministic draw in `[0, 1)` for sampling a heartbeat: FNV-1a over the
+/// entity seeds a SplitMix64 step keyed by the time in milliseconds
+fn sample_draw(entity: &str, time: f64) -> f64 {
+    let seed = entity.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    let mut z = seed
+        .wrapping_add((time * 1000.0) as i64 as u64)
+        .wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Accept `time` as a JSON float, integer or numeric string, normalized to
+/// Unix seconds. One plugin sending `"time": "1700000000"` would otherwise
+/// fail the whole batch.
+fn deserialize_time<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawTime {
+        Number(f64),
+        Text(String),
+    }
+
+    let time = match RawTime::deserialize(deserializer)? {
+        RawTime::Number(time) => time,
+        RawTime::Text(text) => text
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| serde::de::Error::custom(format!("invalid heartbeat time: {:?}", text)))?,
+    };
+    if !time.is_finite() {
+        return Err(serde::de::Error::custom("heartbeat time must be finite"));
+    }
+    Ok(normalize_heartbeat_time(time))
+}
+
+/// Entity string for a file path. Paths that aren't valid UTF-8 are converted
+/// lossily, with a warning, so they still yield a best-effort heartbeat.
+pub fn entity_from_path(path: &std::path::Path) -> String {
+    match path.to_str() {
+        Some(entity) => entity.to_string(),
+        None => {
+            let entity = path.to_string_lossy().into_owned();
+            tracing::warn!("Path contains invalid UTF-8, using {}", entity);
+            entity
+        }
+    }
+}
+
+/// Machine name to report: the first non-empty of `configured`, `system_hostname`,
+/// the machine id, or `unknown-host`. Never empty, even in containers where
+/// `gethostname` yields nothing.
+pub fn resolve_machine_name(configured: Option<String>, system_hostname: String) -> String {
+    let non_empty = |name: String| {
+        let trimmed = name.trim();
+        (!trimmed.is_empty()).then(|| trimmed.to_string())
+    };
+
+    configured
+        .and_then(non_empty)
+        .or_else(|| non_empty(system_hostname))
+        .or_else(|| {
+            MACHINE_ID_FILES
+                .iter()
+                .find_map(|path| std::fs::read_to_string(path).ok().and_then(non_empty))
+        })
+        .unwrap_or_else(|| UNKNOWN_HOST.to_string())
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Heartbeat {
@@ -18,12 +166,15 @@ pub struct Heartbeat {
     pub entity: String,
     #[serde(rename = "type")]
     pub entity_type: String,
+    #[serde(deserialize_with = "deserialize_time")]
     pub time: f64,
     pub project: Option<String>,
     pub branch: Option<String>,
     pub language: Option<String>,
     pub is_write: bool,
     pub lines: Option<i32>,
+    /// Total lines in the file, as opposed to `lines` changed
+    pub lines_in_file: Option<i32>,
     pub lineno: Option<i32>,
     pub cursorpos: Option<i32>,
     pub user_agent: Option<String>,
@@ -42,13 +193,339 @@ pub struct Heartbeat {
     pub repository_url: Option<String>,
 
     pub dependencies: Vec<String>,
+
+    /// Root folder of the project, for `hide_project_folder`. Only kept in
+    /// memory; it is never queued or sent.
+    #[serde(skip)]
+    pub project_root: Option<String>,
+
+    /// Fields this CLI doesn't know, e.g. added by a `pre_send_hook`. They are
+    /// queued and sent as they are.
+    #[serde(flatten, default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Width of the time bucket in [`Heartbeat::logical_key`]
+pub const LOGICAL_TIME_BUCKET_SECONDS: i64 = 60;
+
+/// What makes two heartbeats "the same": entity, entity type, time bucket,
+/// project and whether it was a write
+pub type LogicalKey<'a> = (&'a str, &'a str, i64, Option<&'a str>, bool);
+
+impl Heartbeat {
+    /// Start building a heartbeat for `entity` at `time` (Unix seconds)
+    pub fn builder(entity: impl Into<String>, time: f64) -> HeartbeatBuilder {
+        HeartbeatBuilder::new(entity, time)
+    }
+
+    /// Logical identity of this heartbeat, with time in
+    /// [`LOGICAL_TIME_BUCKET_SECONDS`] buckets. Id, user agent and the other
+    /// descriptive fields don't take part.
+    pub fn logical_key(&self) -> LogicalKey<'_> {
+        self.logical_key_with_bucket(LOGICAL_TIME_BUCKET_SECONDS)
+    }
+
+    /// Like [`logical_key`](Self::logical_key) with `bucket_seconds` wide time buckets
+    pub fn logical_key_with_bucket(&self, bucket_seconds: i64) -> LogicalKey<'_> {
+        let bucket = (self.time / bucket_seconds.max(1) as f64).floor() as i64;
+        (
+            &self.entity,
+            &self.entity_type,
+            bucket,
+            self.project.as_deref(),
+            self.is_write,
+        )
+    }
+}
+
+impl Heartbeat {
+    /// Copy of this heartbeat with the privacy settings in `config` applied.
+    /// Every path that queues or sends a heartbeat applies it last, after
+    /// detection has seen the real path. The transforms run in this order:
+    ///
+    /// 1. `disable_git_info` drops the commit hash, author and message and the
+    ///    repository URL; `hide_commit_hash`, `hide_commit_author`,
+    ///    `hide_commit_message` and `hide_repository_url` drop one each
+    /// 2. `hide_branch_names` drops the branch
+    /// 3. `hide_project_folder` makes a file entity relative to its project root
+    /// 4. `hide_file_names` sends a file entity as `HIDDEN.<ext>` and drops
+    ///    line counts, the cursor position and dependencies
+    /// 5. `hide_project_names` replaces the project with a pseudonym derived
+    ///    from it, so one project keeps one pseudonym
+    pub fn redacted(&self, config: &Config) -> Heartbeat {
+        let mut heartbeat = self.clone();
+        let is_file = heartbeat.entity_type == "file";
+
+        if config.disable_git_info || config.hide_commit_hash {
+            heartbeat.commit_hash = None;
+        }
+        if config.disable_git_info || config.hide_commit_author {
+            heartbeat.commit_author = None;
+        }
+        if config.disable_git_info || config.hide_commit_message {
+            heartbeat.commit_message = None;
+        }
+        if config.disable_git_info || config.hide_repository_url {
+            heartbeat.repository_url = None;
+        }
+
+        if config.hide_branch_names {
+            heartbeat.branch = None;
+        }
+
+        if config.hide_project_folder && is_file {
+            if let Some(relative) = heartbeat
+                .project_root
+                .as_deref()
+                .and_then(|root| {
+                    std::path::Path::new(&heartbeat.entity)
+                        .strip_prefix(root)
+                        .ok()
+                })
+                .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+                .filter(|relative| !relative.is_empty())
+            {
+                heartbeat.entity = relative;
+            }
+        }
+
+        if config.hide_file_names && is_file {
+            heartbeat.entity = match std::path::Path::new(&heartbeat.entity).extension() {
+                Some(ext) => format!("{}.{}", HIDDEN_FILE_NAME, ext.to_string_lossy()),
+                None => HIDDEN_FILE_NAME.to_string(),
+            };
+            heartbeat.lines = None;
+            heartbeat.lines_in_file = None;
+            heartbeat.lineno = None;
+            heartbeat.cursorpos = None;
+            heartbeat.dependencies.clear();
+        }
+
+        if config.hide_project_names {
+            heartbeat.project = heartbeat.project.as_deref().map(|project| {
+                let digest = Uuid::new_v5(&Uuid::NAMESPACE_OID, project.as_bytes()).simple();
+                format!("project-{}", &digest.to_string()[..12])
+            });
+        }
+
+        heartbeat
+    }
+}
+
+/// Heartbeats compare by [`Heartbeat::logical_key`]
+impl PartialEq for Heartbeat {
+    fn eq(&self, other: &Self) -> bool {
+        self.logical_key() == other.logical_key()
+    }
+}
+
+impl Eq for Heartbeat {}
+
+impl std::hash::Hash for Heartbeat {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.logical_key().hash(state);
+    }
+}
+
+/// Chainable constructor for [`Heartbeat`].
+///
+/// Defaults to a fresh UUID, entity type `file`, a non-write heartbeat and every
+/// optional field unset, so call sites only spell out what they know.
+#[derive(Debug, Clone)]
+pub struct HeartbeatBuilder {
+    heartbeat: Heartbeat,
+}
+
+impl HeartbeatBuilder {
+    pub fn new(entity: impl Into<String>, time: f64) -> Self {
+        Self {
+            heartbeat: Heartbeat {
+                id: Uuid::new_v4().to_string(),
+                entity: entity.into(),
+                entity_type: "file".to_string(),
+                time: normalize_heartbeat_time(time),
+                project: None,
+                branch: None,
+                language: None,
+                is_write: false,
+                lines: None,
+                lines_in_file: None,
+                lineno: None,
+                cursorpos: None,
+                user_agent: None,
+                category: None,
+                machine: None,
+                editor: None,
+                operating_system: None,
+                commit_hash: None,
+                commit_author: None,
+                commit_message: None,
+                repository_url: None,
+                dependencies: Vec::new(),
+                project_root: None,
+                extra: serde_json::Map::new(),
+            },
+        }
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.heartbeat.id = id.into();
+        self
+    }
+
+    pub fn entity_type(mut self, entity_type: impl Into<String>) -> Self {
+        self.heartbeat.entity_type = entity_type.into();
+        self
+    }
+
+    pub fn project(mut self, project: impl Into<Option<String>>) -> Self {
+        self.heartbeat.project = project.into();
+        self
+    }
+
+    pub fn branch(mut self, branch: impl Into<Option<String>>) -> Self {
+        self.heartbeat.branch = branch.into();
+        self
+    }
+
+    pub fn language(mut self, language: impl Into<Option<String>>) -> Self {
+        self.heartbeat.language = language.into();
+        self
+    }
+
+    pub fn is_write(mut self, is_write: bool) -> Self {
+        self.heartbeat.is_write = is_write;
+        self
+    }
+
+    pub fn lines(mut self, lines: impl Into<Option<i32>>) -> Self {
+        self.heartbeat.lines = lines.into();
+        self
+    }
+
+    pub fn lines_in_file(mut self, lines_in_file: impl Into<Option<i32>>) -> Self {
+        self.heartbeat.lines_in_file = lines_in_file.into();
+        self
+    }
+
+    pub fn lineno(mut self, lineno: impl Into<Option<i32>>) -> Self {
+        self.heartbeat.lineno = lineno.into();
+        self
+    }
+
+    pub fn cursorpos(mut self, cursorpos: impl Into<Option<i32>>) -> Self {
+        self.heartbeat.cursorpos = cursorpos.into();
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<Option<String>>) -> Self {
+        self.heartbeat.user_agent = user_agent.into();
+        self
+    }
+
+    pub fn category(mut self, category: impl Into<Option<String>>) -> Self {
+        self.heartbeat.category = category.into();
+        self
+    }
+
+    pub fn machine(mut self, machine: impl Into<Option<String>>) -> Self {
+        self.heartbeat.machine = machine.into();
+        self
+    }
+
+    pub fn editor(mut self, editor: impl Into<Option<EditorInfo>>) -> Self {
+        self.heartbeat.editor = editor.into();
+        self
+    }
+
+    pub fn operating_system(mut self, operating_system: impl Into<Option<OsInfo>>) -> Self {
+        self.heartbeat.operating_system = operating_system.into();
+        self
+    }
+
+    pub fn commit_hash(mut self, commit_hash: impl Into<Option<String>>) -> Self {
+        self.heartbeat.commit_hash = commit_hash.into();
+        self
+    }
+
+    pub fn commit_author(mut self, commit_author: impl Into<Option<String>>) -> Self {
+        self.heartbeat.commit_author = commit_author.into();
+        self
+    }
+
+    pub fn commit_message(mut self, commit_message: impl Into<Option<String>>) -> Self {
+        self.heartbeat.commit_message = commit_message.into();
+        self
+    }
+
+    pub fn repository_url(mut self, repository_url: impl Into<Option<String>>) -> Self {
+        self.heartbeat.repository_url = repository_url.into();
+        self
+    }
+
+    pub fn dependencies(mut self, dependencies: Vec<String>) -> Self {
+        self.heartbeat.dependencies = dependencies;
+        self
+    }
+
+    pub fn project_root(mut self, project_root: impl Into<Option<String>>) -> Self {
+        self.heartbeat.project_root = project_root.into();
+        self
+    }
+
+    pub fn build(self) -> Heartbeat {
+        self.heartbeat
+    }
+}
+
+/// A heartbeat time changed by `--fix-timestamps`
+#[derive(Debug, Clone, Serialize)]
+pub struct TimestampFix {
+    pub id: String,
+    pub entity: String,
+    pub old_time: f64,
+    pub new_time: f64,
+}
+
+/// A heartbeat project changed by `--repair-worktree-paths`
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectRepair {
+    pub id: String,
+    pub entity: String,
+    pub old_project: Option<String>,
+    pub new_project: String,
+}
+
+/// Largest batch `--catch-up` sends, whatever the server advertises
+const CATCH_UP_MAX_BATCH_SIZE: usize = 1000;
+
+/// Rate-limited bulk uploads tried per batch before `--catch-up` falls back
+/// to regular sync
+const CATCH_UP_RATE_LIMIT_ATTEMPTS: u32 = 3;
+
+/// What one call to `sync_queued` did with its batch
+#[derive(Debug, Default)]
+struct BatchOutcome {
+    synced: usize,
+    failed: usize,
+    /// Backoff the server asked for; the unsent heartbeats are back in pending
+    rate_limited: Option<std::time::Duration>,
+}
+
+/// Progress of `--catch-up`, reported after each upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CatchUpProgress {
+    pub synced: usize,
+    pub failed: usize,
+    /// Heartbeats still pending
+    pub remaining: usize,
 }
 
 pub struct HeartbeatManager {
     config: Config,
     api_client: ApiClient,
-    authenticated_api_client: Option<crate::api::AuthenticatedApiClient>,
-    queue: Queue,
+    authenticated_api_client: Option<Arc<crate::api::AuthenticatedApiClient>>,
+    queue: SharedQueue,
     collector: DataCollector,
 }
 
@@ -67,45 +544,185 @@ pub struct OsInfo {
     pub version: Option<String>,
 }
 
-impl HeartbeatManager {
-    pub fn new(config: Config) -> Self {
-        let api_client = ApiClient::new(config.get_api_url());
-        let authenticated_api_client = config
-            .get_api_key(None)
-            .map(|key| api_client.clone().with_api_key(key));
-        let queue = Queue::new().expect("Failed to initialize queue");
-        // Ensure a fresh queue state for newly constructed managers (helps tests/isolation)
-        // Ignore any error here — best effort cleanup to avoid leaking state between runs.
-        let _ = queue.cleanup_old_entries(0);
-        let collector = DataCollector::new();
+/// Heartbeat in the WakaTime bulk format, as sent by editor plugins through
+/// `--extra-heartbeats` and written by `--export-heartbeats`. Only `entity`
+/// and `time` are required; queue-internal fields such as `id` are omitted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkHeartbeat {
+    pub entity: String,
+    #[serde(rename = "type", default = "default_entity_type")]
+    pub entity_type: String,
+    #[serde(deserialize_with = "deserialize_time")]
+    pub time: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub is_write: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines_in_file: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lineno: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursorpos: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+fn default_entity_type() -> String {
+    "file".to_string()
+}
 
+impl From<&Heartbeat> for BulkHeartbeat {
+    fn from(heartbeat: &Heartbeat) -> Self {
         Self {
-            config,
-            api_client,
-            authenticated_api_client,
-            queue,
-            collector,
+            entity: heartbeat.entity.clone(),
+            entity_type: heartbeat.entity_type.clone(),
+            time: heartbeat.time,
+            project: heartbeat.project.clone(),
+            branch: heartbeat.branch.clone(),
+            language: heartbeat.language.clone(),
+            is_write: heartbeat.is_write,
+            lines: heartbeat.lines,
+            lines_in_file: heartbeat.lines_in_file,
+            lineno: heartbeat.lineno,
+            cursorpos: heartbeat.cursorpos,
+            user_agent: heartbeat.user_agent.clone(),
+            category: heartbeat.category.clone(),
+            machine: heartbeat.machine.clone(),
+            dependencies: heartbeat.dependencies.clone(),
         }
     }
+}
+
+impl From<BulkHeartbeat> for Heartbeat {
+    fn from(bulk: BulkHeartbeat) -> Self {
+        Heartbeat::builder(bulk.entity, bulk.time)
+            .entity_type(bulk.entity_type)
+            .project(bulk.project)
+            .branch(bulk.branch)
+            .language(bulk.language)
+            .is_write(bulk.is_write)
+            .lines(bulk.lines)
+            .lines_in_file(bulk.lines_in_file)
+            .lineno(bulk.lineno)
+            .cursorpos(bulk.cursorpos)
+            .user_agent(bulk.user_agent)
+            .category(bulk.category)
+            .machine(bulk.machine)
+            .dependencies(bulk.dependencies)
+            .build()
+    }
+}
+
+impl HeartbeatManager {
+    /// Create a HeartbeatManager on the default queue database. Fails when the
+    /// queue cannot be opened, e.g. because `~/.chronova` is not writable, or
+    /// when the TLS settings are unusable, e.g. `ssl_certs_file` is missing.
+    /// With `sync_enabled = false` the queue is kept in memory and no database
+    /// is created.
+    pub fn new(config: Config) -> Result<Self, anyhow::Error> {
+        let queue = if config.sync_config.enabled {
+            let queue = Queue::new()?
+                .with_ttl_days(config.heartbeat_ttl_days)
+                .with_compression(config.compress_queue);
+            if config.compress_queue {
+                queue.compress_existing()?;
+            }
+            queue
+        } else {
+            Queue::in_memory()?
+        };
+        Ok(Self::new_with_queue(config, queue)?)
+    }
 
     /// Create a HeartbeatManager with a custom queue (useful for testing with isolated queues)
-    pub fn new_with_queue(config: Config, queue: Queue) -> Self {
-        let api_client = ApiClient::new(config.get_api_url());
-        let authenticated_api_client = config
-            .get_api_key(None)
-            .map(|key| api_client.clone().with_api_key(key));
-        let collector = DataCollector::new();
+    pub fn new_with_queue(config: Config, queue: Queue) -> Result<Self, crate::api::ApiError> {
+        Self::with_shared(config, None, Arc::new(Mutex::new(queue)))
+    }
 
-        Self {
+    /// Create a HeartbeatManager that reuses an existing API client and queue.
+    ///
+    /// Long-running processes construct these once and hand clones of the `Arc`s
+    /// to every manager instead of rebuilding connection pools and reopening SQLite.
+    /// Without a shared client one is built from `config`, which fails with
+    /// [`ApiError::Tls`](crate::api::ApiError::Tls) when the TLS settings are unusable.
+    pub fn with_shared(
+        config: Config,
+        authenticated_api_client: Option<Arc<crate::api::AuthenticatedApiClient>>,
+        queue: SharedQueue,
+    ) -> Result<Self, crate::api::ApiError> {
+        let (api_client, authenticated_api_client) = match authenticated_api_client {
+            Some(client) => (client.unauthenticated(), Some(client)),
+            None => {
+                let api_client = ApiClient::from_config(&config)?;
+                let authenticated_api_client = config.get_api_key(None).map(|key| {
+                    let mut client = api_client.clone().with_api_key(key);
+                    if let Some(path) = crate::server_caps::ServerCaps::default_path() {
+                        client = client.with_server_caps(crate::server_caps::ServerCaps::new(path));
+                    }
+                    Arc::new(client)
+                });
+                (api_client, authenticated_api_client)
+            }
+        };
+        let mut collector = DataCollector::new()
+            .with_max_detect_file_bytes(config.max_detect_file_bytes)
+            .with_preferred_remote(config.preferred_remote.clone())
+            .with_project_markers(&config.project_markers);
+        if let Some(ceiling) = &config.project_search_ceiling {
+            collector = collector.with_search_ceiling(ceiling);
+        }
+        if config.project_cache {
+            if let Some(path) = crate::project_cache::ProjectCache::default_path() {
+                collector =
+                    collector.with_project_cache(crate::project_cache::ProjectCache::new(path));
+            }
+        }
+
+        Ok(Self {
             config,
             api_client,
             authenticated_api_client,
             queue,
             collector,
-        }
+        })
+    }
+
+    /// Shared handle to the authenticated API client, if an API key is configured
+    pub fn api_client_handle(&self) -> Option<Arc<crate::api::AuthenticatedApiClient>> {
+        self.authenticated_api_client.clone()
+    }
+
+    /// Shared handle to this manager's queue
+    pub fn queue_handle(&self) -> SharedQueue {
+        Arc::clone(&self.queue)
+    }
+
+    fn queue(&self) -> MutexGuard<'_, Queue> {
+        // A panic while holding the lock leaves the connection itself usable
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
     }
 
-    pub async fn process(&self, mut cli: Cli) -> Result<(), anyhow::Error> {
+    pub async fn process(&self, cli: Cli) -> Result<(), anyhow::Error> {
+        // Every log line about this heartbeat, from detection to sync, carries its id
+        let id = Uuid::new_v4().to_string();
+        let span = tracing::info_span!("heartbeat", heartbeat_id = %id);
+        self.process_as(cli, id).instrument(span).await
+    }
+
+    async fn process_as(&self, mut cli: Cli, id: String) -> Result<(), anyhow::Error> {
         // Entity is guaranteed to be Some at this point (checked in main)
         let entity = cli.entity.take().expect("Entity should be present");
 
@@ -116,579 +733,2541 @@ impl HeartbeatManager {
         }
 
         // Create heartbeat from CLI arguments
-        let heartbeat = self.create_heartbeat(cli, entity).await?;
+        let mut heartbeat = self.create_heartbeat(cli, entity).await?;
+        heartbeat.id = id;
+
+        if self.should_skip_empty(&heartbeat) {
+            tracing::debug!("Skipping empty file heartbeat: {}", heartbeat.entity);
+            return Ok(());
+        }
+        if self.should_sample_out(&heartbeat) {
+            tracing::debug!("Sampled out heartbeat: {}", heartbeat.entity);
+            return Ok(());
+        }
+        if self.is_too_far_in_future(&heartbeat) {
+            return Ok(());
+        }
+        let heartbeat = self.finalize(heartbeat).await;
+
+        // Without the offline queue the heartbeat is sent once and not kept
+        if !self.offline_queue_enabled() {
+            return self.send_direct(&heartbeat).await;
+        }
+
+        match self.config.dispatch_mode {
+            DispatchMode::Immediate => return self.send_immediate(heartbeat).await,
+            DispatchMode::Queued => {
+                self.enqueue(vec![heartbeat]).await?;
+                tracing::debug!(
+                    "Heartbeat queued; dispatch_mode = queued leaves syncing for later"
+                );
+                return Ok(());
+            }
+            DispatchMode::Hybrid => {}
+        }
 
         // Use offline-first strategy: always queue first, then try to sync
-        // Offload SQLite work to a blocking thread to avoid blocking the async runtime.
-        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-            let q = crate::queue::Queue::new().map_err(|e| anyhow::anyhow!(e))?;
-            q.add(heartbeat).map_err(|e| anyhow::anyhow!(e))?;
-            Ok(())
-        })
-        .await??;
+        self.enqueue(vec![heartbeat]).await?;
         tracing::debug!("Heartbeat queued for offline-first processing");
 
+        // With single_instance, leave syncing to an invocation already doing it
+        let _sync_lock = if self.config.single_instance {
+            match crate::instance_lock::SyncLock::default_path()
+                .map(|path| crate::instance_lock::SyncLock::try_acquire(&path))
+            {
+                Some(Ok(Some(lock))) => Some(lock),
+                Some(Ok(None)) => {
+                    tracing::debug!("Another invocation is syncing; heartbeat left queued");
+                    return Ok(());
+                }
+                Some(Err(e)) => {
+                    tracing::warn!("Failed to take the sync lock, syncing anyway: {}", e);
+                    None
+                }
+                None => None,
+            }
+        } else {
+            None
+        };
+
         // Process any queued heartbeats using sync strategy
         let (_synced_count, _failed_count) = self.process_queue().await?;
 
         Ok(())
     }
 
-    async fn create_heartbeat(&self, cli: Cli, entity: String) -> Result<Heartbeat, anyhow::Error> {
-        let time = cli
-            .time
-            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis() as f64 / 1000.0);
-
-        // Collect additional data
-        let project_info = self.collector.detect_project(&entity).await;
-        let git_info = self.collector.detect_git_info(&entity).await;
-        let language = self.collector.detect_language(&entity).await;
-
-        // Parse plugin info for user agent
-        // Note: We no longer parse plugin info here as the API handles this
-
-        // Determine project name with priority: cli.project > alternate_project > detected project
-        let project_name = cli.project.or(cli.alternate_project).or_else(|| {
-            project_info.as_ref().map(|p| {
-                p.root
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|| "unknown".to_string())
-            })
-        });
-
-        // Determine branch with priority: cli.branch > git branch
-        let branch = cli
-            .branch
-            .or_else(|| git_info.as_ref().and_then(|g| g.branch.clone()));
-
-        // Determine language with priority: cli.language > detected language
-        let language_name = cli.language.or(language);
-
-        Ok(Heartbeat {
-            id: Uuid::new_v4().to_string(),
-            entity,
-            entity_type: cli.entity_type,
-            time,
-            project: project_name,
-            branch,
-            language: language_name,
-            is_write: cli.write.unwrap_or(false),
-            lines: cli.lines,
-            lineno: cli.lineno,
-            cursorpos: cli.cursorpos,
-            user_agent: Some(generate_user_agent(cli.plugin.as_deref())),
-            category: cli.category,
-            machine: cli
-                .hostname
-                .or_else(|| Some(gethostname::gethostname().to_string_lossy().into_owned())),
-            editor: None,
-            operating_system: None,
-            commit_hash: if self.config.disable_git_info || self.config.hide_commit_hash {
-                None
-            } else {
-                git_info.as_ref().and_then(|g| g.commit_hash.clone())
-            },
-            commit_author: if self.config.disable_git_info || self.config.hide_commit_author {
-                None
-            } else {
-                git_info.as_ref().and_then(|g| g.commit_author.clone())
-            },
-            commit_message: if self.config.disable_git_info || self.config.hide_commit_message {
-                None
-            } else {
-                git_info.as_ref().and_then(|g| g.commit_message.clone())
-            },
-            repository_url: if self.config.disable_git_info || self.config.hide_repository_url {
-                None
-            } else {
-                git_info.as_ref().and_then(|g| g.repository_url.clone())
-            },
-            dependencies: Vec::new(),
+    /// Add `heartbeats` to the offline queue in one transaction, coalescing
+    /// rapid writes per `write_rate_limit_seconds`. Every path that queues
+    /// heartbeats ends here. SQLite work is offloaded to a blocking thread to
+    /// avoid blocking the async runtime.
+    async fn enqueue(&self, heartbeats: Vec<Heartbeat>) -> Result<(), anyhow::Error> {
+        let queue = self.queue_handle();
+        let write_window = self.config.write_rate_limit_seconds;
+        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+            let q = queue.lock().unwrap_or_else(|e| e.into_inner());
+            let (writes, others): (Vec<_>, Vec<_>) = heartbeats
+                .into_iter()
+                .partition(|heartbeat| heartbeat.is_write && write_window > 0);
+            q.with_transaction(|tx| {
+                match <[Heartbeat; 1]>::try_from(others) {
+                    Ok([heartbeat]) => tx.add(heartbeat)?,
+                    Err(others) => tx.add_batch(others)?,
+                }
+                for heartbeat in writes {
+                    tx.add_coalescing_writes(heartbeat, write_window)?;
+                }
+                Ok(())
+            })?;
+            Ok(())
         })
+        .await?
     }
 
-    fn should_ignore_entity(&self, entity: &str) -> bool {
-        // Simple pattern matching for ignore rules
-        for pattern in &self.config.ignore_patterns {
-            if pattern.ends_with('$') {
-                // Exact match at end
-                let base_pattern = &pattern[..pattern.len() - 1];
-                if entity.ends_with(base_pattern) {
-                    return true;
+    /// Send `heartbeat` right away for `dispatch_mode = immediate`, retrying
+    /// transient failures with the configured backoff. A heartbeat that still
+    /// isn't accepted is queued for a later sync instead of being dropped.
+    async fn send_immediate(&self, heartbeat: Heartbeat) -> Result<(), anyhow::Error> {
+        let strategy = crate::sync::RetryStrategy {
+            base_delay_seconds: self.config.sync_config.retry_base_delay_seconds,
+            max_attempts: IMMEDIATE_SEND_ATTEMPTS,
+            max_delay_seconds: self.config.sync_config.retry_max_delay_seconds,
+            use_jitter: self.config.sync_config.retry_use_jitter,
+        };
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = match &self.authenticated_api_client {
+                Some(client) => client.send_heartbeat(&heartbeat).await,
+                None => self.api_client.send_heartbeat(&heartbeat).await,
+            };
+            match result {
+                Ok(response) => {
+                    tracing::debug!(
+                        "Heartbeat sent immediately with status {}",
+                        response.status()
+                    );
+                    return Ok(());
+                }
+                Err(e @ (crate::api::ApiError::Auth(_) | crate::api::ApiError::Tls(_))) => {
+                    tracing::warn!("Immediate send failed, queueing heartbeat: {}", e);
+                    break;
                 }
-            } else if let Some(extension) = pattern.strip_prefix("*.") {
-                // File extension pattern
-                if entity.ends_with(extension) {
-                    return true;
+                Err(e) if strategy.should_retry(attempt) => {
+                    let delay = strategy.calculate_delay(attempt);
+                    tracing::debug!(
+                        "Immediate send attempt {} failed, retrying in {:?}: {}",
+                        attempt,
+                        delay,
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Immediate send failed after {} attempts, queueing heartbeat: {}",
+                        attempt,
+                        e
+                    );
+                    break;
                 }
-            } else if entity.contains(pattern) {
-                return true;
             }
         }
-        false
+
+        self.enqueue(vec![heartbeat]).await
     }
 
-    async fn process_queue(&self) -> Result<(usize, usize), anyhow::Error> {
-        // Process the queue in batches to avoid loading everything into memory at once.
-        // Combine the "prepare retry-eligible failures" pass and the "fetch pending" call
-        // into a single blocking task so the DB is opened only once per loop iteration.
+    /// Whether heartbeats go through the offline queue; with `sync_enabled = false`
+    /// or `disable_offline` they are sent once and not kept
+    fn offline_queue_enabled(&self) -> bool {
+        self.config.sync_config.enabled && !self.config.disable_offline
+    }
+
+    /// Queue `heartbeats`, or send each straight to the API when the offline
+    /// queue is off
+    async fn queue_or_send(&self, heartbeats: Vec<Heartbeat>) -> Result<(), anyhow::Error> {
+        if !self.offline_queue_enabled() {
+            for heartbeat in &heartbeats {
+                self.send_direct(heartbeat).await?;
+            }
+            return Ok(());
+        }
+        self.enqueue(heartbeats).await
+    }
+
+    /// Send `heartbeat` straight to the API, bypassing the offline queue
+    async fn send_direct(&self, heartbeat: &Heartbeat) -> Result<(), anyhow::Error> {
+        let response = match &self.authenticated_api_client {
+            Some(client) => client.send_heartbeat(heartbeat).await?,
+            None => self.api_client.send_heartbeat(heartbeat).await?,
+        };
+        tracing::debug!("Heartbeat sent directly with status {}", response.status());
+        Ok(())
+    }
+
+    /// Build the heartbeat `process` would send, without queueing or sending it.
+    /// Returns `None` when the entity matches an ignore pattern.
+    pub async fn dry_run(&self, mut cli: Cli) -> Result<Option<Heartbeat>, anyhow::Error> {
+        let entity = cli
+            .entity
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("--entity argument is required"))?;
+
+        if self.should_ignore_entity(&entity) {
+            return Ok(None);
+        }
+
+        let heartbeat = self.create_heartbeat(cli, entity).await?;
+        Ok(Some(heartbeat.redacted(&self.config)))
+    }
+
+    /// Run the `pre_send_hook`, then apply the privacy settings: the last step
+    /// before a heartbeat is queued or sent
+    async fn finalize(&self, heartbeat: Heartbeat) -> Heartbeat {
+        // Hook output doesn't carry the project root
+        let project_root = heartbeat.project_root.clone();
+        let mut heartbeat = self.apply_pre_send_hook(heartbeat).await;
+        heartbeat.project_root = project_root;
+        heartbeat.redacted(&self.config)
+    }
+
+    /// Pipe `heartbeat` through the configured `pre_send_hook`, if any
+    async fn apply_pre_send_hook(&self, heartbeat: Heartbeat) -> Heartbeat {
+        match &self.config.pre_send_hook {
+            Some(command) => {
+                crate::hook::apply(command, heartbeat, crate::hook::PRE_SEND_HOOK_TIMEOUT).await
+            }
+            None => heartbeat,
+        }
+    }
+
+    /// Explain how project, git and language detection resolve for `entity`
+    pub fn detection_diagnostics(&self, entity: &str) -> Vec<String> {
+        self.collector.detection_diagnostics(entity)
+    }
+
+    async fn create_heartbeat(&self, cli: Cli, entity: String) -> Result<Heartbeat, anyhow::Error> {
+        if entity.contains(char::REPLACEMENT_CHARACTER) {
+            tracing::warn!(
+                "Entity {} was not valid UTF-8 and was converted lossily; detection is best-effort",
+                entity
+            );
+        }
+
+        let time = cli
+            .time
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis() as f64 / 1000.0);
+
+        // Follow symlinks up front so git and marker detection see the same path
+        let entity = if self.config.resolve_symlinks {
+            std::fs::canonicalize(&entity)
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or(entity)
+        } else {
+            entity
+        };
+
+        // Collect additional data concurrently; partial results are kept on timeout
+        let (project_info, git_info, language) =
+            self.collector.detect_all(&entity, DETECTION_TIMEOUT).await;
+
+        // Unmarked trees under projects_root are named by their path depth
+        let depth_project = match (
+            self.config.project_from_path_depth,
+            self.config.projects_root.as_deref(),
+        ) {
+            (Some(depth), Some(root)) if !self.collector.has_project_marker(&entity) => {
+                self.collector.project_from_path_depth(&entity, root, depth)
+            }
+            _ => None,
+        };
+
+        // Determine project name with priority:
+        // cli.project > alternate_project > path depth fallback > detected project.
+        // Only names that weren't given explicitly are sanitized.
+        let project_name = cli.project.or_else(|| {
+            cli.alternate_project
+                .or(depth_project)
+                .or_else(|| {
+                    project_info.as_ref().map(|p| {
+                        p.root
+                            .file_name()
+                            .and_then(|n| n.to_str())
+                            .map(|s| s.to_string())
+                            .unwrap_or_else(|| "unknown".to_string())
+                    })
+                })
+                .map(|name| {
+                    crate::project_name::sanitize(&name, &self.config.project_name_rewrites)
+                })
+        });
+
+        // Determine branch with priority: cli.branch > git branch
+        let branch = cli
+            .branch
+            .or_else(|| git_info.as_ref().and_then(|g| g.branch.clone()));
+
+        // Determine language with priority: cli.language > detected language >
+        // default_language for files
+        let language_name = cli.language.or(language).or_else(|| {
+            (cli.entity_type == "file")
+                .then(|| self.config.default_language.clone())
+                .flatten()
+        });
+
+        // Hidden commit metadata is dropped later by `Heartbeat::redacted`
+        let git_field = |field: fn(&crate::collector::GitInfo) -> Option<String>| {
+            git_info.as_ref().and_then(field)
+        };
+
+        // The editor comes from --plugin; without one, the parent process may reveal it
+        let plugin = cli.plugin.as_deref().and_then(parse_plugin);
+        let editor = if let Some(plugin) = plugin {
+            Some(EditorInfo {
+                name: plugin.editor,
+                version: plugin.editor_version,
+            })
+        } else if cli.plugin.is_none() && self.config.detect_editor_from_process {
+            self.collector
+                .detect_editor_from_process()
+                .map(|name| EditorInfo {
+                    name,
+                    version: None,
+                })
+        } else {
+            None
+        };
+
+        // Infer a category for files sent without one
+        let is_write = cli.write.unwrap_or(false);
+        let category = cli.category.or_else(|| {
+            (cli.entity_type == "file").then(|| {
+                crate::category::infer_category(&self.config.category_rules, &entity, is_write)
+            })
+        });
+
+        // Total lines of the file on disk, bounded by the detection size cap
+        let lines_in_file = if cli.entity_type == "file" {
+            self.collector.count_lines(std::path::Path::new(&entity))
+        } else {
+            None
+        };
+        // Editors that omit --lines get the file's total
+        let lines = cli.lines.or(lines_in_file);
+
+        Ok(Heartbeat::builder(entity, time)
+            .entity_type(cli.entity_type)
+            .project(project_name)
+            .branch(branch)
+            .language(language_name)
+            .is_write(is_write)
+            .lines(lines)
+            .lines_in_file(lines_in_file)
+            .lineno(cli.lineno)
+            .cursorpos(cli.cursorpos)
+            .user_agent(generate_user_agent(cli.plugin.as_deref()))
+            .category(category)
+            .editor(editor)
+            .machine(resolve_machine_name(
+                cli.hostname.or_else(|| self.config.hostname.clone()),
+                gethostname::gethostname().to_string_lossy().into_owned(),
+            ))
+            .commit_hash(git_field(|g| g.commit_hash.clone()))
+            .commit_author(git_field(|g| g.commit_author.clone()))
+            .commit_message(git_field(|g| g.commit_message.clone()).map(|message| {
+                shorten_commit_message(
+                    &message,
+                    self.config.max_commit_message_len,
+                    self.config.full_commit_message,
+                )
+            }))
+            .repository_url(git_field(|g| g.repository_url.clone()))
+            .project_root(
+                cli.project_folder
+                    .or_else(|| project_info.map(|p| p.root.to_string_lossy().into_owned())),
+            )
+            .build())
+    }
+
+    /// Whether `entity` matches an `exclude` pattern and no `include` pattern
+    fn should_ignore_entity(&self, entity: &str) -> bool {
+        self.config
+            .ignore_patterns
+            .iter()
+            .any(|pattern| entity_matches_pattern(entity, pattern))
+            && !self.is_included_entity(entity)
+    }
+
+    /// Whether `entity` matches an `include` pattern, which wins over `exclude`
+    fn is_included_entity(&self, entity: &str) -> bool {
+        self.config
+            .include_patterns
+            .iter()
+            .any(|pattern| entity_matches_pattern(entity, pattern))
+    }
+
+    /// With `sample_rate` below 1, drop that share of non-write heartbeats.
+    /// The first heartbeat after an idle gap is always kept so sessions keep
+    /// their start. Otherwise the draw is seeded from the entity and time, so
+    /// the same heartbeat is always kept or always dropped.
+    fn should_sample_out(&self, heartbeat: &Heartbeat) -> bool {
+        let rate = self.config.sample_rate;
+        if rate >= 1.0 {
+            return false;
+        }
+
+        let previous = self
+            .queue()
+            .record_activity(heartbeat.time)
+            .unwrap_or_else(|e| {
+                tracing::debug!("Failed to record heartbeat activity: {}", e);
+                None
+            });
+        let session_start =
+            previous.is_none_or(|last| heartbeat.time - last >= SAMPLE_SESSION_GAP_SECONDS);
+        if heartbeat.is_write || session_start {
+            return false;
+        }
+        sample_draw(&heartbeat.entity, heartbeat.time) >= rate
+    }
+
+    /// Whether `heartbeat` is timed more than `max_future_skew_seconds` ahead
+    /// of the local clock. Such heartbeats are dropped with a warning; smaller
+    /// offsets are ordinary clock jitter and kept as they are.
+    fn is_too_far_in_future(&self, heartbeat: &Heartbeat) -> bool {
+        let now = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+        let skew = heartbeat.time - now;
+        if skew <= self.config.max_future_skew_seconds as f64 {
+            return false;
+        }
+        tracing::warn!(
+            "Dropping heartbeat for {}: its time is {:.0}s in the future (max_future_skew_seconds = {})",
+            heartbeat.entity,
+            skew,
+            self.config.max_future_skew_seconds
+        );
+        true
+    }
+
+    /// With `skip_empty_files`, drop non-write heartbeats for files with zero
+    /// lines (or zero bytes on disk). Writes still count so newly created files
+    /// the user is actively saving are tracked.
+    fn should_skip_empty(&self, heartbeat: &Heartbeat) -> bool {
+        if !self.config.skip_empty_files || heartbeat.is_write || heartbeat.entity_type != "file" {
+            return false;
+        }
+
+        match heartbeat.lines {
+            Some(lines) => lines == 0,
+            None => std::fs::metadata(&heartbeat.entity)
+                .map(|m| m.is_file() && m.len() == 0)
+                .unwrap_or(false),
+        }
+    }
+
+    async fn process_queue(&self) -> Result<(usize, usize), anyhow::Error> {
+        // Process the queue in batches to avoid loading everything into memory at once.
         let batch_size: usize = 50;
 
         // Counters to return to callers
         let mut total_synced: usize = 0;
         let mut total_failed: usize = 0;
 
-        loop {
-            // Single blocking operation: prepare retry-eligible failed heartbeats and fetch a batch of pending
-            let queued =
-                tokio::task::spawn_blocking(move || -> Result<Vec<Heartbeat>, anyhow::Error> {
-                    let q = crate::queue::Queue::new().map_err(|e| anyhow::anyhow!(e))?;
-
-                    // Prepare failed -> pending for retry (single DB connection)
-                    let failed = q
-                        .get_pending(Some(1000), Some(crate::sync::SyncStatus::Failed))
-                        .map_err(|e| anyhow::anyhow!(e))?;
-                    for hb in failed {
-                        let current_retry_count = q.get_retry_count(&hb.id).unwrap_or(0);
-                        if current_retry_count < 3 {
-                            q.update_sync_status(
-                                &hb.id,
-                                crate::sync::SyncStatus::Pending,
-                                Some(format!("Retry eligible (attempt {})", current_retry_count)),
-                            )
-                            .map_err(|e| anyhow::anyhow!(e))?;
-                        }
-                    }
+        // --sync-max-age leaves older heartbeats pending for a later run or export
+        let max_age = self.config.sync_config.max_age();
 
-                    // Now fetch the next batch of pending heartbeats for processing
-                    q.get_pending(Some(batch_size), None)
-                        .map_err(|e| anyhow::anyhow!(e))
-                })
-                .await??;
+        // Failures from earlier runs get one more chance per cycle; doing this once
+        // up front keeps a large failed set from being rescanned for every batch
+        self.prepare_retry_eligible_failures().await?;
 
+        let mut waited_out_rate_limit = false;
+        loop {
+            let queued = self.next_pending_batch(batch_size, max_age).await?;
             if queued.is_empty() {
                 break;
             }
 
-            tracing::info!(
-                "Processing {} queued heartbeats (batch size {})",
-                queued.len(),
-                batch_size
-            );
+            let outcome = self.sync_queued(queued, batch_size).await?;
+            total_synced += outcome.synced;
+            total_failed += outcome.failed;
+
+            if let Some(backoff) = outcome.rate_limited {
+                // Sleep through one short rate limit per run; a longer or repeated
+                // one ends the run with the rest of the queue still pending
+                if waited_out_rate_limit || backoff > MAX_INLINE_BACKOFF {
+                    tracing::warn!(
+                        "Rate limited for {}s, leaving queued heartbeats for the next sync",
+                        backoff.as_secs()
+                    );
+                    break;
+                }
+                tracing::warn!(
+                    "Rate limited, sleeping {}s before retrying",
+                    backoff.as_secs()
+                );
+                tokio::time::sleep(backoff).await;
+                waited_out_rate_limit = true;
+            }
+        }
 
-            // If more than one heartbeat, try to send as a batch for efficiency
-            if queued.len() > 1 {
-                // Mark all as syncing (do it in a single blocking operation)
-                let queued_ids = queued.iter().map(|h| h.id.clone()).collect::<Vec<_>>();
-                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                    let q = crate::queue::Queue::new().map_err(|e| anyhow::anyhow!(e))?;
-                    for id in queued_ids {
-                        let retry_count = q.get_retry_count(&id).map_err(|e| anyhow::anyhow!(e))?;
-                        q.update_sync_status(
-                            &id,
-                            crate::sync::SyncStatus::Syncing,
-                            Some(format!("Attempting sync (attempt {})", retry_count + 1)),
-                        )
-                        .map_err(|e| anyhow::anyhow!(e))?;
+        // Once per cycle, with whatever the sync couldn't deliver still queued
+        let max_queue_size = self.config.sync_config.max_queue_size;
+        let queue = self.queue_handle();
+        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+            let q = queue.lock().unwrap_or_else(|e| e.into_inner());
+            if let Err(e) = q.trim_for_outage(max_queue_size) {
+                tracing::warn!("Failed to trim the offline queue: {}", e);
+            }
+            Ok(())
+        })
+        .await??;
+
+        Ok((total_synced, total_failed))
+    }
+
+    /// Send one batch of queued heartbeats, falling back to one request per
+    /// heartbeat when the batch request fails. A rate limit stops the batch
+    /// without sleeping and hands the unsent heartbeats back to pending.
+    #[tracing::instrument(
+        name = "sync_batch",
+        skip_all,
+        fields(batch_id = %Uuid::new_v4(), size = queued.len())
+    )]
+    async fn sync_queued(
+        &self,
+        queued: Vec<Heartbeat>,
+        batch_size: usize,
+    ) -> Result<BatchOutcome, anyhow::Error> {
+        let mut total_synced: usize = 0;
+        let mut total_failed: usize = 0;
+
+        tracing::info!(
+            "Processing {} queued heartbeats (batch size {})",
+            queued.len(),
+            batch_size
+        );
+
+        // If more than one heartbeat, try to send as a batch for efficiency
+        if queued.len() > 1 {
+            // Log which IDs are being sent in this batch for debugging
+            let queued_ids_dbg = queued.iter().map(|h| h.id.clone()).collect::<Vec<_>>();
+            tracing::debug!("Attempting batch send for ids: {:?}", queued_ids_dbg);
+            let send_result = if let Some(auth_client) = &self.authenticated_api_client {
+                auth_client.send_heartbeats_batch(&queued).await
+            } else {
+                self.api_client.send_heartbeats_batch(&queued).await
+            };
+            tracing::debug!("Batch send result success: {}", send_result.is_ok());
+
+            match send_result {
+                Ok(response) => {
+                    // A 202 may accept the batch while rejecting individual items
+                    let rejections = crate::api::parse_batch_rejections(response).await;
+                    let (queued_ids, rejected) = crate::api::partition_batch(&queued, rejections);
+
+                    // Success: mark accepted items as synced and remove them (single blocking op)
+                    let synced_len = queued_ids.len();
+                    self.finalize_synced(queued_ids).await?;
+
+                    // Account for synced items
+                    total_synced += synced_len;
+
+                    if !rejected.is_empty() {
+                        tracing::warn!(
+                            "Server rejected {} heartbeats in accepted batch",
+                            rejected.len()
+                        );
+                        total_failed += self.record_failures(rejected).await?;
                     }
-                    Ok(())
-                })
-                .await??;
-
-                // Log which IDs are being sent in this batch for debugging
-                let queued_ids_dbg = queued.iter().map(|h| h.id.clone()).collect::<Vec<_>>();
-                tracing::debug!("Attempting batch send for ids: {:?}", queued_ids_dbg);
-                let send_result = if let Some(auth_client) = &self.authenticated_api_client {
-                    auth_client.send_heartbeats_batch(&queued).await
-                } else {
-                    self.api_client.send_heartbeats_batch(&queued).await
-                };
-                tracing::debug!("Batch send result success: {}", send_result.is_ok());
-
-                match send_result {
-                    Ok(_) => {
-                        // Success: mark all as synced and remove them (single blocking op)
-                        let queued_ids = queued.iter().map(|h| h.id.clone()).collect::<Vec<_>>();
-                        let synced_len = queued.len();
-                        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                            let q = crate::queue::Queue::new().map_err(|e| anyhow::anyhow!(e))?;
-                            for id in queued_ids {
-                                q.update_sync_status(
-                                    &id,
-                                    crate::sync::SyncStatus::Synced,
-                                    Some("Successfully synced".to_string()),
-                                )
-                                .map_err(|e| anyhow::anyhow!(e))?;
-                                q.remove(&id).map_err(|e| anyhow::anyhow!(e))?;
-                            }
-                            Ok(())
-                        })
-                        .await??;
 
-                        // Account for synced items
-                        total_synced += synced_len;
+                    // Continue to next batch
+                    return Ok(BatchOutcome {
+                        synced: total_synced,
+                        failed: total_failed,
+                        rate_limited: None,
+                    });
+                }
+                Err(e) => {
+                    // Handle batch-level errors: fall back to per-item retries for anything but rate-limits
+                    tracing::warn!("Batch sync failed: {}", e);
+                    if let crate::api::ApiError::RateLimit(_, retry_after) = e {
+                        // The caller decides whether to wait; the batch goes back to pending
+                        self.release_to_pending(queued.iter().map(|h| h.id.clone()).collect())
+                            .await?;
+                        return Ok(BatchOutcome {
+                            rate_limited: Some(retry_after.unwrap_or(MAX_INLINE_BACKOFF)),
+                            ..BatchOutcome::default()
+                        });
+                    } else {
+                        // For other errors, fall back to per-heartbeat send so we can granularly retry/mark permanent
+                        tracing::debug!("Falling back to per-heartbeat sync after batch failure");
+                    }
+                }
+            }
+        }
+
+        // Process items individually (either because batch failed or batch size == 1)
+        // Collect successful ids to apply final DB updates in a single blocking operation.
+        let mut synced_ids: Vec<String> = Vec::new();
+        // Collect failed items (id, error) to update retry counts/statuses in one DB op.
+        let mut failed_updates: Vec<(String, String)> = Vec::new();
+        // Items the server rejected as invalid, with their field errors
+        let mut invalid_updates: Vec<(String, String, crate::api::FieldErrors)> = Vec::new();
+        // Items left unsent after a rate limit, with the backoff the server asked for
+        let mut unsent: Vec<String> = Vec::new();
+        let mut rate_limited = None;
+        for heartbeat in queued {
+            if rate_limited.is_some() {
+                unsent.push(heartbeat.id.clone());
+                continue;
+            }
+
+            tracing::debug!(
+                "Attempting individual send for heartbeat id: {}",
+                heartbeat.id
+            );
+            let heartbeat_span = tracing::info_span!("heartbeat", heartbeat_id = %heartbeat.id);
+            let send_result = if let Some(auth_client) = &self.authenticated_api_client {
+                auth_client
+                    .send_heartbeat(&heartbeat)
+                    .instrument(heartbeat_span)
+                    .await
+            } else {
+                self.api_client
+                    .send_heartbeat(&heartbeat)
+                    .instrument(heartbeat_span)
+                    .await
+            };
+            tracing::debug!(
+                "Individual send result for {} success: {}",
+                heartbeat.id,
+                send_result.is_ok()
+            );
 
-                        // Continue to next batch
+            match send_result {
+                Ok(_) => {
+                    // Defer DB updates/removal for successful sends and batch-apply later
+                    tracing::debug!("Queued heartbeat marked for finalization: {}", heartbeat.id);
+                    synced_ids.push(heartbeat.id.clone());
+                    total_synced += 1;
+                }
+                Err(e) => {
+                    // Resending a heartbeat the server found invalid can't succeed
+                    if let crate::api::ApiError::Validation(errors) = &e {
+                        tracing::warn!("Heartbeat {} rejected: {}", heartbeat.id, e);
+                        invalid_updates.push((heartbeat.id.clone(), e.to_string(), errors.clone()));
                         continue;
                     }
-                    Err(e) => {
-                        // Handle batch-level errors: fall back to per-item retries with backoff for rate-limits
-                        tracing::warn!("Batch sync failed: {}", e);
-                        if let crate::api::ApiError::RateLimit(_) = e {
-                            // Simple backoff strategy: wait based on queue size to avoid hammering the server
-                            // Note: use bounded backoff here to avoid long blocking in caller
-                            let backoff_secs = 60u64; // base 60s for rate-limits on batch failure
-                            tracing::warn!(
-                                "Rate limited on batch sync, sleeping {}s before retrying batch",
-                                backoff_secs
-                            );
-                            tokio::time::sleep(std::time::Duration::from_secs(backoff_secs)).await;
-                            // After sleeping, retry this batch once more (will loop)
-                            continue;
-                        } else {
-                            // For other errors, fall back to per-heartbeat send so we can granularly retry/mark permanent
-                            tracing::debug!(
-                                "Falling back to per-heartbeat sync after batch failure"
-                            );
-                        }
+
+                    // A rate limit isn't this heartbeat's fault: stop sending and leave
+                    // it and the rest of the batch pending without using up an attempt
+                    if let crate::api::ApiError::RateLimit(_, retry_after) = e {
+                        tracing::warn!("Heartbeat {} rate-limited", heartbeat.id);
+                        rate_limited = Some(retry_after.unwrap_or(MAX_INLINE_BACKOFF));
+                        unsent.push(heartbeat.id.clone());
+                        continue;
+                    }
+
+                    // Defer retry increment and status updates to a consolidated blocking operation
+                    // to avoid opening the DB per-failure and to improve atomicity.
+                    let id = heartbeat.id.clone();
+                    let e_str = format!("{}", e);
+                    failed_updates.push((id, e_str));
+                }
+            }
+        }
+
+        // Consolidate failure updates (increment retry + set status) in one blocking operation
+        if !failed_updates.is_empty() {
+            // Account for newly permanent failures
+            total_failed += self.record_failures(failed_updates).await?;
+        }
+        if !invalid_updates.is_empty() {
+            total_failed += self.record_invalid(invalid_updates).await?;
+        }
+
+        // Apply final DB updates for all successfully synced ids in one blocking operation
+        if !synced_ids.is_empty() {
+            self.finalize_synced(synced_ids).await?;
+        }
+        if !unsent.is_empty() {
+            self.release_to_pending(unsent).await?;
+        }
+
+        Ok(BatchOutcome {
+            synced: total_synced,
+            failed: total_failed,
+            rate_limited,
+        })
+    }
+
+    /// Mark heartbeats the server accepted as synced and remove them
+    async fn finalize_synced(&self, ids: Vec<String>) -> Result<(), anyhow::Error> {
+        let queue = self.queue_handle();
+        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+            let q = queue.lock().unwrap_or_else(|e| e.into_inner());
+            crate::sync::finalize_synced(&q, &ids)?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Number of heartbeats still waiting to be synced
+    async fn pending_count(&self) -> Result<usize, anyhow::Error> {
+        let queue = self.queue_handle();
+        tokio::task::spawn_blocking(move || -> Result<usize, anyhow::Error> {
+            let q = queue.lock().unwrap_or_else(|e| e.into_inner());
+            Ok(q.count_by_status(Some(crate::sync::SyncStatus::Pending))?)
+        })
+        .await?
+    }
+
+    /// Hand claimed heartbeats back to pending without counting an attempt
+    async fn release_to_pending(&self, ids: Vec<String>) -> Result<(), anyhow::Error> {
+        let queue = self.queue_handle();
+        tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
+            let q = queue.lock().unwrap_or_else(|e| e.into_inner());
+            q.with_transaction(|tx| {
+                for id in &ids {
+                    tx.update_sync_status(id, crate::sync::SyncStatus::Pending, None)?;
+                }
+                Ok(())
+            })
+            .map_err(|e| anyhow::anyhow!(e))
+        })
+        .await?
+    }
+
+    /// Next `batch_size` pending heartbeats that still have attempts left, claimed
+    /// for sync. Heartbeats out of attempts are marked permanently failed on the way.
+    async fn next_pending_batch(
+        &self,
+        batch_size: usize,
+        max_age: Option<std::time::Duration>,
+    ) -> Result<Vec<Heartbeat>, anyhow::Error> {
+        // Single blocking operation so the DB is opened only once per batch
+        let queue = self.queue_handle();
+        tokio::task::spawn_blocking(move || -> Result<Vec<Heartbeat>, anyhow::Error> {
+            let q = queue.lock().unwrap_or_else(|e| e.into_inner());
+
+            // Rows left in syncing by a crashed run would otherwise never be sent
+            q.requeue_stale_syncing(crate::sync::SYNCING_STALE_TIMEOUT)
+                .map_err(|e| anyhow::anyhow!(e))?;
+
+            loop {
+                // Claimed in the same transaction as the select, so a concurrent
+                // sync cannot send the same heartbeats
+                let candidates = q
+                    .claim_pending(batch_size, max_age)
+                    .map_err(|e| anyhow::anyhow!(e))?;
+
+                let candidates_len = candidates.len();
+                let mut batch = Vec::with_capacity(candidates_len);
+                for hb in candidates {
+                    let current_retry_count = q.get_retry_count(&hb.id).unwrap_or(0);
+                    if current_retry_count >= 3 {
+                        // Out of attempts; keep it from occupying the head of the queue
+                        q.update_sync_status(
+                            &hb.id,
+                            crate::sync::SyncStatus::PermanentFailure,
+                            Some(
+                                crate::sync::SyncMetadata::note(
+                                    "Out of retry attempts",
+                                    current_retry_count,
+                                )
+                                .to_json(),
+                            ),
+                        )
+                        .map_err(|e| anyhow::anyhow!(e))?;
+                        continue;
                     }
+                    batch.push(hb);
+                }
+
+                // Stop once something is sendable; rows marked permanent are not refetched
+                if candidates_len == 0 || !batch.is_empty() {
+                    return Ok(batch);
                 }
             }
+        })
+        .await?
+    }
 
-            // Process items individually (either because batch failed or batch size == 1)
-            // Collect successful ids to apply final DB updates in a single blocking operation.
-            let mut synced_ids: Vec<String> = Vec::new();
-            // Collect failed items (id, error) to update retry counts/statuses in one DB op.
-            let mut failed_updates: Vec<(String, String)> = Vec::new();
-            // Prefetch retry counts and mark items as Syncing in a single blocking operation to avoid per-item DB opens.
-            let retry_map: std::collections::HashMap<String, u32> = tokio::task::spawn_blocking({
-                let ids = queued.iter().map(|h| h.id.clone()).collect::<Vec<_>>();
-                move || -> Result<std::collections::HashMap<String, u32>, anyhow::Error> {
-                    let q = crate::queue::Queue::new().map_err(|e| anyhow::anyhow!(e))?;
-                    let mut map = std::collections::HashMap::new();
-                    for id in ids {
-                        let rc = q.get_retry_count(&id).unwrap_or(0);
-                        // Best-effort: mark as syncing with next attempt info
-                        let _ = q.update_sync_status(
+    /// Increment retry counts for failed sends and mark each heartbeat failed, or
+    /// permanently failed after 3 attempts. Returns the number of new permanent failures.
+    async fn record_failures(
+        &self,
+        updates: Vec<(String, String)>,
+    ) -> Result<usize, anyhow::Error> {
+        let queue = self.queue_handle();
+        let perm_count = tokio::task::spawn_blocking(move || -> Result<usize, anyhow::Error> {
+            let q = queue.lock().unwrap_or_else(|e| e.into_inner());
+            q.with_transaction(|tx| {
+                let mut perm = 0usize;
+                for (id, err_meta) in updates {
+                    // Increment retry and read new count
+                    tx.increment_retry(&id)?;
+                    let rc = tx.get_retry_count(&id)?;
+                    // Failed heartbeats are retried on the next run, so no retry time is set
+                    let metadata =
+                        crate::sync::SyncMetadata::failure(&err_meta, rc, None).to_json();
+                    if rc >= 3 {
+                        tx.update_sync_status(
                             &id,
-                            crate::sync::SyncStatus::Syncing,
-                            Some(format!("Attempting sync (attempt {})", rc + 1)),
-                        );
-                        map.insert(id.clone(), rc);
+                            crate::sync::SyncStatus::PermanentFailure,
+                            Some(metadata),
+                        )?;
+                        perm += 1;
+                    } else {
+                        tx.update_sync_status(
+                            &id,
+                            crate::sync::SyncStatus::Failed,
+                            Some(metadata),
+                        )?;
                     }
-                    Ok(map)
                 }
+                Ok(perm)
+            })
+            .map_err(|e| anyhow::anyhow!(e))
+        })
+        .await??;
+
+        Ok(perm_count)
+    }
+
+    /// Mark heartbeats the server rejected as invalid permanently failed, keeping
+    /// the field errors in their sync metadata. Returns the number marked.
+    async fn record_invalid(
+        &self,
+        updates: Vec<(String, String, crate::api::FieldErrors)>,
+    ) -> Result<usize, anyhow::Error> {
+        let queue = self.queue_handle();
+        tokio::task::spawn_blocking(move || -> Result<usize, anyhow::Error> {
+            let q = queue.lock().unwrap_or_else(|e| e.into_inner());
+            q.with_transaction(|tx| {
+                for (id, err_meta, field_errors) in &updates {
+                    tx.increment_retry(id)?;
+                    let rc = tx.get_retry_count(id).unwrap_or(0);
+                    let metadata = crate::sync::SyncMetadata {
+                        field_errors: field_errors.clone(),
+                        ..crate::sync::SyncMetadata::failure(err_meta, rc, None)
+                    };
+                    tx.update_sync_status(
+                        id,
+                        crate::sync::SyncStatus::PermanentFailure,
+                        Some(metadata.to_json()),
+                    )?;
+                }
+                Ok(updates.len())
+            })
+            .map_err(|e| anyhow::anyhow!(e))
+        })
+        .await?
+    }
+
+    /// Update failed heartbeats with retry_count < 3 to pending status for retry,
+    /// reading `retry_scan_limit` failed rows at a time
+    async fn prepare_retry_eligible_failures(&self) -> Result<(), anyhow::Error> {
+        let scan_limit = self.config.sync_config.retry_scan_limit;
+        // Run the prepare pass inside a single blocking task so we open the DB once
+        let queue = self.queue_handle();
+        let retry_count: usize =
+            tokio::task::spawn_blocking(move || -> Result<usize, anyhow::Error> {
+                let q = queue.lock().unwrap_or_else(|e| e.into_inner());
+                q.prepare_failed_for_retry(3, scan_limit)
+                    .map_err(|e| anyhow::anyhow!(e))
             })
             .await??;
-            for heartbeat in queued {
-                // Use prefetched retry count and previously set syncing status
-                let retry_count: u32 = *retry_map.get(&heartbeat.id).unwrap_or(&0);
 
-                tracing::debug!(
-                    "Attempting individual send for heartbeat id: {}",
-                    heartbeat.id
-                );
-                let send_result = if let Some(auth_client) = &self.authenticated_api_client {
-                    auth_client.send_heartbeat(&heartbeat).await
-                } else {
-                    self.api_client.send_heartbeat(&heartbeat).await
-                };
-                tracing::debug!(
-                    "Individual send result for {} success: {}",
-                    heartbeat.id,
-                    send_result.is_ok()
-                );
+        if retry_count > 0 {
+            tracing::info!("Prepared {} failed heartbeats for retry", retry_count);
+        }
 
-                match send_result {
-                    Ok(_) => {
-                        // Defer DB updates/removal for successful sends and batch-apply later
-                        tracing::debug!(
-                            "Queued heartbeat marked for finalization: {}",
-                            heartbeat.id
-                        );
-                        synced_ids.push(heartbeat.id.clone());
-                        total_synced += 1;
-                    }
-                    Err(e) => {
-                        // Rate-limit handling: apply backoff and retry in-memory once before incrementing retry count
-                        if let crate::api::ApiError::RateLimit(_) = e {
-                            let backoff_secs = 2u64.pow(std::cmp::min(retry_count as u32, 6)) * 5; // exponential backoff capped
-                            tracing::warn!(
-                                "Heartbeat {} rate-limited, backing off {}s before retry",
-                                heartbeat.id,
-                                backoff_secs
-                            );
-                            tokio::time::sleep(std::time::Duration::from_secs(backoff_secs)).await;
-
-                            // Try once more after backoff
-                            let retry_send =
-                                if let Some(auth_client) = &self.authenticated_api_client {
-                                    auth_client.send_heartbeat(&heartbeat).await
-                                } else {
-                                    self.api_client.send_heartbeat(&heartbeat).await
-                                };
-
-                            if retry_send.is_ok() {
-                                // Defer final DB update/removal to the consolidated batch finalization.
-                                // This avoids opening the DB in a per-item blocking task even in the rare backoff-success path.
-                                let id = heartbeat.id.clone();
-                                tracing::debug!("Successfully synced queued heartbeat after backoff (deferring DB update): {}", id);
-                                synced_ids.push(id);
-                                total_synced += 1;
-                                continue;
-                            }
-                            // If still failing, fallthrough to increment retry below
-                        }
+        Ok(())
+    }
+}
+
+/// Extension trait for HeartbeatManager to add offline sync capabilities
+#[allow(async_fn_in_trait)]
+pub trait HeartbeatManagerExt {
+    /// Process heartbeats using offline-first strategy
+    async fn process_offline_first(&self) -> Result<(), anyhow::Error>;
+
+    /// Get queue statistics including sync status
+    fn get_queue_stats(&self) -> Result<SyncStatusSummary, anyhow::Error>;
+
+    /// Manually trigger sync of offline heartbeats
+    async fn manual_sync(&self) -> Result<SyncResult, anyhow::Error>;
+}
+
+impl HeartbeatManagerExt for HeartbeatManager {
+    async fn process_offline_first(&self) -> Result<(), anyhow::Error> {
+        // For now, this is a placeholder that uses the existing process_queue logic
+        // In the future, this will integrate with the SyncManager
+        let _ = self.process_queue().await?;
+        Ok(())
+    }
+
+    fn get_queue_stats(&self) -> Result<SyncStatusSummary, anyhow::Error> {
+        // Get sync statistics from the queue
+        let stats = self.queue().get_sync_stats()?;
+        Ok(stats)
+    }
+
+    async fn manual_sync(&self) -> Result<SyncResult, anyhow::Error> {
+        // Process the queue to sync pending heartbeats
+        let start_time = std::time::SystemTime::now();
+
+        // Do not clear the queue here; caller (or tests) control initial state.
+
+        // Get initial stats before sync
+        let initial_stats = self.queue().get_sync_stats()?;
+        let _initial_total = initial_stats.total;
+
+        // Process the queue and obtain counts
+        let (synced_count, failed_count) = self.process_queue().await?;
+
+        let end_time = std::time::SystemTime::now();
+        let duration = end_time.duration_since(start_time).unwrap_or_default();
+
+        Ok(SyncResult {
+            synced_count,
+            failed_count,
+            total_count: (synced_count + failed_count),
+            duration,
+            error: None,
+            start_time: Some(start_time),
+            end_time: Some(end_time),
+            avg_latency_ms: if (synced_count + failed_count) > 0 {
+                Some(duration.as_millis() as f64 / (synced_count + failed_count) as f64)
+            } else {
+                None
+            },
+        })
+    }
+}
+
+impl HeartbeatManager {
+    /// Add a heartbeat directly to the queue for offline processing
+    pub async fn add_heartbeat_to_queue(&self, heartbeat: Heartbeat) -> anyhow::Result<()> {
+        // Check if entity should be ignored
+        if self.should_ignore_entity(&heartbeat.entity) {
+            tracing::debug!("Ignoring entity: {}", heartbeat.entity);
+            return Ok(());
+        }
+
+        if self.should_skip_empty(&heartbeat) {
+            tracing::debug!("Skipping empty file heartbeat: {}", heartbeat.entity);
+            return Ok(());
+        }
+        if self.should_sample_out(&heartbeat) {
+            tracing::debug!("Sampled out heartbeat: {}", heartbeat.entity);
+            return Ok(());
+        }
+        if self.is_too_far_in_future(&heartbeat) {
+            return Ok(());
+        }
+
+        // Add heartbeat to queue
+        let heartbeat = self.finalize(heartbeat).await;
+        self.enqueue(vec![heartbeat]).await?;
+        tracing::debug!("Heartbeat queued for offline-first processing");
+
+        Ok(())
+    }
+
+    /// Queue write heartbeats for the files changed by the HEAD commit of the
+    /// repository containing `repo_path`. Each heartbeat goes through normal
+    /// detection with `cli` as the base, so commit metadata and overrides apply.
+    /// Without the offline queue they are sent directly instead.
+    /// Returns the number of heartbeats queued.
+    pub async fn queue_git_commit(
+        &self,
+        cli: &Cli,
+        repo_path: &std::path::Path,
+    ) -> anyhow::Result<usize> {
+        let commit = crate::git_hook::head_commit_files(repo_path)?;
+
+        let mut batch = Vec::new();
+        for file in commit.files {
+            let entity = entity_from_path(&file);
+            if self.should_ignore_entity(&entity) {
+                continue;
+            }
+
+            let mut file_cli = cli.clone();
+            file_cli.write = Some(true);
+            file_cli.time = Some(commit.time);
+            let heartbeat = self.create_heartbeat(file_cli, entity).await?;
+
+            if self.should_skip_empty(&heartbeat) {
+                continue;
+            }
+            batch.push(self.finalize(heartbeat).await);
+        }
+        let queued = batch.len();
+        self.queue_or_send(batch).await?;
+
+        tracing::info!("Queued {} heartbeats from git commit", queued);
+        Ok(queued)
+    }
+
+    /// Queue a write heartbeat for a file saved under `--watch`, using `cli` as
+    /// the base for detection, or send it directly without the offline queue.
+    /// Returns whether a heartbeat was queued.
+    pub async fn queue_file_write(
+        &self,
+        cli: &Cli,
+        path: &std::path::Path,
+    ) -> anyhow::Result<bool> {
+        let entity = entity_from_path(path);
+        if self.should_ignore_entity(&entity) {
+            tracing::debug!("Ignoring watched file: {}", entity);
+            return Ok(false);
+        }
+
+        let mut file_cli = cli.clone();
+        file_cli.write = Some(true);
+        file_cli.time = None;
+        let heartbeat = self.create_heartbeat(file_cli, entity).await?;
+
+        if self.should_skip_empty(&heartbeat) {
+            return Ok(false);
+        }
+        let heartbeat = self.finalize(heartbeat).await;
+        self.queue_or_send(vec![heartbeat]).await?;
+        Ok(true)
+    }
+
+    /// Fill in an external heartbeat from `--extra-heartbeats`. Fields the plugin
+    /// sent are kept verbatim; only file heartbeats run detection, and only for
+    /// the project, branch and language they left out.
+    pub async fn complete_extra_heartbeat(&self, bulk: BulkHeartbeat) -> Heartbeat {
+        let mut heartbeat = Heartbeat::from(bulk);
+
+        if heartbeat.entity_type == "file"
+            && (heartbeat.project.is_none()
+                || heartbeat.branch.is_none()
+                || heartbeat.language.is_none())
+        {
+            let (project_info, git_info, language) = self
+                .collector
+                .detect_all(&heartbeat.entity, DETECTION_TIMEOUT)
+                .await;
+
+            if heartbeat.project.is_none() {
+                heartbeat.project = project_info.and_then(|p| {
+                    p.root
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .map(|s| s.to_string())
+                });
+            }
+            if heartbeat.branch.is_none() {
+                heartbeat.branch = git_info.and_then(|g| g.branch);
+            }
+            if heartbeat.language.is_none() {
+                heartbeat.language = language;
+            }
+        }
+
+        if heartbeat.user_agent.is_none() {
+            heartbeat.user_agent = Some(generate_user_agent(None));
+        }
+
+        heartbeat
+    }
+
+    /// Queue a JSON array of heartbeats read from `--extra-heartbeats`. Accepts
+    /// full queue heartbeats or the relaxed WakaTime bulk format, where entries
+    /// may mix file, app and domain types. Returns the number of heartbeats queued.
+    pub async fn queue_extra_heartbeats(&self, input: &str) -> anyhow::Result<usize> {
+        let heartbeats = match serde_json::from_str::<Vec<Heartbeat>>(input) {
+            Ok(heartbeats) => heartbeats,
+            Err(e) => {
+                tracing::debug!("Parsing extra heartbeats with relaxed validation: {}", e);
+                let relaxed: Vec<BulkHeartbeat> = serde_json::from_str(input)
+                    .map_err(|e| anyhow::anyhow!("Failed to parse extra heartbeats: {}", e))?;
+
+                let mut heartbeats = Vec::with_capacity(relaxed.len());
+                for bulk in relaxed {
+                    heartbeats.push(self.complete_extra_heartbeat(bulk).await);
+                }
+                heartbeats
+            }
+        };
+
+        let mut batch = Vec::new();
+        for mut heartbeat in heartbeats {
+            heartbeat.time = normalize_heartbeat_time(heartbeat.time);
+            if self.should_ignore_entity(&heartbeat.entity)
+                || self.should_skip_empty(&heartbeat)
+                || self.should_sample_out(&heartbeat)
+                || self.is_too_far_in_future(&heartbeat)
+            {
+                continue;
+            }
+            batch.push(self.finalize(heartbeat).await);
+        }
+        let queued = batch.len();
+        self.enqueue(batch).await?;
+
+        Ok(queued)
+    }
+
+    /// Write every queued heartbeat, regardless of sync status, to `path` as a
+    /// WakaTime bulk JSON array. Returns the number of heartbeats exported.
+    pub fn export_heartbeats(&self, path: &std::path::Path) -> anyhow::Result<usize> {
+        let heartbeats = self.queue().get_all()?;
+        let bulk: Vec<BulkHeartbeat> = heartbeats.iter().map(BulkHeartbeat::from).collect();
+
+        let json = serde_json::to_string_pretty(&bulk)?;
+        std::fs::write(path, json).map_err(|e| {
+            anyhow::anyhow!("Failed to write export file {}: {}", path.display(), e)
+        })?;
+
+        tracing::info!("Exported {} heartbeats to {}", bulk.len(), path.display());
+        Ok(bulk.len())
+    }
+
+    /// Shift the time of every unsynced heartbeat by `offset_seconds`, for
+    /// `--fix-timestamps`. With `dry_run` the queue is left untouched; either
+    /// way the planned changes are returned, oldest first.
+    pub fn fix_timestamps(
+        &self,
+        offset_seconds: f64,
+        dry_run: bool,
+    ) -> anyhow::Result<Vec<TimestampFix>> {
+        // Read and shift in one transaction so heartbeats queued in between
+        // are neither missed nor shifted without being reported
+        let fixes = self.queue().with_transaction(|tx| {
+            let unsynced = tx.get_pending_with_statuses(
+                None,
+                &[
+                    crate::sync::SyncStatus::Pending,
+                    crate::sync::SyncStatus::Failed,
+                ],
+            )?;
+            let fixes: Vec<TimestampFix> = unsynced
+                .into_iter()
+                .map(|hb| TimestampFix {
+                    new_time: hb.time + offset_seconds,
+                    old_time: hb.time,
+                    id: hb.id,
+                    entity: hb.entity,
+                })
+                .collect();
+
+            if !dry_run && !fixes.is_empty() {
+                let ids: Vec<String> = fixes.iter().map(|fix| fix.id.clone()).collect();
+                tx.shift_times(&ids, offset_seconds)?;
+            }
+            Ok(fixes)
+        })?;
+        Ok(fixes)
+    }
+
+    /// Upload the offline queue for `--catch-up`. When the server advertises
+    /// a bulk heartbeats endpoint, batches as large as it accepts go there
+    /// back to back and `progress` is called after each one. Without a bulk
+    /// endpoint, or once a bulk upload fails, the rest goes through the
+    /// regular sync.
+    pub async fn catch_up<F>(&self, mut progress: F) -> Result<SyncResult, anyhow::Error>
+    where
+        F: FnMut(CatchUpProgress),
+    {
+        let start_time = std::time::SystemTime::now();
+        let mut synced = 0usize;
+        let mut failed = 0usize;
+
+        let bulk = match &self.authenticated_api_client {
+            Some(client) => client
+                .bulk_upload_limit()
+                .await
+                .map(|limit| (client, limit.min(CATCH_UP_MAX_BATCH_SIZE))),
+            None => None,
+        };
+
+        if let Some((client, batch_size)) = bulk {
+            tracing::info!(
+                "Catching up through the bulk endpoint, {} per request",
+                batch_size
+            );
+            let max_age = self.config.sync_config.max_age();
+            self.prepare_retry_eligible_failures().await?;
+
+            loop {
+                let batch = self.next_pending_batch(batch_size, max_age).await?;
+                if batch.is_empty() {
+                    break;
+                }
+                let ids: Vec<String> = batch.iter().map(|hb| hb.id.clone()).collect();
+
+                let mut attempt = 1;
+                let sent = loop {
+                    match client.send_heartbeats_bulk(&batch).await {
+                        Err(crate::api::ApiError::RateLimit(_, retry_after))
+                            if attempt < CATCH_UP_RATE_LIMIT_ATTEMPTS
+                                && retry_after.unwrap_or(MAX_INLINE_BACKOFF)
+                                    <= MAX_INLINE_BACKOFF =>
+                        {
+                            let backoff = retry_after.unwrap_or(MAX_INLINE_BACKOFF);
+                            tracing::warn!(
+                                "Rate limited during catch-up, sleeping {}s",
+                                backoff.as_secs()
+                            );
+                            tokio::time::sleep(backoff).await;
+                            attempt += 1;
+                        }
+                        result => break result,
+                    }
+                };
+
+                match sent {
+                    Ok(response) => {
+                        let rejections = crate::api::parse_batch_rejections(response).await;
+                        let (accepted, rejected) = crate::api::partition_batch(&batch, rejections);
+                        synced += accepted.len();
+                        self.finalize_synced(accepted).await?;
+                        if !rejected.is_empty() {
+                            failed += self.record_failures(rejected).await?;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!("Bulk upload failed, continuing with regular sync: {}", e);
+                        // Hand the claimed batch back so the regular sync picks it up
+                        self.release_to_pending(ids).await?;
+                        break;
+                    }
+                }
+
+                let remaining = self.pending_count().await?;
+                progress(CatchUpProgress {
+                    synced,
+                    failed,
+                    remaining,
+                });
+            }
+        } else {
+            tracing::info!("Server has no bulk heartbeats endpoint, using regular sync");
+        }
+
+        // Whatever the bulk uploads left behind
+        let (more_synced, more_failed) = self.process_queue().await?;
+        if more_synced + more_failed > 0 {
+            synced += more_synced;
+            failed += more_failed;
+            let remaining = self.pending_count().await?;
+            progress(CatchUpProgress {
+                synced,
+                failed,
+                remaining,
+            });
+        }
+
+        let end_time = std::time::SystemTime::now();
+        let duration = end_time.duration_since(start_time).unwrap_or_default();
+        let total_count = synced + failed;
+        Ok(SyncResult {
+            synced_count: synced,
+            failed_count: failed,
+            total_count,
+            duration,
+            error: None,
+            start_time: Some(start_time),
+            end_time: Some(end_time),
+            avg_latency_ms: (total_count > 0)
+                .then(|| duration.as_millis() as f64 / total_count as f64),
+        })
+    }
+
+    /// Re-detect the project of unsynced file heartbeats queued from inside a
+    /// git worktree, for `--repair-worktree-paths`. Only heartbeats still named
+    /// after the worktree folder, as before worktrees resolved to their main
+    /// repository, are changed. Detection skips the project cache, which may
+    /// hold the same stale names. With `dry_run` the queue is left untouched;
+    /// either way the planned changes are returned.
+    pub async fn repair_worktree_paths(&self, dry_run: bool) -> anyhow::Result<Vec<ProjectRepair>> {
+        let unsynced = self.queue().get_pending_with_statuses(
+            None,
+            &[
+                crate::sync::SyncStatus::Pending,
+                crate::sync::SyncStatus::Failed,
+            ],
+        )?;
+        let collector = DataCollector::new()
+            .with_max_detect_file_bytes(self.config.max_detect_file_bytes)
+            .with_project_markers(&self.config.project_markers);
+        let project_name = |root: &std::path::Path| {
+            let name = root
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown");
+            crate::project_name::sanitize(name, &self.config.project_name_rewrites)
+        };
+
+        let mut repairs = Vec::new();
+        for hb in unsynced.into_iter().filter(|hb| hb.entity_type == "file") {
+            let path = crate::collector::normalize_entity_path(&hb.entity);
+            let Some(worktree) = collector.worktree_root(std::path::Path::new(&path)) else {
+                continue;
+            };
+            if hb.project.as_deref() != Some(project_name(&worktree).as_str()) {
+                continue;
+            }
+            let Some(project) = collector.detect_project(&hb.entity).await else {
+                continue;
+            };
+            let new_project = project_name(&project.root);
+            if hb.project.as_deref() != Some(new_project.as_str()) {
+                repairs.push(ProjectRepair {
+                    id: hb.id,
+                    entity: hb.entity,
+                    old_project: hb.project,
+                    new_project,
+                });
+            }
+        }
+
+        if !dry_run && !repairs.is_empty() {
+            let projects: Vec<(String, String)> = repairs
+                .iter()
+                .map(|repair| (repair.id.clone(), repair.new_project.clone()))
+                .collect();
+            self.queue().set_projects(&projects)?;
+        }
+        Ok(repairs)
+    }
+
+    /// List heartbeats currently marked as syncing, oldest attempt first
+    pub fn get_syncing_heartbeats(&self) -> anyhow::Result<Vec<crate::queue::SyncingEntry>> {
+        Ok(self.queue().get_syncing()?)
+    }
+
+    /// List up to `limit` unsynced queue entries with their retry state, oldest first
+    pub fn get_unsynced_entries(
+        &self,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<crate::queue::QueueEntry>> {
+        use crate::sync::SyncStatus;
+        Ok(self.queue().get_entries(
+            limit,
+            &[
+                SyncStatus::Pending,
+                SyncStatus::Syncing,
+                SyncStatus::Failed,
+                SyncStatus::PermanentFailure,
+            ],
+        )?)
+    }
+
+    /// List every failed and permanently failed queue entry with its retry
+    /// state, oldest first
+    pub fn get_failed_entries(&self) -> anyhow::Result<Vec<crate::queue::QueueEntry>> {
+        use crate::sync::SyncStatus;
+        Ok(self
+            .queue()
+            .get_entries(None, &[SyncStatus::Failed, SyncStatus::PermanentFailure])?)
+    }
+
+    /// Queue heartbeats from a WakaTime bulk JSON array stored at `path`.
+    /// Ignore patterns still apply. Returns the number of heartbeats queued.
+    pub fn import_heartbeats(&self, path: &std::path::Path) -> anyhow::Result<usize> {
+        let input = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read import file {}: {}", path.display(), e))?;
+
+        let heartbeats: Vec<Heartbeat> = match serde_json::from_str::<Vec<Heartbeat>>(&input) {
+            Ok(heartbeats) => heartbeats,
+            Err(_) => serde_json::from_str::<Vec<BulkHeartbeat>>(&input)
+                .map_err(|e| anyhow::anyhow!("Failed to parse heartbeats: {}", e))?
+                .into_iter()
+                .map(Heartbeat::from)
+                .collect(),
+        };
+
+        let heartbeats: Vec<Heartbeat> = heartbeats
+            .into_iter()
+            .filter(|hb| !self.should_ignore_entity(&hb.entity))
+            .map(|hb| hb.redacted(&self.config))
+            .collect();
+        let count = heartbeats.len();
+        self.queue().add_batch(heartbeats)?;
+
+        tracing::info!("Imported {} heartbeats from {}", count, path.display());
+        Ok(count)
+    }
+}
+
+impl Drop for HeartbeatManager {
+    fn drop(&mut self) {
+        // The last manager using the queue checkpoints its WAL on the way out;
+        // processes that exit() early leave that to the next run
+        if Arc::strong_count(&self.queue) == 1 {
+            if let Err(e) = self.queue().checkpoint() {
+                tracing::debug!("Failed to checkpoint the offline queue: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a HeartbeatManager backed by an isolated temp database.
+    /// Returns the manager and the TempDir guard — the caller must keep
+    /// the TempDir alive for as long as the manager is used.
+    /// This prevents parallel tests from contending on the shared `~/.chronova/queue.db`.
+    fn create_test_manager(config: Config) -> (HeartbeatManager, tempfile::TempDir) {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let db_path = temp_dir.path().join("test_queue.db");
+        let queue = Queue::with_path(db_path).expect("Failed to create test queue");
+        let manager =
+            HeartbeatManager::new_with_queue(config, queue).expect("Failed to create manager");
+        (manager, temp_dir)
+    }
+
+    #[test]
+    fn test_logical_key_ignores_id_and_user_agent() {
+        let time = 1_700_000_040.0;
+        let first = Heartbeat::builder("/src/main.rs", time)
+            .project("chronova".to_string())
+            .user_agent("vscode/1.90".to_string())
+            .build();
+        let second = Heartbeat::builder("/src/main.rs", time + 30.0)
+            .project("chronova".to_string())
+            .user_agent("chronova-cli/1.0".to_string())
+            .build();
+
+        assert_ne!(first.id, second.id);
+        assert_eq!(first.logical_key(), second.logical_key());
+        assert_eq!(first, second);
+        let unique: std::collections::HashSet<&Heartbeat> = [&first, &second].into();
+        assert_eq!(unique.len(), 1);
+
+        let write = Heartbeat::builder("/src/main.rs", time)
+            .project("chronova".to_string())
+            .is_write(true)
+            .build();
+        let later = Heartbeat::builder("/src/main.rs", time + 60.0)
+            .project("chronova".to_string())
+            .build();
+        assert_ne!(first, write);
+        assert_ne!(first, later);
+    }
+
+    #[test]
+    fn test_fix_timestamps_shifts_unsynced_heartbeats() {
+        let (manager, _temp_dir) = create_test_manager(Config::default());
+        // Recorded with the clock a year behind
+        let wrong = 1_668_000_000.0;
+        let year = 365.0 * 24.0 * 60.0 * 60.0;
+        manager
+            .queue()
+            .add(
+                Heartbeat::builder("/src/a.rs", wrong)
+                    .id("skewed-a")
+                    .build(),
+            )
+            .unwrap();
+        manager
+            .queue()
+            .add(
+                Heartbeat::builder("/src/b.rs", wrong + 30.0)
+                    .id("skewed-b")
+                    .build(),
+            )
+            .unwrap();
+        manager
+            .queue()
+            .add(Heartbeat::builder("/src/c.rs", wrong).id("synced").build())
+            .unwrap();
+        manager
+            .queue()
+            .update_sync_status("synced", crate::sync::SyncStatus::Synced, None)
+            .unwrap();
+
+        let preview = manager.fix_timestamps(year, true).unwrap();
+        assert_eq!(preview.len(), 2);
+        assert_eq!(preview[0].new_time, wrong + year);
+        let times = |manager: &HeartbeatManager| -> Vec<(String, f64)> {
+            let mut times: Vec<(String, f64)> = manager
+                .queue()
+                .get_all()
+                .unwrap()
+                .into_iter()
+                .map(|hb| (hb.id, hb.time))
+                .collect();
+            times.sort_by(|a, b| a.0.cmp(&b.0));
+            times
+        };
+        // A dry run changes nothing
+        assert!(times(&manager).iter().all(|(_, time)| *time < wrong + 60.0));
+
+        let fixed = manager.fix_timestamps(year, false).unwrap();
+        assert_eq!(fixed.len(), 2);
+        assert_eq!(
+            times(&manager),
+            vec![
+                ("skewed-a".to_string(), wrong + year),
+                ("skewed-b".to_string(), wrong + 30.0 + year),
+                ("synced".to_string(), wrong),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repair_worktree_paths_renames_stale_projects() {
+        let (manager, _temp_dir) = create_test_manager(Config::default());
+        let repos = tempfile::tempdir().unwrap();
+        let main_path = repos.path().join("chronova");
+        std::fs::create_dir_all(&main_path).unwrap();
+        let repo = git2::Repository::init(&main_path).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[])
+            .unwrap();
+        let worktree_path = repos.path().join("feature");
+        repo.worktree("feature", &worktree_path, None).unwrap();
+        let file = worktree_path.join("lib.rs").to_string_lossy().into_owned();
+        std::fs::write(&file, "fn main() {}\n").unwrap();
+
+        // Queued before worktrees resolved to the main repository
+        manager
+            .queue()
+            .add(
+                Heartbeat::builder(file.clone(), 1_700_000_000.0)
+                    .id("stale")
+                    .project("feature".to_string())
+                    .build(),
+            )
+            .unwrap();
+        // Named explicitly with --project
+        manager
+            .queue()
+            .add(
+                Heartbeat::builder(file.clone(), 1_700_000_030.0)
+                    .id("explicit")
+                    .project("billing".to_string())
+                    .build(),
+            )
+            .unwrap();
+        let projects = |manager: &HeartbeatManager| -> Vec<(String, Option<String>)> {
+            let mut projects: Vec<_> = manager
+                .queue()
+                .get_all()
+                .unwrap()
+                .into_iter()
+                .map(|hb| (hb.id, hb.project))
+                .collect();
+            projects.sort();
+            projects
+        };
+
+        let preview = manager.repair_worktree_paths(true).await.unwrap();
+        assert_eq!(preview.len(), 1);
+        assert_eq!(preview[0].id, "stale");
+        assert_eq!(preview[0].new_project, "chronova");
+        // A dry run changes nothing
+        assert!(projects(&manager).contains(&("stale".to_string(), Some("feature".to_string()))));
+
+        let repaired = manager.repair_worktree_paths(false).await.unwrap();
+        assert_eq!(repaired.len(), 1);
+        assert_eq!(
+            projects(&manager),
+            vec![
+                ("explicit".to_string(), Some("billing".to_string())),
+                ("stale".to_string(), Some("chronova".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_should_ignore_entity() {
+        let config = Config {
+            ignore_patterns: vec!["COMMIT_EDITMSG$".to_string(), "*.tmp".to_string()],
+            ..Default::default()
+        };
+
+        let (manager, _temp_dir) = create_test_manager(config);
+
+        assert!(manager.should_ignore_entity("/path/to/COMMIT_EDITMSG"));
+        assert!(manager.should_ignore_entity("/path/to/file.tmp"));
+        assert!(!manager.should_ignore_entity("/path/to/normal_file.rs"));
+    }
+
+    #[test]
+    fn test_vcs_internals_and_editor_temp_files_ignored_by_default() {
+        let (manager, _temp_dir) = create_test_manager(Config::default());
+
+        assert!(manager.should_ignore_entity("/repo/.git/index"));
+        assert!(manager.should_ignore_entity("/repo/.git/rebase-merge/done"));
+        assert!(manager.should_ignore_entity("C:\\repo\\.git\\HEAD"));
+        assert!(manager.should_ignore_entity("/repo/src/foo.swp"));
+        assert!(manager.should_ignore_entity("/repo/src/main.rs~"));
+        assert!(manager.should_ignore_entity("/repo/src/.#main.rs"));
+        assert!(!manager.should_ignore_entity("/repo/.gitignore"));
+        assert!(!manager.should_ignore_entity("/repo/src/main.rs"));
+    }
+
+    #[tokio::test]
+    async fn test_skip_empty_files() {
+        let config = Config {
+            skip_empty_files: true,
+            ..Default::default()
+        };
+        let (manager, _temp_dir) = create_test_manager(config);
+
+        let empty_heartbeat = |id: &str, is_write: bool| {
+            Heartbeat::builder(format!("/path/to/{}.rs", id), 1.0)
+                .id(id)
+                .is_write(is_write)
+                .lines(0)
+                .build()
+        };
+
+        manager
+            .add_heartbeat_to_queue(empty_heartbeat("opened", false))
+            .await
+            .unwrap();
+        manager
+            .add_heartbeat_to_queue(empty_heartbeat("created", true))
+            .await
+            .unwrap();
+
+        let queued = manager.queue().get_all().unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].id, "created");
+    }
+
+    #[test]
+    fn test_heartbeat_manager_ext_implementation() {
+        let config = Config::default();
+        let (manager, _temp_dir) = create_test_manager(config);
+
+        // Clear any existing heartbeats from the queue first
+        let _ = manager.queue().cleanup_old_entries(0); // Remove all entries
+
+        // Test that HeartbeatManagerExt is implemented by calling methods directly
+        let stats = manager.get_queue_stats();
+        assert!(stats.is_ok(), "get_queue_stats should return Ok");
+    }
+
+    #[test]
+    fn test_get_queue_stats() {
+        let config = Config::default();
+        let (manager, _temp_dir) = create_test_manager(config);
+
+        // Clear any existing heartbeats from the queue first
+        let _ = manager.queue().cleanup_old_entries(0); // Remove all entries
+
+        let stats = manager.get_queue_stats();
+        assert!(stats.is_ok(), "get_queue_stats should return Ok");
+
+        let summary = stats.unwrap();
+        assert_eq!(summary.total, 0, "Initial queue should be empty");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_resolve_symlinks_uses_target_project() {
+        use clap::Parser;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project = temp_dir.path().join("linked-project");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(project.join("Cargo.toml"), "[package]\n").unwrap();
+        let target = project.join("src").join("main.rs");
+        std::fs::write(&target, "fn main() {}\n").unwrap();
+
+        let outside = temp_dir.path().join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        let link = outside.join("main.rs");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let link = link.to_string_lossy().to_string();
+
+        let config = Config {
+            resolve_symlinks: true,
+            ..Default::default()
+        };
+        let (manager, _db_dir) = create_test_manager(config);
+        let cli = Cli::parse_from(["chronova-cli", "--entity", &link]);
+        let heartbeat = manager.create_heartbeat(cli, link.clone()).await.unwrap();
+
+        assert_eq!(heartbeat.project.as_deref(), Some("linked-project"));
+        assert_eq!(
+            heartbeat.entity,
+            std::fs::canonicalize(&target).unwrap().to_string_lossy()
+        );
+
+        // Default keeps the reported path as given
+        let (manager, _db_dir) = create_test_manager(Config::default());
+        let cli = Cli::parse_from(["chronova-cli", "--entity", &link]);
+        let heartbeat = manager.create_heartbeat(cli, link.clone()).await.unwrap();
+        assert_eq!(heartbeat.entity, link);
+    }
+
+    #[tokio::test]
+    async fn test_queue_git_commit_queues_changed_files() {
+        use clap::Parser;
+
+        let repo_dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(repo_dir.path()).unwrap();
+        let signature = git2::Signature::now("Dev", "dev@example.com").unwrap();
+        let commit_files = |paths: &[&str], message: &str| {
+            let mut index = repo.index().unwrap();
+            for path in paths {
+                std::fs::write(repo_dir.path().join(path), format!("// {}\n", message)).unwrap();
+                index.add_path(std::path::Path::new(path)).unwrap();
+            }
+            index.write().unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parents: Vec<git2::Commit> = repo
+                .head()
+                .ok()
+                .and_then(|h| h.peel_to_commit().ok())
+                .into_iter()
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .unwrap();
+        };
+        commit_files(&["untouched.rs", "lib.rs"], "initial");
+        commit_files(&["lib.rs", "main.rs"], "add main");
+
+        let (manager, _db_dir) = create_test_manager(Config::default());
+        let cli = Cli::parse_from(["chronova-cli", "--from-git-hook"]);
+        let queued = manager
+            .queue_git_commit(&cli, repo_dir.path())
+            .await
+            .unwrap();
+        assert_eq!(queued, 2);
+
+        let heartbeats = manager.queue().get_all().unwrap();
+        let mut entities: Vec<String> = heartbeats
+            .iter()
+            .map(|hb| {
+                std::path::Path::new(&hb.entity)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        entities.sort();
+        assert_eq!(entities, vec!["lib.rs", "main.rs"]);
+        for hb in &heartbeats {
+            assert!(hb.is_write);
+            assert_eq!(hb.commit_message.as_deref(), Some("add main"));
+            assert_eq!(hb.language.as_deref(), Some("Rust"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_commit_message_truncated_to_subject() {
+        use clap::Parser;
+
+        let repo_dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(repo_dir.path()).unwrap();
+        let signature = git2::Signature::now("Dev", "dev@example.com").unwrap();
+        std::fs::write(repo_dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(std::path::Path::new("main.rs")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let body = "Long explanation. ".repeat(40);
+        let message = format!("Fix parser crash\n\n{}", body);
+        repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &[])
+            .unwrap();
+
+        let cli = Cli::parse_from(["chronova-cli", "--from-git-hook"]);
+        let (manager, _db_dir) = create_test_manager(Config::default());
+        manager
+            .queue_git_commit(&cli, repo_dir.path())
+            .await
+            .unwrap();
+        let heartbeats = manager.queue().get_all().unwrap();
+        assert_eq!(
+            heartbeats[0].commit_message.as_deref(),
+            Some("Fix parser crash")
+        );
+
+        let (manager, _db_dir) = create_test_manager(Config {
+            full_commit_message: true,
+            max_commit_message_len: 40,
+            ..Default::default()
+        });
+        manager
+            .queue_git_commit(&cli, repo_dir.path())
+            .await
+            .unwrap();
+        let full = manager.queue().get_all().unwrap()[0]
+            .commit_message
+            .clone()
+            .unwrap();
+        assert_eq!(full.chars().count(), 40);
+        assert!(full.starts_with("Fix parser crash\n\nLong explanation."));
+        assert!(full.ends_with('…'));
+    }
+
+    #[tokio::test]
+    async fn test_extra_heartbeats_mixed_file_and_domain() {
+        let (manager, temp_dir) = create_test_manager(Config::default());
+        let file = temp_dir.path().join("main.rs");
+        std::fs::write(&file, "fn main() {}\n").unwrap();
+
+        let input = serde_json::json!([
+            {"entity": file.to_string_lossy(), "time": 1_700_000_000.0},
+            {
+                "entity": "docs.rs",
+                "type": "domain",
+                "time": 1_700_000_001.0,
+                "category": "browsing",
+                "project": "research",
+                "machine": "laptop",
+                "user_agent": "chrome-wakatime/4.0.0"
+            }
+        ])
+        .to_string();
+
+        assert_eq!(manager.queue_extra_heartbeats(&input).await.unwrap(), 2);
+
+        let queued = manager.queue().get_all().unwrap();
+        let file_hb = queued.iter().find(|hb| hb.entity_type == "file").unwrap();
+        assert_eq!(file_hb.language.as_deref(), Some("Rust"));
+
+        let domain_hb = queued.iter().find(|hb| hb.entity_type == "domain").unwrap();
+        assert_eq!(domain_hb.entity, "docs.rs");
+        assert_eq!(domain_hb.category.as_deref(), Some("browsing"));
+        assert_eq!(domain_hb.project.as_deref(), Some("research"));
+        assert_eq!(domain_hb.machine.as_deref(), Some("laptop"));
+        assert_eq!(domain_hb.language, None);
+        assert_eq!(
+            domain_hb.user_agent.as_deref(),
+            Some("chrome-wakatime/4.0.0")
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pre_send_hook_runs_for_watched_files() {
+        use clap::Parser;
+
+        let (manager, temp_dir) = create_test_manager(Config {
+            pre_send_hook: Some(r#"echo '{"ticket": "ABC-123"}'"#.to_string()),
+            ..Default::default()
+        });
+        let file = temp_dir.path().join("main.rs");
+        std::fs::write(&file, "fn main() {}\n").unwrap();
+
+        let cli = Cli::parse_from(["chronova-cli", "--watch", "."]);
+        assert!(manager.queue_file_write(&cli, &file).await.unwrap());
+
+        let queued = manager.queue().get_all().unwrap();
+        assert_eq!(queued[0].extra["ticket"], "ABC-123");
+    }
+
+    #[tokio::test]
+    async fn test_imported_writes_are_coalesced() {
+        let (manager, _temp_dir) = create_test_manager(Config {
+            write_rate_limit_seconds: 60,
+            ..Default::default()
+        });
+
+        let input = serde_json::json!([
+            {"entity": "/work/a.rs", "time": 1_700_000_000.0, "is_write": true},
+            {"entity": "/work/a.rs", "time": 1_700_000_010.0, "is_write": true},
+            {"entity": "/work/a.rs", "time": 1_700_000_020.0, "is_write": true},
+            {"entity": "/work/a.rs", "time": 1_700_000_021.0},
+        ])
+        .to_string();
+
+        manager.queue_extra_heartbeats(&input).await.unwrap();
+        let queued = manager.queue().get_all().unwrap();
+        let writes: Vec<f64> = queued
+            .iter()
+            .filter(|hb| hb.is_write)
+            .map(|hb| hb.time)
+            .collect();
+        assert_eq!(writes, vec![1_700_000_020.0]);
+        assert_eq!(queued.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_include_overrides_exclude_for_imported_heartbeats() {
+        let (manager, _temp_dir) = create_test_manager(Config {
+            ignore_patterns: vec!["/vendor/".to_string()],
+            include_patterns: vec!["/vendor/patched/".to_string()],
+            ..Default::default()
+        });
+
+        let input = serde_json::json!([
+            {"entity": "/work/vendor/lib.rs", "time": 1_700_000_000.0},
+            {"entity": "/work/vendor/patched/lib.rs", "time": 1_700_000_001.0},
+        ])
+        .to_string();
+
+        assert_eq!(manager.queue_extra_heartbeats(&input).await.unwrap(), 1);
+        let queued = manager.queue().get_all().unwrap();
+        assert_eq!(queued[0].entity, "/work/vendor/patched/lib.rs");
+    }
+
+    #[tokio::test]
+    async fn test_zero_sample_rate_keeps_writes_and_session_starts() {
+        let (manager, _temp_dir) = create_test_manager(Config {
+            sample_rate: 0.0,
+            ..Default::default()
+        });
 
-                        // Defer retry increment and status updates to a consolidated blocking operation
-                        // to avoid opening the DB per-failure and to improve atomicity.
-                        let id = heartbeat.id.clone();
-                        let e_str = format!("{}", e);
-                        failed_updates.push((id, e_str));
-                    }
-                }
-            }
+        let input = serde_json::json!([
+            {"entity": "/work/a.rs", "time": 1_700_000_000.0},
+            {"entity": "/work/b.rs", "time": 1_700_000_001.0, "is_write": true},
+            {"entity": "/work/c.rs", "time": 1_700_000_002.0},
+            {"entity": "/work/d.rs", "time": 1_700_000_003.0, "is_write": true},
+            {"entity": "/work/e.rs", "time": 1_700_003_600.0},
+            {"entity": "/work/f.rs", "time": 1_700_003_601.0},
+        ])
+        .to_string();
+
+        // a.rs and e.rs start a session, the first ever and after an hour idle
+        assert_eq!(manager.queue_extra_heartbeats(&input).await.unwrap(), 4);
+        let mut entities: Vec<String> = manager
+            .queue()
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .map(|hb| hb.entity)
+            .collect();
+        entities.sort();
+        assert_eq!(
+            entities,
+            ["/work/a.rs", "/work/b.rs", "/work/d.rs", "/work/e.rs"]
+        );
 
-            // Consolidate failure updates (increment retry + set status) in one blocking operation
-            if !failed_updates.is_empty() {
-                let updates = failed_updates.clone();
-                let perm_count: usize =
-                    tokio::task::spawn_blocking(move || -> Result<usize, anyhow::Error> {
-                        let q = crate::queue::Queue::new().map_err(|e| anyhow::anyhow!(e))?;
-                        let mut perm = 0usize;
-                        for (id, err_meta) in updates {
-                            // Increment retry and read new count
-                            let _ = q.increment_retry(&id);
-                            let rc = q.get_retry_count(&id).unwrap_or(0);
-                            if rc >= 3 {
-                                let _ = q.update_sync_status(
-                                    &id,
-                                    crate::sync::SyncStatus::PermanentFailure,
-                                    Some(format!(
-                                        "Permanent failure after {} attempts: {}",
-                                        rc, err_meta
-                                    )),
-                                );
-                                perm += 1;
-                            } else {
-                                let _ = q.update_sync_status(
-                                    &id,
-                                    crate::sync::SyncStatus::Failed,
-                                    Some(format!("Sync failed (attempt {}): {}", rc, err_meta)),
-                                );
-                            }
-                        }
-                        Ok(perm)
-                    })
-                    .await??;
+        // The default rate keeps everything
+        let (manager, _temp_dir) = create_test_manager(Config::default());
+        let read = Heartbeat::builder("/work/a.rs", 1_700_000_000.0).build();
+        assert!(!manager.should_sample_out(&read));
+        assert_eq!(
+            sample_draw("/work/a.rs", 1_700_000_000.0),
+            sample_draw("/work/a.rs", 1_700_000_000.0)
+        );
+    }
 
-                // Account for newly permanent failures
-                total_failed += perm_count;
-            }
+    #[tokio::test]
+    async fn test_far_future_heartbeats_are_dropped() {
+        let (manager, _temp_dir) = create_test_manager(Config::default());
+        let now = chrono::Utc::now().timestamp() as f64;
+
+        let input = serde_json::json!([
+            {"entity": "/work/now.rs", "time": now},
+            {"entity": "/work/jitter.rs", "time": now + 30.0},
+            {"entity": "/work/next_year.rs", "time": now + 365.0 * 86400.0},
+        ])
+        .to_string();
+
+        assert_eq!(manager.queue_extra_heartbeats(&input).await.unwrap(), 2);
+        let mut queued = manager.queue().get_all().unwrap();
+        queued.sort_by(|a, b| a.entity.cmp(&b.entity));
+        let entities: Vec<&str> = queued.iter().map(|hb| hb.entity.as_str()).collect();
+        assert_eq!(entities, vec!["/work/jitter.rs", "/work/now.rs"]);
+        // Jitter within the allowed skew is kept unchanged
+        assert_eq!(queued[0].time, now + 30.0);
+
+        let (manager, _temp_dir) = create_test_manager(Config {
+            max_future_skew_seconds: 10,
+            ..Default::default()
+        });
+        let ahead = Heartbeat::builder("/work/ahead.rs", now + 60.0).build();
+        manager.add_heartbeat_to_queue(ahead).await.unwrap();
+        assert!(manager.queue().get_all().unwrap().is_empty());
+    }
 
-            // Apply final DB updates for all successfully synced ids in one blocking operation
-            if !synced_ids.is_empty() {
-                let final_ids = synced_ids.clone();
-                tokio::task::spawn_blocking(move || -> Result<(), anyhow::Error> {
-                    let q = crate::queue::Queue::new().map_err(|e| anyhow::anyhow!(e))?;
-                    for id in final_ids {
-                        q.update_sync_status(
-                            &id,
-                            crate::sync::SyncStatus::Synced,
-                            Some("Successfully synced".to_string()),
-                        )
-                        .map_err(|e| anyhow::anyhow!(e))?;
-                        q.remove(&id).map_err(|e| anyhow::anyhow!(e))?;
-                    }
-                    Ok(())
-                })
-                .await??;
-            }
-        }
+    #[test]
+    fn test_machine_name_never_empty() {
+        assert_eq!(
+            resolve_machine_name(Some("box".to_string()), String::new()),
+            "box"
+        );
+        assert_eq!(
+            resolve_machine_name(Some("  ".to_string()), "laptop".to_string()),
+            "laptop"
+        );
 
-        Ok((total_synced, total_failed))
+        // A failed gethostname falls back to the machine id or unknown-host
+        let fallback = resolve_machine_name(None, String::new());
+        assert!(!fallback.is_empty());
+        assert_eq!(fallback, resolve_machine_name(None, " \n".to_string()));
     }
 
-    /// Update failed heartbeats with retry_count < 3 to pending status for retry
-    #[allow(dead_code)]
-    async fn prepare_retry_eligible_failures(&self) -> Result<(), anyhow::Error> {
-        // Run the prepare pass inside a single blocking task so we open the DB once
-        let retry_count: usize = tokio::task::spawn_blocking(|| -> Result<usize, anyhow::Error> {
-            let q = crate::queue::Queue::new().map_err(|e| anyhow::anyhow!(e))?;
-            let failed = q
-                .get_pending(Some(1000), Some(crate::sync::SyncStatus::Failed))
-                .map_err(|e| anyhow::anyhow!(e))?;
+    #[test]
+    fn test_time_normalized_to_seconds() {
+        let from_millis = Heartbeat::builder("/work/main.rs", 1_700_000_000_123.0).build();
+        assert_eq!(from_millis.time, 1_700_000_000.123);
 
-            let mut prepared = 0usize;
-            for hb in failed {
-                let current_retry_count =
-                    q.get_retry_count(&hb.id).map_err(|e| anyhow::anyhow!(e))?;
-                if current_retry_count < 3 {
-                    q.update_sync_status(
-                        &hb.id,
-                        crate::sync::SyncStatus::Pending,
-                        Some(format!("Retry eligible (attempt {})", current_retry_count)),
-                    )
-                    .map_err(|e| anyhow::anyhow!(e))?;
-                    prepared += 1;
-                }
-            }
+        let from_seconds = Heartbeat::builder("/work/main.rs", 1_700_000_000.123_456).build();
+        assert_eq!(from_seconds.time, 1_700_000_000.123);
+    }
 
-            Ok(prepared)
-        })
-        .await??;
+    #[test]
+    fn test_time_accepts_integer_float_and_string() {
+        let parse = |time: &str| {
+            let json = format!(r#"{{"entity": "/work/main.rs", "time": {}}}"#, time);
+            serde_json::from_str::<BulkHeartbeat>(&json).map(|hb| hb.time)
+        };
 
-        if retry_count > 0 {
-            tracing::info!("Prepared {} failed heartbeats for retry", retry_count);
-        }
+        assert_eq!(parse("1700000000").unwrap(), 1_700_000_000.0);
+        assert_eq!(parse("1700000000.5").unwrap(), 1_700_000_000.5);
+        assert_eq!(parse(r#""1700000000""#).unwrap(), 1_700_000_000.0);
+        assert_eq!(parse(r#"" 1700000000.25 ""#).unwrap(), 1_700_000_000.25);
+        assert_eq!(parse(r#""1700000000123""#).unwrap(), 1_700_000_000.123);
+        assert!(parse(r#""yesterday""#).is_err());
+
+        let queued = r#"{"id": "hb-1", "entity": "/work/main.rs", "type": "file",
+            "time": "1700000000", "project": null, "branch": null, "language": null,
+            "is_write": false, "lines": null, "lineno": null, "cursorpos": null,
+            "user_agent": null, "category": null, "machine": null, "editor": null,
+            "operating_system": null, "commit_hash": null, "commit_author": null,
+            "commit_message": null, "repository_url": null, "dependencies": []}"#;
+        let heartbeat: Heartbeat = serde_json::from_str(queued).unwrap();
+        assert_eq!(heartbeat.time, 1_700_000_000.0);
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn test_dry_run_without_entity_is_an_error() {
+        use clap::Parser;
+
+        let (manager, _db_dir) = create_test_manager(Config::default());
+        let cli = Cli::parse_from(["chronova-cli", "--dry-run"]);
+        let err = manager.dry_run(cli).await.unwrap_err();
+        assert!(err.to_string().contains("--entity"));
     }
-}
 
-/// Extension trait for HeartbeatManager to add offline sync capabilities
-#[allow(async_fn_in_trait)]
-pub trait HeartbeatManagerExt {
-    /// Process heartbeats using offline-first strategy
-    async fn process_offline_first(&self) -> Result<(), anyhow::Error>;
+    #[tokio::test]
+    async fn test_editor_detected_from_parent_process() {
+        use clap::Parser;
 
-    /// Get queue statistics including sync status
-    fn get_queue_stats(&self) -> Result<SyncStatusSummary, anyhow::Error>;
+        let config = Config {
+            detect_editor_from_process: true,
+            ..Config::default()
+        };
+        let (mut manager, _db_dir) = create_test_manager(config);
+        manager.collector = DataCollector::new()
+            .with_process_names(|| vec!["bash".to_string(), "code".to_string()]);
+
+        let cli = Cli::parse_from(["chronova-cli", "--entity", "/repo/src/main.rs"]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.editor.map(|e| e.name).as_deref(), Some("VS Code"));
+
+        // An explicit plugin wins over process detection
+        let cli = Cli::parse_from([
+            "chronova-cli",
+            "--entity",
+            "/repo/src/main.rs",
+            "--plugin",
+            "vim/9.0 vim-wakatime/11.0",
+        ]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        let editor = heartbeat.editor.unwrap();
+        assert_eq!(editor.name, "vim");
+        assert_eq!(editor.version.as_deref(), Some("9.0"));
+
+        // Detection stays off unless configured
+        let (mut manager, _db_dir) = create_test_manager(Config::default());
+        manager.collector = DataCollector::new().with_process_names(|| vec!["nvim".to_string()]);
+        let cli = Cli::parse_from(["chronova-cli", "--entity", "/repo/src/main.rs"]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert!(heartbeat.editor.is_none());
+    }
 
-    /// Manually trigger sync of offline heartbeats
-    async fn manual_sync(&self) -> Result<SyncResult, anyhow::Error>;
-}
+    #[tokio::test]
+    async fn test_default_language_for_unknown_file_types() {
+        use clap::Parser;
 
-impl HeartbeatManagerExt for HeartbeatManager {
-    async fn process_offline_first(&self) -> Result<(), anyhow::Error> {
-        // For now, this is a placeholder that uses the existing process_queue logic
-        // In the future, this will integrate with the SyncManager
-        let _ = self.process_queue().await?;
-        Ok(())
+        let unknown = || Cli::parse_from(["chronova-cli", "--entity", "/repo/data.zzq"]);
+        let (manager, _db_dir) = create_test_manager(Config::default());
+        let heartbeat = manager.dry_run(unknown()).await.unwrap().unwrap();
+        assert_eq!(heartbeat.language, None);
+
+        let config = Config {
+            default_language: Some("Other".to_string()),
+            ..Config::default()
+        };
+        let (manager, _db_dir) = create_test_manager(config);
+        let heartbeat = manager.dry_run(unknown()).await.unwrap().unwrap();
+        assert_eq!(heartbeat.language.as_deref(), Some("Other"));
+
+        // Detected and given languages are kept
+        let cli = Cli::parse_from(["chronova-cli", "--entity", "/repo/src/main.rs"]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.language.as_deref(), Some("Rust"));
+        let cli = Cli::parse_from([
+            "chronova-cli",
+            "--entity",
+            "/repo/data.zzq",
+            "--language",
+            "Prolog",
+        ]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.language.as_deref(), Some("Prolog"));
     }
 
-    fn get_queue_stats(&self) -> Result<SyncStatusSummary, anyhow::Error> {
-        // Get sync statistics from the queue
-        let stats = self.queue.get_sync_stats()?;
-        Ok(stats)
+    #[tokio::test]
+    async fn test_detected_project_name_is_sanitized() {
+        use clap::Parser;
+
+        let config = Config {
+            project_name_rewrites: crate::project_name::parse_rewrites("-main$ =>"),
+            ..Config::default()
+        };
+        let (manager, _db_dir) = create_test_manager(config);
+
+        let cli = Cli::parse_from([
+            "chronova-cli",
+            "--entity",
+            "/repo/src/main.rs",
+            "--alternate-project",
+            " chronova.git ",
+        ]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.project.as_deref(), Some("chronova"));
+
+        let cli = Cli::parse_from([
+            "chronova-cli",
+            "--entity",
+            "/repo/src/main.rs",
+            "--alternate-project",
+            "chronova-main",
+        ]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.project.as_deref(), Some("chronova"));
+
+        // --project is used as given
+        let cli = Cli::parse_from([
+            "chronova-cli",
+            "--entity",
+            "/repo/src/main.rs",
+            "--project",
+            "chronova-main.git",
+        ]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.project.as_deref(), Some("chronova-main.git"));
     }
 
-    async fn manual_sync(&self) -> Result<SyncResult, anyhow::Error> {
-        // Process the queue to sync pending heartbeats
-        let start_time = std::time::SystemTime::now();
+    #[test]
+    fn test_redacted_applies_full_privacy_profile() {
+        let config = Config {
+            hide_file_names: true,
+            hide_project_names: true,
+            hide_branch_names: true,
+            hide_project_folder: true,
+            hide_commit_message: true,
+            hide_repository_url: true,
+            ..Config::default()
+        };
+        let heartbeat = Heartbeat::builder("/work/chronova/src/main.rs", 1_700_000_000.0)
+            .project("chronova".to_string())
+            .branch("feature/secret-launch".to_string())
+            .language("Rust".to_string())
+            .lines(120)
+            .lines_in_file(120)
+            .lineno(7)
+            .cursorpos(3)
+            .commit_hash("abc123".to_string())
+            .commit_author("Dev".to_string())
+            .commit_message("Launch the secret thing".to_string())
+            .repository_url("git@example.com:acme/chronova.git".to_string())
+            .dependencies(vec!["serde".to_string()])
+            .project_root("/work/chronova".to_string())
+            .build();
+
+        let redacted = heartbeat.redacted(&config);
+        assert_eq!(redacted.entity, "HIDDEN.rs");
+        assert_eq!(redacted.branch, None);
+        assert_eq!(redacted.lines, None);
+        assert_eq!(redacted.lines_in_file, None);
+        assert_eq!(redacted.lineno, None);
+        assert_eq!(redacted.cursorpos, None);
+        assert!(redacted.dependencies.is_empty());
+        assert_eq!(redacted.commit_hash.as_deref(), Some("abc123"));
+        assert_eq!(redacted.commit_author.as_deref(), Some("Dev"));
+        assert_eq!(redacted.commit_message, None);
+        assert_eq!(redacted.repository_url, None);
+        assert_eq!(redacted.language.as_deref(), Some("Rust"));
+        let project = redacted.project.unwrap();
+        assert!(project.starts_with("project-"));
+        assert!(!project.contains("chronova"));
+        assert_eq!(
+            heartbeat.redacted(&config).project.as_deref(),
+            Some(project.as_str())
+        );
 
-        // Do not clear the queue here; caller (or tests) control initial state.
+        // Without hidden file names the entity is relative to the project
+        let folder_only = Config {
+            hide_project_folder: true,
+            disable_git_info: true,
+            ..Config::default()
+        };
+        let redacted = heartbeat.redacted(&folder_only);
+        assert_eq!(redacted.entity, "src/main.rs");
+        assert_eq!(redacted.commit_hash, None);
+        assert_eq!(redacted.commit_author, None);
+        assert_eq!(redacted.branch.as_deref(), Some("feature/secret-launch"));
+    }
 
-        // Get initial stats before sync
-        let initial_stats = self.queue.get_sync_stats()?;
-        let _initial_total = initial_stats.total;
+    #[tokio::test]
+    async fn test_language_detected_before_file_name_is_hidden() {
+        use clap::Parser;
 
-        // Process the queue and obtain counts
-        let (synced_count, failed_count) = self.process_queue().await?;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("main.py");
+        std::fs::write(&file, "print('hi')\n").unwrap();
 
-        let end_time = std::time::SystemTime::now();
-        let duration = end_time.duration_since(start_time).unwrap_or_default();
+        let (manager, _db_dir) = create_test_manager(Config {
+            hide_file_names: true,
+            ..Config::default()
+        });
+        let cli = Cli::parse_from(["chronova-cli", "--entity", file.to_str().unwrap()]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
 
-        Ok(SyncResult {
-            synced_count,
-            failed_count,
-            total_count: (synced_count + failed_count),
-            duration,
-            error: None,
-            start_time: Some(start_time),
-            end_time: Some(end_time),
-            avg_latency_ms: if (synced_count + failed_count) > 0 {
-                Some(duration.as_millis() as f64 / (synced_count + failed_count) as f64)
-            } else {
-                None
-            },
-        })
+        assert_eq!(heartbeat.entity, "HIDDEN.py");
+        assert_eq!(heartbeat.language.as_deref(), Some("Python"));
     }
-}
 
-impl HeartbeatManager {
-    /// Add a heartbeat directly to the queue for offline processing
-    pub fn add_heartbeat_to_queue(&self, heartbeat: Heartbeat) -> anyhow::Result<()> {
-        // Check if entity should be ignored
-        if self.should_ignore_entity(&heartbeat.entity) {
-            tracing::debug!("Ignoring entity: {}", heartbeat.entity);
-            return Ok(());
-        }
+    #[tokio::test]
+    async fn test_lines_filled_from_file_when_omitted() {
+        use clap::Parser;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("main.py");
+        std::fs::write(&file, "import os\n\nprint(os.getcwd())\n").unwrap();
+        let entity = file.to_str().unwrap();
+
+        let (manager, _db_dir) = create_test_manager(Config::default());
+        let cli = Cli::parse_from(["chronova-cli", "--entity", entity]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.lines, Some(3));
+
+        let (manager, _db_dir) = create_test_manager(Config {
+            hide_file_names: true,
+            ..Config::default()
+        });
+        let cli = Cli::parse_from(["chronova-cli", "--entity", entity]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.lines, None);
 
-        // Add heartbeat to queue
-        self.queue.add(heartbeat)?;
-        tracing::debug!("Heartbeat queued for offline-first processing");
+        let (manager, _db_dir) = create_test_manager(Config {
+            max_detect_file_bytes: 8,
+            ..Config::default()
+        });
+        let cli = Cli::parse_from(["chronova-cli", "--entity", entity]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.lines, None);
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn test_lines_in_file_counted_for_existing_files() {
+        use clap::Parser;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("lib.rs");
+        std::fs::write(&file, "mod a;\nmod b;\nmod c;\nmod d;\n").unwrap();
+
+        let (manager, _db_dir) = create_test_manager(Config::default());
+        let cli = Cli::parse_from([
+            "chronova-cli",
+            "--entity",
+            file.to_str().unwrap(),
+            "--lines",
+            "1",
+        ]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.lines_in_file, Some(4));
+        assert_eq!(heartbeat.lines, Some(1));
+        let payload = serde_json::to_value(&heartbeat).unwrap();
+        assert_eq!(payload["lines_in_file"], 4);
+
+        let cli = Cli::parse_from(["chronova-cli", "--entity", "/no/such/file.rs"]);
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.lines_in_file, None);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_category_inferred_when_absent() {
+        use clap::Parser;
+
+        let (manager, _db_dir) = create_test_manager(Config::default());
+        let readme_write =
+            Cli::parse_from(["chronova-cli", "--entity", "/repo/README.md", "--write"]);
+        let heartbeat = manager.dry_run(readme_write).await.unwrap().unwrap();
+        assert_eq!(heartbeat.category.as_deref(), Some("writing docs"));
+
+        let rust_read = Cli::parse_from(["chronova-cli", "--entity", "/repo/src/main.rs"]);
+        let heartbeat = manager.dry_run(rust_read).await.unwrap().unwrap();
+        assert_eq!(heartbeat.category.as_deref(), Some("coding"));
+
+        let explicit = Cli::parse_from([
+            "chronova-cli",
+            "--entity",
+            "/repo/README.md",
+            "--write",
+            "--category",
+            "code reviewing",
+        ]);
+        let heartbeat = manager.dry_run(explicit).await.unwrap().unwrap();
+        assert_eq!(heartbeat.category.as_deref(), Some("code reviewing"));
+    }
 
-    /// Create a HeartbeatManager backed by an isolated temp database.
-    /// Returns the manager and the TempDir guard — the caller must keep
-    /// the TempDir alive for as long as the manager is used.
-    /// This prevents parallel tests from contending on the shared `~/.chronova/queue.db`.
-    fn create_test_manager(config: Config) -> (HeartbeatManager, tempfile::TempDir) {
-        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
-        let db_path = temp_dir.path().join("test_queue.db");
-        let queue = Queue::with_path(db_path).expect("Failed to create test queue");
-        let manager = HeartbeatManager::new_with_queue(config, queue);
-        (manager, temp_dir)
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_non_utf8_entity_still_creates_heartbeat() {
+        use clap::Parser;
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+
+        let entity = OsString::from_vec(b"/work/caf\xe9/main.rs".to_vec());
+        let args = crate::cli::args_lossy([
+            OsString::from("chronova-cli"),
+            OsString::from("--entity"),
+            entity.clone(),
+        ]);
+        let cli = Cli::parse_from(args);
+
+        let (manager, _db_dir) = create_test_manager(Config::default());
+        let heartbeat = manager.dry_run(cli).await.unwrap().unwrap();
+        assert_eq!(heartbeat.entity, "/work/caf\u{FFFD}/main.rs");
+        assert_eq!(heartbeat.language.as_deref(), Some("Rust"));
+
+        assert_eq!(
+            entity_from_path(std::path::Path::new(&entity)),
+            heartbeat.entity
+        );
     }
 
     #[test]
-    fn test_should_ignore_entity() {
+    fn test_builder_defaults_match_manual_construction() {
+        let built = Heartbeat::builder("/work/main.rs", 1_700_000_000.0)
+            .id("hb-1")
+            .build();
+        let manual = Heartbeat {
+            id: "hb-1".to_string(),
+            entity: "/work/main.rs".to_string(),
+            entity_type: "file".to_string(),
+            time: 1_700_000_000.0,
+            project: None,
+            branch: None,
+            language: None,
+            is_write: false,
+            lines: None,
+            lines_in_file: None,
+            lineno: None,
+            cursorpos: None,
+            user_agent: None,
+            category: None,
+            machine: None,
+            editor: None,
+            operating_system: None,
+            commit_hash: None,
+            commit_author: None,
+            commit_message: None,
+            repository_url: None,
+            dependencies: Vec::new(),
+            project_root: None,
+            extra: serde_json::Map::new(),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&built).unwrap(),
+            serde_json::to_value(&manual).unwrap()
+        );
+
+        // Each build without an explicit id gets a fresh one
+        let a = Heartbeat::builder("/work/main.rs", 1.0).build();
+        let b = Heartbeat::builder("/work/main.rs", 1.0).build();
+        assert_ne!(a.id, b.id);
+
+        let written = Heartbeat::builder("/work/main.rs", 1.0)
+            .is_write(true)
+            .lines(42)
+            .project("chronova".to_string())
+            .build();
+        assert!(written.is_write);
+        assert_eq!(written.lines, Some(42));
+        assert_eq!(written.project.as_deref(), Some("chronova"));
+    }
+
+    #[tokio::test]
+    async fn test_managers_share_client_and_queue() {
         let config = Config {
-            ignore_patterns: vec!["COMMIT_EDITMSG$".to_string(), "*.tmp".to_string()],
+            api_key: Some("test-key".to_string()),
             ..Default::default()
         };
+        let (first, _temp_dir) = create_test_manager(config.clone());
+        let second =
+            HeartbeatManager::with_shared(config, first.api_client_handle(), first.queue_handle())
+                .unwrap();
 
-        let (manager, _temp_dir) = create_test_manager(config);
+        assert!(Arc::ptr_eq(
+            first.authenticated_api_client.as_ref().unwrap(),
+            second.authenticated_api_client.as_ref().unwrap()
+        ));
+        assert!(Arc::ptr_eq(&first.queue, &second.queue));
 
-        assert!(manager.should_ignore_entity("/path/to/COMMIT_EDITMSG"));
-        assert!(manager.should_ignore_entity("/path/to/file.tmp"));
-        assert!(!manager.should_ignore_entity("/path/to/normal_file.rs"));
+        let heartbeat = Heartbeat::builder("/work/shared/main.rs", 1_700_000_000.0).build();
+        first.add_heartbeat_to_queue(heartbeat).await.unwrap();
+
+        assert_eq!(second.get_queue_stats().unwrap().total, 1);
     }
 
     #[test]
-    fn test_heartbeat_manager_ext_implementation() {
-        let config = Config::default();
-        let (manager, _temp_dir) = create_test_manager(config);
-
-        // Clear any existing heartbeats from the queue first
-        let _ = manager.queue.cleanup_old_entries(0); // Remove all entries
-
-        // Test that HeartbeatManagerExt is implemented by calling methods directly
-        let stats = manager.get_queue_stats();
-        assert!(stats.is_ok(), "get_queue_stats should return Ok");
-        assert!(
-            true,
-            "HeartbeatManager should implement HeartbeatManagerExt"
-        );
+    fn test_unusable_tls_settings_are_an_error() {
+        let config = Config {
+            ssl_certs_file: Some("/nonexistent/certs.pem".to_string()),
+            ..Default::default()
+        };
+        let result = HeartbeatManager::new_with_queue(config, Queue::in_memory().unwrap());
+        assert!(matches!(result, Err(crate::api::ApiError::Tls(_))));
     }
 
     #[test]
-    fn test_get_queue_stats() {
-        let config = Config::default();
-        let (manager, _temp_dir) = create_test_manager(config);
+    fn test_export_import_round_trip() {
+        let (source, _source_dir) = create_test_manager(Config::default());
+        for (i, project) in ["alpha", "beta", "gamma"].iter().enumerate() {
+            let heartbeat = Heartbeat::builder(
+                format!("/work/{}/main.rs", project),
+                1_700_000_000.0 + i as f64,
+            )
+            .id(format!("hb-{}", i))
+            .project(project.to_string())
+            .language("Rust".to_string())
+            .user_agent("test/1.0".to_string())
+            .category("coding".to_string())
+            .build();
+            source.queue().add(heartbeat).unwrap();
+        }
+        // Exports cover every status, not just pending rows
+        source
+            .queue()
+            .update_sync_status("hb-1", crate::sync::SyncStatus::PermanentFailure, None)
+            .unwrap();
+
+        let export_dir = tempfile::tempdir().unwrap();
+        let export_path = export_dir.path().join("heartbeats.json");
+        assert_eq!(source.export_heartbeats(&export_path).unwrap(), 3);
+
+        let (target, _target_dir) = create_test_manager(Config::default());
+        assert_eq!(target.import_heartbeats(&export_path).unwrap(), 3);
+
+        let key = |hb: &Heartbeat| (hb.entity.clone(), hb.time.to_bits(), hb.project.clone());
+        let mut expected: Vec<_> = source.queue().get_all().unwrap().iter().map(key).collect();
+        let mut imported: Vec<_> = target.queue().get_all().unwrap().iter().map(key).collect();
+        expected.sort();
+        imported.sort();
+        assert_eq!(imported, expected);
+    }
 
-        // Clear any existing heartbeats from the queue first
-        let _ = manager.queue.cleanup_old_entries(0); // Remove all entries
+    #[tokio::test]
+    async fn test_log_events_carry_heartbeat_id() {
+        use clap::Parser;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
 
-        let stats = manager.get_queue_stats();
-        assert!(stats.is_ok(), "get_queue_stats should return Ok");
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Capture {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
 
-        let summary = stats.unwrap();
-        assert_eq!(summary.total, 0, "Initial queue should be empty");
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&mock_server)
+            .await;
+
+        let config = Config {
+            api_key: Some("test-key".to_string()),
+            api_url: Some(mock_server.uri()),
+            disable_offline: true,
+            ..Default::default()
+        };
+        let (manager, _db_dir) = create_test_manager(config);
+
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .with_max_level(tracing::Level::DEBUG)
+            .finish();
+        {
+            let _default = tracing::subscriber::set_default(subscriber);
+            let cli = Cli::parse_from(["chronova-cli", "--entity", "/tmp/span_test.rs"]);
+            manager.process(cli).await.unwrap();
+        }
+
+        let requests = mock_server.received_requests().await.unwrap();
+        let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        let span = format!("heartbeat{{heartbeat_id={}}}", sent["id"].as_str().unwrap());
+
+        let logs = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("Heartbeat sent directly"));
+        for line in logs.lines() {
+            assert!(line.contains(&span), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_dropping_manager_checkpoints_queue_wal() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let wal_path = temp_dir.path().join("queue.db-wal");
+
+        // A second connection keeps SQLite from checkpointing on its own
+        let other = rusqlite::Connection::open(&db_path).unwrap();
+        let manager = HeartbeatManager::new_with_queue(
+            Config::default(),
+            Queue::with_path(db_path.clone()).unwrap(),
+        )
+        .unwrap();
+        manager
+            .queue()
+            .add(Heartbeat::builder("/tmp/checkpoint.rs", 1_700_000_000.0).build())
+            .unwrap();
+        other
+            .query_row("SELECT COUNT(*) FROM heartbeats", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .unwrap();
+        assert!(std::fs::metadata(&wal_path).unwrap().len() > 0);
+
+        // A manager sharing the queue leaves the checkpoint to the last one
+        let shared =
+            HeartbeatManager::with_shared(Config::default(), None, manager.queue_handle()).unwrap();
+        drop(shared);
+        assert!(std::fs::metadata(&wal_path).unwrap().len() > 0);
+
+        drop(manager);
+        assert_eq!(std::fs::metadata(&wal_path).unwrap().len(), 0);
+        drop(other);
     }
 
     #[tokio::test]
-    #[ignore = "manual_sync internally opens Queue::new() which uses the shared DB path"]
     async fn test_manual_sync() {
         let config = Config::default();
         let (manager, _temp_dir) = create_test_manager(config);
@@ -704,7 +3283,6 @@ mod tests {
     }
 
     #[tokio::test]
-    #[ignore = "manual_sync internally opens Queue::new() which uses the shared DB path"]
     async fn test_manual_sync_with_mock_server_batches() {
         use crate::api::ApiClient;
         use wiremock::matchers::{method, path};
@@ -726,61 +3304,28 @@ mod tests {
         manager.api_client = ApiClient::new(mock_server.uri());
         manager.authenticated_api_client = None;
 
-        // Clear any existing entries from previous test runs
-        let _ = manager.queue.cleanup_old_entries(0);
-
         // Add two heartbeats to the queue using the manager's queue directly
-        let hb1 = Heartbeat {
-            id: "hb-1".to_string(),
-            entity: "/path/a.rs".to_string(),
-            entity_type: "file".to_string(),
-            time: 1.0,
-            project: Some("p".to_string()),
-            branch: None,
-            language: Some("Rust".to_string()),
-            is_write: false,
-            lines: None,
-            lineno: None,
-            cursorpos: None,
-            user_agent: Some("test/1.0".to_string()),
-            category: Some("coding".to_string()),
-            machine: Some("m".to_string()),
-            editor: None,
-            operating_system: None,
-            commit_hash: None,
-            commit_author: None,
-            commit_message: None,
-            repository_url: None,
-            dependencies: Vec::new(),
-        };
-
-        let hb2 = Heartbeat {
-            id: "hb-2".to_string(),
-            entity: "/path/b.rs".to_string(),
-            entity_type: "file".to_string(),
-            time: 2.0,
-            project: Some("p".to_string()),
-            branch: None,
-            language: Some("Rust".to_string()),
-            is_write: false,
-            lines: None,
-            lineno: None,
-            cursorpos: None,
-            user_agent: Some("test/1.0".to_string()),
-            category: Some("coding".to_string()),
-            machine: Some("m".to_string()),
-            editor: None,
-            operating_system: None,
-            commit_hash: None,
-            commit_author: None,
-            commit_message: None,
-            repository_url: None,
-            dependencies: Vec::new(),
-        };
+        let hb1 = Heartbeat::builder("/path/a.rs", 1.0)
+            .id("hb-1")
+            .project("p".to_string())
+            .language("Rust".to_string())
+            .user_agent("test/1.0".to_string())
+            .category("coding".to_string())
+            .machine("m".to_string())
+            .build();
+
+        let hb2 = Heartbeat::builder("/path/b.rs", 2.0)
+            .id("hb-2")
+            .project("p".to_string())
+            .language("Rust".to_string())
+            .user_agent("test/1.0".to_string())
+            .category("coding".to_string())
+            .machine("m".to_string())
+            .build();
 
         // Add heartbeats directly to the manager's queue
-        manager.queue.add(hb1).unwrap();
-        manager.queue.add(hb2).unwrap();
+        manager.queue().add(hb1).unwrap();
+        manager.queue().add(hb2).unwrap();
 
         // Run manual sync which uses batching logic
         let res = manager.manual_sync().await;
@@ -792,5 +3337,8 @@ mod tests {
             sync.synced_count, 2,
             "Both queued heartbeats should be synced"
         );
+
+        // The sync ran against the manager's own queue, not the default database
+        assert_eq!(manager.queue().count().unwrap(), 0);
     }
 }
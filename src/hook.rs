@@ -0,0 +1,185 @@
+//! `pre_send_hook`: an external command that enriches heartbeats.
+//!
+//! The command runs through the shell for every heartbeat before it is queued
+//! or sent. It gets the heartbeat as JSON on stdin and prints the heartbeat to
+//! use on stdout, while anything it writes to stderr goes to the log. Fields it
+//! leaves out keep their values, and fields this CLI doesn't know are sent as
+//! they are:
+//!
+//! ```ini
+//! [settings]
+//! pre_send_hook = jq -c '.ticket = (.branch // "" | capture("(?<t>[A-Z]+-[0-9]+)").t)'
+//! ```
+//!
+//! The hook fails open: when it can't be started, exits non-zero, runs longer
+//! than [`PRE_SEND_HOOK_TIMEOUT`] or prints something that isn't a valid
+//! heartbeat, the original heartbeat is used.
+
+use serde_json::{Map, Value};
+use std::process::Stdio;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+
+use crate::heartbeat::{normalize_heartbeat_time, Heartbeat};
+
+/// How long a hook may run before the original heartbeat is used
+pub const PRE_SEND_HOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Error, Debug)]
+pub enum HookError {
+    #[error("failed to run hook: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("hook timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("hook exited with {0}")]
+    Failed(std::process::ExitStatus),
+    #[error("hook printed invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("hook returned an invalid heartbeat: {0}")]
+    Invalid(String),
+}
+
+/// Run `command` on `heartbeat`, keeping it unchanged if the hook fails
+pub async fn apply(command: &str, heartbeat: Heartbeat, timeout: Duration) -> Heartbeat {
+    match run(command, &heartbeat, timeout).await {
+        Ok(enriched) => enriched,
+        Err(e) => {
+            tracing::warn!("pre_send_hook failed, using the heartbeat unchanged: {}", e);
+            heartbeat
+        }
+    }
+}
+
+/// Run `command` on `heartbeat` and validate the heartbeat it prints
+pub async fn run(
+    command: &str,
+    heartbeat: &Heartbeat,
+    timeout: Duration,
+) -> Result<Heartbeat, HookError> {
+    let input = serde_json::to_vec(heartbeat)?;
+    let mut child = shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+
+    let exchange = async {
+        let write = async move {
+            // A hook that ignores its input may exit before reading all of it
+            let _ = stdin.write_all(&input).await;
+        };
+        let mut output = Vec::new();
+        let mut errors = Vec::new();
+        let (_, read, read_errors) = tokio::join!(
+            write,
+            stdout.read_to_end(&mut output),
+            stderr.read_to_end(&mut errors)
+        );
+        read?;
+        read_errors?;
+        Ok::<_, std::io::Error>((child.wait().await?, output, errors))
+    };
+    let (status, output, errors) = tokio::time::timeout(timeout, exchange)
+        .await
+        .map_err(|_| HookError::Timeout(timeout))??;
+
+    let errors = String::from_utf8_lossy(&errors);
+    if !errors.trim().is_empty() {
+        if status.success() {
+            tracing::debug!("pre_send_hook stderr: {}", errors.trim_end());
+        } else {
+            tracing::warn!("pre_send_hook stderr: {}", errors.trim_end());
+        }
+    }
+
+    if !status.success() {
+        return Err(HookError::Failed(status));
+    }
+    validate(heartbeat, serde_json::from_slice(&output)?)
+}
+
+/// Merge the hook's output over `original` and check the result is still a
+/// heartbeat that can be queued. The id can't be changed.
+fn validate(original: &Heartbeat, output: Value) -> Result<Heartbeat, HookError> {
+    let Value::Object(changes) = output else {
+        return Err(HookError::Invalid("not a JSON object".to_string()));
+    };
+    let mut merged = match serde_json::to_value(original)? {
+        Value::Object(fields) => fields,
+        _ => Map::new(),
+    };
+    merged.extend(changes);
+
+    let mut heartbeat: Heartbeat = serde_json::from_value(Value::Object(merged))?;
+    heartbeat.id = original.id.clone();
+    if heartbeat.entity.trim().is_empty() {
+        return Err(HookError::Invalid("empty entity".to_string()));
+    }
+    if !heartbeat.time.is_finite() || heartbeat.time <= 0.0 {
+        return Err(HookError::Invalid(format!("time {}", heartbeat.time)));
+    }
+    heartbeat.time = normalize_heartbeat_time(heartbeat.time);
+    Ok(heartbeat)
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.args(["/C", command]);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", command]);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_output_is_validated() {
+        let original = Heartbeat::builder("/work/main.rs", 1_700_000_000.0).build();
+
+        let enriched = validate(
+            &original,
+            serde_json::json!({"id": "other", "project": "acme", "ticket": "ABC-1"}),
+        )
+        .unwrap();
+        assert_eq!(enriched.id, original.id);
+        assert_eq!(enriched.entity, "/work/main.rs");
+        assert_eq!(enriched.project.as_deref(), Some("acme"));
+        assert_eq!(enriched.extra["ticket"], "ABC-1");
+
+        assert!(validate(&original, serde_json::json!([1])).is_err());
+        assert!(validate(&original, serde_json::json!({"entity": " "})).is_err());
+        assert!(validate(&original, serde_json::json!({"time": -1.0})).is_err());
+        assert!(validate(&original, serde_json::json!({"is_write": "yes"})).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failing_hook_keeps_original() {
+        let original = Heartbeat::builder("/work/main.rs", 1_700_000_000.0).build();
+        let timeout = Duration::from_millis(500);
+
+        for command in ["exit 3", "echo not-json", "sleep 5"] {
+            let heartbeat = apply(command, original.clone(), timeout).await;
+            assert_eq!(
+                serde_json::to_value(&heartbeat).unwrap(),
+                serde_json::to_value(&original).unwrap(),
+                "{}",
+                command
+            );
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Advisory lock that lets one CLI invocation sync at a time.
+//!
+//! With `single_instance` enabled, an invocation that finds the lock held by
+//! another process still queues its heartbeat but leaves syncing to the lock
+//! holder, so editors firing many invocations don't all contend for the
+//! database and the network.
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+/// Exclusive lock on `~/.chronova/cli.lock`, released when dropped
+#[derive(Debug)]
+pub struct SyncLock {
+    _file: File,
+}
+
+impl SyncLock {
+    /// Path of the default lock file, `~/.chronova/cli.lock`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".chronova").join("cli.lock"))
+    }
+
+    /// Take the lock at `path` without waiting. Returns `Ok(None)` while
+    /// another process holds it.
+    pub fn try_acquire(path: &Path) -> std::io::Result<Option<Self>> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("nested").join("cli.lock");
+
+        let held = SyncLock::try_acquire(&path).unwrap();
+        assert!(held.is_some());
+        assert!(SyncLock::try_acquire(&path).unwrap().is_none());
+
+        drop(held);
+        assert!(SyncLock::try_acquire(&path).unwrap().is_some());
+    }
+}
@@ -3,15 +3,28 @@
 //! A high-performance, drop-in replacement for wakatime-cli written in Rust.
 
 pub mod api;
+pub mod benchmark;
+pub mod category;
 pub mod cli;
 pub mod collector;
 pub mod config;
+pub mod config_watch;
+pub mod debug_dump;
+pub mod exit_code;
+pub mod git_hook;
 pub mod heartbeat;
+pub mod hook;
+pub mod instance_lock;
 pub mod logger;
+pub mod project_cache;
+pub mod project_name;
 pub mod queue;
+pub mod selftest;
+pub mod server_caps;
 pub mod sync;
 pub mod updater;
 pub mod user_agent;
+pub mod watch;
 // Re-export commonly used types for easier access
 pub use api::ApiClient;
 pub use cli::Cli;
@@ -115,7 +115,7 @@ mod tests {
     fn test_setup_logging() {
         // This test just ensures the function doesn't panic
         // We can't easily test the actual logging behavior without complex setup
-        let temp_file = NamedTempFile::new().unwrap();
+        let _temp_file = NamedTempFile::new().unwrap();
         let _guard = setup_logging(false).unwrap();
 
         // Log a test message
@@ -1,16 +1,75 @@
 use anyhow::Result;
 use clap::{CommandFactory, Parser};
-use std::process;
 
 use chronova_cli::api::ApiClient;
 use chronova_cli::cli::Cli;
 use chronova_cli::config::Config;
+use chronova_cli::exit_code::ExitCode;
 use chronova_cli::heartbeat::{HeartbeatManager, HeartbeatManagerExt};
+use chronova_cli::server_caps::ServerCaps;
+use chronova_cli::sync::SyncStatus;
+
+/// `println!` for informational output, which `--quiet` suppresses
+macro_rules! inform {
+    ($quiet:expr, $($arg:tt)*) => {
+        if !$quiet {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Load the configuration with `-o` overrides, the `--proxy` flags, `--api-url`
+/// and the git privacy flags applied, exiting with the config error code when
+/// it can't be read
+fn load_config(cli: &Cli) -> Config {
+    let mut config = Config::load_with_overrides(&cli.config, &cli.config_overrides)
+        .map(|config| config.with_cli_proxy(cli.proxy.as_deref(), cli.no_proxy))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to load configuration: {}", e);
+            ExitCode::Config.exit();
+        });
+    if let Some(api_url) = &cli.api_url {
+        config.api_url = Some(api_url.clone());
+    }
+    // Merge git privacy flags from CLI
+    if cli.disable_git_info {
+        config.disable_git_info = true;
+    }
+    if cli.hide_commit_hash {
+        config.hide_commit_hash = true;
+    }
+    if cli.hide_commit_author {
+        config.hide_commit_author = true;
+    }
+    if cli.hide_commit_message {
+        config.hide_commit_message = true;
+    }
+    if cli.hide_repository_url {
+        config.hide_repository_url = true;
+    }
+    config
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Parse command line arguments
-    let cli = Cli::parse();
+    // Parse command line arguments, expanding @argfiles first
+    let args =
+        chronova_cli::cli::expand_argfiles(chronova_cli::cli::args_lossy(std::env::args_os()))
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to read argument file: {}", e);
+                ExitCode::Config.exit();
+            });
+    let mut cli = Cli::parse_from(args);
+    if cli.entity_from_stdin {
+        match chronova_cli::cli::read_entity(std::io::stdin().lock()) {
+            Ok(entity) => cli.entity = Some(entity),
+            Err(e) => {
+                eprintln!("Failed to read --entity-from-stdin: {}", e);
+                ExitCode::Config.exit();
+            }
+        }
+    }
+    let quiet = cli.quiet;
 
     // Handle --version flag (print version and exit)
     if cli.version {
@@ -19,8 +78,8 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
-    // Handle --today flag (fetch and display today's coding activity)
-    if cli.today {
+    // Handle --today / --today-detailed (fetch and display today's coding activity)
+    if cli.today || cli.today_detailed || cli.today_team.is_some() || cli.today_goal.is_some() {
         // Check if JSON output is requested - if so, disable stdout logging to avoid corrupting JSON
         let json_output = cli
             .output
@@ -32,25 +91,23 @@ async fn main() -> Result<()> {
             chronova_cli::logger::setup_logging_with_output_format(cli.verbose, true)
                 .unwrap_or_else(|e| {
                     eprintln!("Failed to setup logging: {}", e);
-                    process::exit(1);
+                    ExitCode::General.exit();
                 })
         } else {
-            chronova_cli::logger::setup_logging(cli.verbose).unwrap_or_else(|e| {
-                eprintln!("Failed to setup logging: {}", e);
-                process::exit(1);
-            })
+            chronova_cli::logger::setup_logging_with_output_format(cli.verbose, quiet)
+                .unwrap_or_else(|e| {
+                    eprintln!("Failed to setup logging: {}", e);
+                    ExitCode::General.exit();
+                })
         };
 
         // Load configuration
-        let config = Config::load(&cli.config).unwrap_or_else(|e| {
-            eprintln!("Failed to load configuration: {}", e);
-            process::exit(1);
-        });
+        let config = load_config(&cli);
 
         // Fetch and display today's activity
         if let Err(e) = fetch_today_activity(&config, &cli).await {
             eprintln!("Error fetching today's activity: {}", e);
-            process::exit(1);
+            ExitCode::from(&e).exit();
         }
         return Ok(());
     }
@@ -59,7 +116,7 @@ async fn main() -> Result<()> {
     if cli.config_read.is_some() || cli.config_write.is_some() {
         if let Err(e) = handle_config_operations(&cli).await {
             eprintln!("Error handling config operation: {}", e);
-            process::exit(1);
+            ExitCode::from(&e).exit();
         }
         return Ok(());
     }
@@ -77,58 +134,472 @@ async fn main() -> Result<()> {
             chronova_cli::logger::setup_logging_with_output_format(cli.verbose, true)
                 .unwrap_or_else(|e| {
                     eprintln!("Failed to setup logging: {}", e);
-                    process::exit(1);
+                    ExitCode::General.exit();
                 })
         } else {
-            chronova_cli::logger::setup_logging(cli.verbose).unwrap_or_else(|e| {
-                eprintln!("Failed to setup logging: {}", e);
-                process::exit(1);
-            })
+            chronova_cli::logger::setup_logging_with_output_format(cli.verbose, quiet)
+                .unwrap_or_else(|e| {
+                    eprintln!("Failed to setup logging: {}", e);
+                    ExitCode::General.exit();
+                })
         };
 
         // Load configuration
-        let config = Config::load(&cli.config).unwrap_or_else(|e| {
-            eprintln!("Failed to load configuration: {}", e);
-            process::exit(1);
-        });
+        let config = load_config(&cli);
 
         // Initialize heartbeat manager
-        let mut config = config;
-        if let Some(api_url) = &cli.api_url {
-            config.api_url = Some(api_url.clone());
+        let heartbeat_manager = HeartbeatManager::new(config).unwrap_or_else(|e| {
+            eprintln!("Failed to initialize: {}", e);
+            ExitCode::from(&e).exit();
+        });
+
+        // Get queue statistics
+        match heartbeat_manager.get_queue_stats() {
+            Ok(stats) => {
+                inform!(quiet, "Offline heartbeats queue status:");
+                inform!(quiet, "  Total: {}", stats.total);
+                inform!(quiet, "  Pending: {}", stats.pending);
+                inform!(quiet, "  Syncing: {}", stats.syncing);
+                inform!(quiet, "  Synced: {}", stats.synced);
+                inform!(quiet, "  Failed: {}", stats.failed);
+                inform!(quiet, "  Permanent failures: {}", stats.permanent_failures);
+            }
+            Err(e) => {
+                eprintln!("Error getting offline queue stats: {}", e);
+                ExitCode::from(&e).exit();
+            }
         }
-        // Merge git privacy flags from CLI
-        if cli.disable_git_info {
-            config.disable_git_info = true;
+        return Ok(());
+    }
+
+    // Handle --config-validate: strict parse, unlike the lenient load used everywhere else
+    if cli.config_validate {
+        match Config::load_strict(&cli.config) {
+            Ok(_) => inform!(quiet, "Config is valid"),
+            Err(e) => {
+                eprintln!("Invalid config: {}", e);
+                ExitCode::Config.exit();
+            }
         }
-        if cli.hide_commit_hash {
-            config.hide_commit_hash = true;
+        return Ok(());
+    }
+
+    // Handle --print-config-path: show which config file and server are in effect
+    if cli.print_config_path {
+        let path = Config::resolve_config_path(&cli.config)?;
+        let exists = if path.exists() { "exists" } else { "not found" };
+        println!("Config file: {} ({})", path.display(), exists);
+
+        let config = load_config(&cli);
+        println!("API URL: {}", config.get_api_url());
+        match config.masked_api_key(cli.key.as_ref()) {
+            Some(masked) => println!("API key: {}", masked),
+            None => println!("API key: not set"),
         }
-        if cli.hide_commit_author {
-            config.hide_commit_author = true;
+        return Ok(());
+    }
+
+    // Handle --queue-health: inspect the queue database without modifying it
+    if cli.queue_health {
+        let db_path = match cli.offline_queue_file.as_deref() {
+            Some(path) => std::path::PathBuf::from(path),
+            None => chronova_cli::queue::Queue::default_db_path()?,
+        };
+        let health = match chronova_cli::queue::Queue::check_health(&db_path) {
+            Ok(health) => health,
+            Err(e) => {
+                eprintln!("Failed to inspect {}: {}", db_path.display(), e);
+                eprintln!(
+                    "Restore it from {} if it exists, or move it aside so a fresh queue is created.",
+                    db_path.with_extension("db.backup").display()
+                );
+                ExitCode::Queue.exit();
+            }
+        };
+        match cli.output.as_deref() {
+            Some("json") | Some("raw-json") => {
+                println!("{}", serde_json::to_string_pretty(&health)?);
+            }
+            _ => inform!(quiet, "{}", health.to_text()),
         }
-        if cli.hide_commit_message {
-            config.hide_commit_message = true;
+        if !health.is_healthy() {
+            ExitCode::Queue.exit();
         }
-        if cli.hide_repository_url {
-            config.hide_repository_url = true;
+        return Ok(());
+    }
+
+    // Handle --debug-dump-queue: one bundle with everything a bug report needs
+    if let Some(dump_path) = &cli.debug_dump_queue {
+        let db_path = match cli.offline_queue_file.as_deref() {
+            Some(path) => std::path::PathBuf::from(path),
+            None => chronova_cli::queue::Queue::default_db_path()?,
+        };
+        let bundle = chronova_cli::debug_dump::collect(&cli.config, &db_path);
+        if let Err(e) = std::fs::write(dump_path, serde_json::to_vec_pretty(&bundle)?) {
+            eprintln!("Failed to write {}: {}", dump_path, e);
+            ExitCode::General.exit();
         }
-        let heartbeat_manager = HeartbeatManager::new(config);
+        inform!(quiet, "Wrote debug bundle to {}", dump_path);
+        return Ok(());
+    }
 
-        // Get queue statistics
-        match heartbeat_manager.get_queue_stats() {
-            Ok(stats) => {
-                println!("Offline heartbeats queue status:");
-                println!("  Total: {}", stats.total);
-                println!("  Pending: {}", stats.pending);
-                println!("  Syncing: {}", stats.syncing);
-                println!("  Synced: {}", stats.synced);
-                println!("  Failed: {}", stats.failed);
-                println!("  Permanent failures: {}", stats.permanent_failures);
+    // Handle --selftest: offline check of the local pipeline
+    if cli.selftest {
+        let report = chronova_cli::selftest::run(&cli.config).await;
+        match cli.output.as_deref() {
+            Some("json") | Some("raw-json") => {
+                println!("{}", serde_json::to_string_pretty(&report)?);
             }
+            _ => inform!(quiet, "{}", report.to_text()),
+        }
+        if !report.passed {
+            ExitCode::General.exit();
+        }
+        return Ok(());
+    }
+
+    // Handle --benchmark: local throughput numbers against a throwaway queue
+    if let Some(count) = cli.benchmark {
+        match chronova_cli::benchmark::run(count).await {
+            Ok(report) => match cli.output.as_deref() {
+                Some("json") | Some("raw-json") => {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                }
+                _ => inform!(quiet, "{}", report.to_text()),
+            },
             Err(e) => {
-                eprintln!("Error getting offline queue stats: {}", e);
-                process::exit(1);
+                eprintln!("Benchmark failed: {}", e);
+                ExitCode::General.exit();
+            }
+        }
+        return Ok(());
+    }
+
+    // Handle --install-git-hook: write a post-commit hook for the current repository
+    if cli.install_git_hook {
+        let cwd = std::env::current_dir()?;
+        let exe = std::env::current_exe()?;
+        match chronova_cli::git_hook::install_post_commit_hook(&cwd, &exe) {
+            Ok(path) => inform!(quiet, "Installed post-commit hook at {}", path.display()),
+            Err(e) => {
+                eprintln!("Failed to install git hook: {}", e);
+                ExitCode::from(&e).exit();
+            }
+        }
+        return Ok(());
+    }
+
+    // Handle --from-git-hook: queue write heartbeats for the files in HEAD
+    if cli.from_git_hook {
+        let _guard = chronova_cli::logger::setup_logging_with_output_format(cli.verbose, quiet)
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to setup logging: {}", e);
+                ExitCode::General.exit();
+            });
+
+        // Load configuration
+        let config = load_config(&cli);
+        let heartbeat_manager = HeartbeatManager::new(config).unwrap_or_else(|e| {
+            eprintln!("Failed to initialize: {}", e);
+            ExitCode::from(&e).exit();
+        });
+
+        let cwd = std::env::current_dir()?;
+        if let Err(e) = heartbeat_manager.queue_git_commit(&cli, &cwd).await {
+            eprintln!("Error queueing git commit heartbeats: {}", e);
+            ExitCode::from(&e).exit();
+        }
+        if let Err(e) = heartbeat_manager.process_offline_first().await {
+            tracing::warn!("Failed to sync git commit heartbeats: {}", e);
+        }
+        return Ok(());
+    }
+
+    // Handle --watch: queue write heartbeats for saves under a directory until interrupted
+    if let Some(dir) = &cli.watch {
+        let _guard = chronova_cli::logger::setup_logging_with_output_format(cli.verbose, quiet)
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to setup logging: {}", e);
+                ExitCode::General.exit();
+            });
+
+        // Load configuration
+        let config = load_config(&cli);
+        let heartbeat_manager = HeartbeatManager::new(config).unwrap_or_else(|e| {
+            eprintln!("Failed to initialize: {}", e);
+            ExitCode::from(&e).exit();
+        });
+
+        let mut watcher = chronova_cli::watch::DirWatcher::new(std::path::Path::new(dir))
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to watch {}: {}", dir, e);
+                ExitCode::General.exit();
+            });
+        tracing::info!("Watching {} for file saves", dir);
+
+        while let Some(saves) = watcher.next_saves().await {
+            let mut queued = 0;
+            for path in saves {
+                match heartbeat_manager.queue_file_write(&cli, &path).await {
+                    Ok(true) => queued += 1,
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!("Failed to queue {}: {}", path.display(), e),
+                }
+            }
+            if queued > 0 {
+                if let Err(e) = heartbeat_manager.process_offline_first().await {
+                    tracing::warn!("Failed to sync watched heartbeats: {}", e);
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    // Handle --status: queue summary plus in-flight/stuck syncing heartbeats
+    if cli.status {
+        let _guard = chronova_cli::logger::setup_logging_with_output_format(cli.verbose, quiet)
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to setup logging: {}", e);
+                ExitCode::General.exit();
+            });
+
+        // Load configuration
+        let config = load_config(&cli);
+        let heartbeat_manager = HeartbeatManager::new(config).unwrap_or_else(|e| {
+            eprintln!("Failed to initialize: {}", e);
+            ExitCode::from(&e).exit();
+        });
+
+        let stats = heartbeat_manager.get_queue_stats().unwrap_or_else(|e| {
+            eprintln!("Error getting offline queue stats: {}", e);
+            ExitCode::from(&e).exit();
+        });
+        inform!(quiet, "Offline heartbeats queue status:");
+        inform!(quiet, "  Total: {}", stats.total);
+        inform!(quiet, "  Pending: {}", stats.pending);
+        inform!(quiet, "  Syncing: {}", stats.syncing);
+        inform!(quiet, "  Synced: {}", stats.synced);
+        inform!(quiet, "  Failed: {}", stats.failed);
+        inform!(quiet, "  Permanent failures: {}", stats.permanent_failures);
+        inform!(
+            quiet,
+            "  Data size: {} bytes stored, {} bytes uncompressed",
+            stats.stored_bytes,
+            stats.logical_bytes
+        );
+
+        let syncing = heartbeat_manager
+            .get_syncing_heartbeats()
+            .unwrap_or_else(|e| {
+                eprintln!("Error listing syncing heartbeats: {}", e);
+                ExitCode::from(&e).exit();
+            });
+        if !syncing.is_empty() {
+            inform!(quiet, "Syncing heartbeats:");
+            for entry in syncing {
+                let age = entry
+                    .last_attempt_age
+                    .map(|age| format!("last attempt {}s ago", age.as_secs()))
+                    .unwrap_or_else(|| "no attempt recorded".to_string());
+                let flag = if entry.is_stale(chronova_cli::sync::SYNCING_STALE_TIMEOUT) {
+                    " - stuck (will be requeued)"
+                } else {
+                    ""
+                };
+                inform!(quiet, "  {} {} ({}){}", entry.id, entry.entity, age, flag);
+            }
+        }
+
+        let failed = heartbeat_manager.get_failed_entries().unwrap_or_else(|e| {
+            eprintln!("Error listing failed heartbeats: {}", e);
+            ExitCode::from(&e).exit();
+        });
+        if !failed.is_empty() {
+            inform!(quiet, "Failed heartbeats:");
+            for entry in failed {
+                let metadata = entry.sync_metadata.unwrap_or_default();
+                let next_retry = match (entry.sync_status, metadata.next_retry_at) {
+                    (SyncStatus::PermanentFailure, _) => "no more retries".to_string(),
+                    (_, Some(at)) => format!("next retry {}", at.format("%Y-%m-%d %H:%M:%S UTC")),
+                    (_, None) => "retried on next sync".to_string(),
+                };
+                let error = metadata
+                    .last_error
+                    .or(metadata.note)
+                    .unwrap_or_else(|| "unknown error".to_string());
+                inform!(
+                    quiet,
+                    "  {} {} (attempt {}, {}): {}",
+                    entry.heartbeat.id,
+                    entry.heartbeat.entity,
+                    metadata.attempt,
+                    next_retry,
+                    error
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    // Handle --print-offline-heartbeats: unsynced queue entries with their retry state as JSON
+    if let Some(limit) = cli.print_offline_heartbeats {
+        let _guard = chronova_cli::logger::setup_logging_with_output_format(cli.verbose, true)
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to setup logging: {}", e);
+                ExitCode::General.exit();
+            });
+
+        let config = load_config(&cli);
+        let heartbeat_manager = HeartbeatManager::new(config).unwrap_or_else(|e| {
+            eprintln!("Failed to initialize: {}", e);
+            ExitCode::from(&e).exit();
+        });
+
+        let entries = heartbeat_manager
+            .get_unsynced_entries(Some(limit.max(0) as usize))
+            .unwrap_or_else(|e| {
+                eprintln!("Error listing offline heartbeats: {}", e);
+                ExitCode::from(&e).exit();
+            });
+        println!("{}", serde_json::to_string(&entries)?);
+        return Ok(());
+    }
+
+    // Handle heartbeat export/import in WakaTime bulk format
+    if cli.export_heartbeats.is_some() || cli.import_heartbeats.is_some() {
+        let _guard = chronova_cli::logger::setup_logging_with_output_format(cli.verbose, quiet)
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to setup logging: {}", e);
+                ExitCode::General.exit();
+            });
+
+        // Load configuration
+        let config = load_config(&cli);
+        let heartbeat_manager = HeartbeatManager::new(config).unwrap_or_else(|e| {
+            eprintln!("Failed to initialize: {}", e);
+            ExitCode::from(&e).exit();
+        });
+
+        if let Some(path) = &cli.export_heartbeats {
+            match heartbeat_manager.export_heartbeats(std::path::Path::new(path)) {
+                Ok(count) => inform!(quiet, "Exported {} heartbeats to {}", count, path),
+                Err(e) => {
+                    eprintln!("Error exporting heartbeats: {}", e);
+                    ExitCode::from(&e).exit();
+                }
+            }
+        }
+        if let Some(path) = &cli.import_heartbeats {
+            match heartbeat_manager.import_heartbeats(std::path::Path::new(path)) {
+                Ok(count) => inform!(quiet, "Imported {} heartbeats from {}", count, path),
+                Err(e) => {
+                    eprintln!("Error importing heartbeats: {}", e);
+                    ExitCode::from(&e).exit();
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    // Handle --fix-timestamps: repair unsynced heartbeats recorded with a wrong clock
+    if let Some(offset) = cli.offset.filter(|_| cli.fix_timestamps) {
+        let _guard = chronova_cli::logger::setup_logging_with_output_format(cli.verbose, quiet)
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to setup logging: {}", e);
+                ExitCode::General.exit();
+            });
+
+        let config = load_config(&cli);
+        let heartbeat_manager = HeartbeatManager::new(config).unwrap_or_else(|e| {
+            eprintln!("Failed to initialize: {}", e);
+            ExitCode::from(&e).exit();
+        });
+
+        let fixes = heartbeat_manager
+            .fix_timestamps(offset, cli.dry_run)
+            .unwrap_or_else(|e| {
+                eprintln!("Error fixing timestamps: {}", e);
+                ExitCode::from(&e).exit();
+            });
+        match cli.output.as_deref() {
+            Some("json") | Some("raw-json") => {
+                println!("{}", serde_json::to_string_pretty(&fixes)?);
+            }
+            _ => {
+                for fix in &fixes {
+                    inform!(
+                        quiet,
+                        "  {} {}: {} -> {}",
+                        fix.id,
+                        fix.entity,
+                        format_heartbeat_time(fix.old_time),
+                        format_heartbeat_time(fix.new_time)
+                    );
+                }
+                let verb = if cli.dry_run {
+                    "Would shift"
+                } else {
+                    "Shifted"
+                };
+                inform!(
+                    quiet,
+                    "{} {} heartbeats by {} seconds",
+                    verb,
+                    fixes.len(),
+                    offset
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    // Handle --repair-worktree-paths: rename heartbeats queued under a worktree's folder name
+    if cli.repair_worktree_paths {
+        let _guard = chronova_cli::logger::setup_logging_with_output_format(cli.verbose, quiet)
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to setup logging: {}", e);
+                ExitCode::General.exit();
+            });
+
+        let config = load_config(&cli);
+        let heartbeat_manager = HeartbeatManager::new(config).unwrap_or_else(|e| {
+            eprintln!("Failed to initialize: {}", e);
+            ExitCode::from(&e).exit();
+        });
+
+        let repairs = heartbeat_manager
+            .repair_worktree_paths(cli.dry_run)
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Error repairing worktree projects: {}", e);
+                ExitCode::from(&e).exit();
+            });
+        match cli.output.as_deref() {
+            Some("json") | Some("raw-json") => {
+                println!("{}", serde_json::to_string_pretty(&repairs)?);
+            }
+            _ => {
+                for repair in &repairs {
+                    inform!(
+                        quiet,
+                        "  {} {}: {} -> {}",
+                        repair.id,
+                        repair.entity,
+                        repair.old_project.as_deref().unwrap_or("(none)"),
+                        repair.new_project
+                    );
+                }
+                let verb = if cli.dry_run {
+                    "Would update"
+                } else {
+                    "Updated"
+                };
+                inform!(
+                    quiet,
+                    "{} the project of {} heartbeats",
+                    verb,
+                    repairs.len()
+                );
             }
         }
         return Ok(());
@@ -136,18 +607,26 @@ async fn main() -> Result<()> {
 
     // Handle file experts operations
     if cli.file_experts {
-        return Err(anyhow::anyhow!(
-            "File experts operation is not yet implemented. \n\
-            This feature will be available in a future release."
-        ));
-    }
+        let json_output = cli
+            .output
+            .as_ref()
+            .is_some_and(|format| format == "json" || format == "raw-json");
+        let _guard = chronova_cli::logger::setup_logging_with_output_format(
+            cli.verbose,
+            quiet || json_output,
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to setup logging: {}", e);
+            ExitCode::General.exit();
+        });
+
+        let config = load_config(&cli);
 
-    // Handle today goal operations
-    if cli.today_goal.is_some() {
-        return Err(anyhow::anyhow!(
-            "Today goal operation is not yet implemented. \n\
-            This feature will be available in a future release."
-        ));
+        if let Err(e) = fetch_file_experts(&config, &cli).await {
+            eprintln!("Error fetching file experts: {}", e);
+            ExitCode::from(&e).exit();
+        }
+        return Ok(());
     }
 
     // Handle --check-update: query GitHub for latest release and report
@@ -156,21 +635,22 @@ async fn main() -> Result<()> {
             .map_err(|e| anyhow::anyhow!("Failed to initialize updater: {}", e))?;
         match updater.check_for_update().await {
             Ok(Some(info)) => {
-                println!(
+                inform!(
+                    quiet,
                     "Update available: {} (current: {})",
                     info.version,
                     env!("CARGO_PKG_VERSION")
                 );
-                println!("Download: {}", info.download_url);
+                inform!(quiet, "Download: {}", info.download_url);
                 return Ok(());
             }
             Ok(None) => {
-                println!("Already up to date (v{})", env!("CARGO_PKG_VERSION"));
+                inform!(quiet, "Already up to date (v{})", env!("CARGO_PKG_VERSION"));
                 return Ok(());
             }
             Err(e) => {
                 eprintln!("Error checking for update: {}", e);
-                process::exit(1);
+                ExitCode::from(&e).exit();
             }
         }
     }
@@ -181,17 +661,17 @@ async fn main() -> Result<()> {
             .map_err(|e| anyhow::anyhow!("Failed to initialize updater: {}", e))?;
         match updater.check_and_update().await {
             Ok(true) => {
-                println!("Successfully updated to the latest version.");
-                println!("Restart chronova-cli to use the new version.");
+                inform!(quiet, "Successfully updated to the latest version.");
+                inform!(quiet, "Restart chronova-cli to use the new version.");
                 return Ok(());
             }
             Ok(false) => {
-                println!("Already up to date (v{})", env!("CARGO_PKG_VERSION"));
+                inform!(quiet, "Already up to date (v{})", env!("CARGO_PKG_VERSION"));
                 return Ok(());
             }
             Err(e) => {
                 eprintln!("Error updating: {}", e);
-                process::exit(1);
+                ExitCode::from(&e).exit();
             }
         }
     }
@@ -216,48 +696,29 @@ async fn main() -> Result<()> {
             chronova_cli::logger::setup_logging_with_output_format(cli.verbose, true)
                 .unwrap_or_else(|e| {
                     eprintln!("Failed to setup logging: {}", e);
-                    process::exit(1);
+                    ExitCode::General.exit();
                 })
         } else {
-            chronova_cli::logger::setup_logging(cli.verbose).unwrap_or_else(|e| {
-                eprintln!("Failed to setup logging: {}", e);
-                process::exit(1);
-            })
+            chronova_cli::logger::setup_logging_with_output_format(cli.verbose, quiet)
+                .unwrap_or_else(|e| {
+                    eprintln!("Failed to setup logging: {}", e);
+                    ExitCode::General.exit();
+                })
         };
 
         // Load configuration
-        let config = Config::load(&cli.config).unwrap_or_else(|e| {
-            eprintln!("Failed to load configuration: {}", e);
-            process::exit(1);
-        });
+        let config = load_config(&cli);
 
         // Initialize heartbeat manager
-        let mut config = config;
-        if let Some(api_url) = &cli.api_url {
-            config.api_url = Some(api_url.clone());
-        }
-        // Merge git privacy flags from CLI
-        if cli.disable_git_info {
-            config.disable_git_info = true;
-        }
-        if cli.hide_commit_hash {
-            config.hide_commit_hash = true;
-        }
-        if cli.hide_commit_author {
-            config.hide_commit_author = true;
-        }
-        if cli.hide_commit_message {
-            config.hide_commit_message = true;
-        }
-        if cli.hide_repository_url {
-            config.hide_repository_url = true;
-        }
-        let heartbeat_manager = HeartbeatManager::new(config);
+        let heartbeat_manager = HeartbeatManager::new(config).unwrap_or_else(|e| {
+            eprintln!("Failed to initialize: {}", e);
+            ExitCode::from(&e).exit();
+        });
 
         // Read extra heartbeats from STDIN as JSON array
         if let Err(e) = process_extra_heartbeats(heartbeat_manager).await {
             eprintln!("Error processing extra heartbeats: {}", e);
-            process::exit(1);
+            ExitCode::from(&e).exit();
         }
         return Ok(());
     }
@@ -267,7 +728,7 @@ async fn main() -> Result<()> {
         eprintln!("Error: --entity argument is required");
         eprintln!();
         eprintln!("{}", Cli::command().render_help());
-        process::exit(1);
+        ExitCode::Config.exit();
     }
 
     // Check if JSON output is requested - if so, disable stdout logging to avoid corrupting JSON
@@ -277,25 +738,23 @@ async fn main() -> Result<()> {
         .is_some_and(|format| format == "json" || format == "raw-json");
 
     // Setup logging with appropriate output format handling
-    let _guard = if json_output {
-        chronova_cli::logger::setup_logging_with_output_format(cli.verbose, true).unwrap_or_else(
-            |e| {
-                eprintln!("Failed to setup logging: {}", e);
-                process::exit(1);
-            },
-        )
-    } else {
-        chronova_cli::logger::setup_logging(cli.verbose).unwrap_or_else(|e| {
-            eprintln!("Failed to setup logging: {}", e);
-            process::exit(1);
-        })
-    };
+    let _guard =
+        if json_output {
+            chronova_cli::logger::setup_logging_with_output_format(cli.verbose, true)
+                .unwrap_or_else(|e| {
+                    eprintln!("Failed to setup logging: {}", e);
+                    ExitCode::General.exit();
+                })
+        } else {
+            chronova_cli::logger::setup_logging_with_output_format(cli.verbose, quiet)
+                .unwrap_or_else(|e| {
+                    eprintln!("Failed to setup logging: {}", e);
+                    ExitCode::General.exit();
+                })
+        };
 
     // Load configuration
-    let config = Config::load(&cli.config).unwrap_or_else(|e| {
-        eprintln!("Failed to load configuration: {}", e);
-        process::exit(1);
-    });
+    let config = load_config(&cli);
 
     // Spawn background auto-update if enabled in config
     if config.auto_update {
@@ -332,60 +791,66 @@ async fn main() -> Result<()> {
             chronova_cli::logger::setup_logging_with_output_format(cli.verbose, true)
                 .unwrap_or_else(|e| {
                     eprintln!("Failed to setup logging: {}", e);
-                    process::exit(1);
+                    ExitCode::General.exit();
                 })
         } else {
-            chronova_cli::logger::setup_logging(cli.verbose).unwrap_or_else(|e| {
-                eprintln!("Failed to setup logging: {}", e);
-                process::exit(1);
-            })
+            chronova_cli::logger::setup_logging_with_output_format(cli.verbose, quiet)
+                .unwrap_or_else(|e| {
+                    eprintln!("Failed to setup logging: {}", e);
+                    ExitCode::General.exit();
+                })
         };
 
         // Load configuration
-        let config = Config::load(&cli.config).unwrap_or_else(|e| {
-            eprintln!("Failed to load configuration: {}", e);
-            process::exit(1);
-        });
+        let config = load_config(&cli);
 
         // Initialize heartbeat manager
         let mut config = config;
-        if let Some(api_url) = &cli.api_url {
-            config.api_url = Some(api_url.clone());
-        }
-        // Merge git privacy flags from CLI
-        if cli.disable_git_info {
-            config.disable_git_info = true;
-        }
-        if cli.hide_commit_hash {
-            config.hide_commit_hash = true;
-        }
-        if cli.hide_commit_author {
-            config.hide_commit_author = true;
-        }
-        if cli.hide_commit_message {
-            config.hide_commit_message = true;
-        }
-        if cli.hide_repository_url {
-            config.hide_repository_url = true;
+        if let Some(days) = cli.sync_max_age {
+            config.sync_config.max_age_days = Some(days);
         }
-        let heartbeat_manager = HeartbeatManager::new(config);
+        let heartbeat_manager = HeartbeatManager::new(config).unwrap_or_else(|e| {
+            eprintln!("Failed to initialize: {}", e);
+            ExitCode::from(&e).exit();
+        });
 
         // Perform manual sync
-        println!("Syncing offline heartbeats...");
+        if !json_output {
+            inform!(quiet, "Syncing offline heartbeats...");
+        }
         let force = cli.force_sync;
-        match heartbeat_manager.manual_sync().await {
+        let sync_result = if cli.catch_up {
+            heartbeat_manager
+                .catch_up(|progress| {
+                    if !json_output {
+                        inform!(
+                            quiet,
+                            "  Uploaded {} heartbeats, {} remaining",
+                            progress.synced,
+                            progress.remaining
+                        );
+                    }
+                })
+                .await
+        } else {
+            heartbeat_manager.manual_sync().await
+        };
+        match sync_result {
+            Ok(result) if json_output => {
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            }
             Ok(result) => {
-                println!("Sync completed:");
-                println!("  Heartbeats synced: {}", result.synced_count);
-                println!("  Heartbeats failed: {}", result.failed_count);
-                println!("  Total processed: {}", result.total_count);
+                inform!(quiet, "Sync completed:");
+                inform!(quiet, "  Heartbeats synced: {}", result.synced_count);
+                inform!(quiet, "  Heartbeats failed: {}", result.failed_count);
+                inform!(quiet, "  Total processed: {}", result.total_count);
                 if force {
-                    println!("  Forced sync: true");
+                    inform!(quiet, "  Forced sync: true");
                 }
             }
             Err(e) => {
                 eprintln!("Error syncing offline heartbeats: {}", e);
-                process::exit(1);
+                ExitCode::from(&e).exit();
             }
         }
         return Ok(());
@@ -393,83 +858,229 @@ async fn main() -> Result<()> {
 
     // Initialize heartbeat manager
     let mut config = config;
-    if let Some(api_url) = &cli.api_url {
-        config.api_url = Some(api_url.clone());
+    if cli.disable_offline {
+        config.disable_offline = true;
     }
-    // Merge git privacy flags from CLI
-    if cli.disable_git_info {
-        config.disable_git_info = true;
-    }
-    if cli.hide_commit_hash {
-        config.hide_commit_hash = true;
-    }
-    if cli.hide_commit_author {
-        config.hide_commit_author = true;
-    }
-    if cli.hide_commit_message {
-        config.hide_commit_message = true;
+    // A wrong --api-url otherwise only shows as heartbeats piling up in the queue
+    if cli.verbose && cli.api_url.is_some() && !cli.dry_run {
+        let reachable = api_reachable(&config).await;
+        inform!(
+            quiet,
+            "API server {} is {}",
+            config.get_api_url(),
+            if reachable {
+                "reachable"
+            } else {
+                "not reachable; heartbeats will be queued until it is"
+            }
+        );
     }
-    if cli.hide_repository_url {
-        config.hide_repository_url = true;
+    let heartbeat_manager = HeartbeatManager::new(config).unwrap_or_else(|e| {
+        eprintln!("Failed to initialize: {}", e);
+        ExitCode::from(&e).exit();
+    });
+
+    // Handle --dry-run: show the heartbeat (and optionally detection details) without sending
+    if cli.dry_run {
+        if cli.verbose {
+            if let Some(entity) = &cli.entity {
+                for line in heartbeat_manager.detection_diagnostics(entity) {
+                    println!("{}", line);
+                }
+            }
+        }
+        match heartbeat_manager.dry_run(cli).await {
+            Ok(Some(heartbeat)) => println!("{}", serde_json::to_string_pretty(&heartbeat)?),
+            Ok(None) => inform!(
+                quiet,
+                "Entity matches an ignore pattern; no heartbeat would be sent"
+            ),
+            Err(e) => {
+                eprintln!("Error building heartbeat: {}", e);
+                ExitCode::from(&e).exit();
+            }
+        }
+        return Ok(());
     }
-    let heartbeat_manager = HeartbeatManager::new(config);
 
     // Process the heartbeat
     if let Err(e) = heartbeat_manager.process(cli).await {
         eprintln!("Error processing heartbeat: {}", e);
-        process::exit(1);
+        ExitCode::from(&e).exit();
     }
 
     Ok(())
 }
 
+/// Whether the configured API server answers within a few seconds
+async fn api_reachable(config: &Config) -> bool {
+    let Ok(client) = ApiClient::from_config(config) else {
+        return false;
+    };
+    tokio::time::timeout(
+        std::time::Duration::from_secs(3),
+        client.check_connectivity(),
+    )
+    .await
+    .is_ok_and(|result| result.unwrap_or(false))
+}
+
 async fn fetch_today_activity(config: &Config, cli: &Cli) -> Result<(), anyhow::Error> {
     let api_key = config.api_key.as_ref().ok_or_else(|| {
-        anyhow::anyhow!(
+        chronova_cli::api::ApiError::Auth(
             "API key not found in configuration. Please set api_key in your .chronova.cfg file."
+                .to_string(),
         )
     })?;
 
-    let base_url = config.get_api_url();
-    let api_client = ApiClient::new(base_url);
-    let auth_client = api_client.with_api_key(api_key.clone());
+    let api_client = ApiClient::from_config(config)?;
+    let mut auth_client = api_client.with_api_key(api_key.clone());
+    if let Some(path) = ServerCaps::default_path() {
+        auth_client = auth_client.with_server_caps(ServerCaps::new(path));
+    }
 
-    // Fetch today's statusbar data using the correct endpoint
-    let statusbar_data = auth_client.get_today_statusbar().await?;
+    // --today-goal prints the time done toward one goal, like wakatime-cli
+    if let Some(goal_id) = &cli.today_goal {
+        let goal = auth_client.get_goal_progress(goal_id).await?;
+        match cli.output.as_deref() {
+            Some("json") | Some("raw-json") => print!("{}", serde_json::to_string(&goal)?),
+            _ => println!("{}", goal.text),
+        }
+        return Ok(());
+    }
 
-    // Handle output format based on --output flag
-    if let Some(output_format) = &cli.output {
-        match output_format.as_str() {
-            "json" | "raw-json" => {
-                // Return JSON format expected by VSCode WakaTime extension
-                // When output is JSON, we MUST only output the JSON and nothing else
-                // to avoid breaking VSCode extension parsing
-                let json_output = serde_json::json!({
-                    "text": chronova_cli::api::format_today_output(&statusbar_data, cli.today_hide_categories),
-                    "has_team_features": statusbar_data.has_team_features.unwrap_or(false)
-                });
-                // Use print! instead of println! to avoid adding extra newline for JSON output
-                print!("{}", serde_json::to_string(&json_output)?);
+    // --today-team needs an account with team features; others get the usual output
+    let mut statusbar = None;
+    if let Some(team_id) = &cli.today_team {
+        let own = auth_client.get_today_statusbar().await?;
+        if own.has_team_features == Some(true) {
+            let team = auth_client.get_team_today(team_id).await?;
+            match cli.output.as_deref() {
+                Some("json") | Some("raw-json") => print!("{}", serde_json::to_string(&team)?),
+                _ => println!(
+                    "{}",
+                    chronova_cli::api::format_team_today(
+                        &team,
+                        chronova_cli::api::TODAY_BREAKDOWN_ROWS
+                    )
+                ),
             }
-            _ => {
-                // Default text output
-                let output = chronova_cli::api::format_today_output(
-                    &statusbar_data,
-                    cli.today_hide_categories,
-                );
-                println!("{}", output);
+            return Ok(());
+        }
+        eprintln!("Warning: ignoring --today-team, this account has no team features");
+        statusbar = Some(own);
+    }
+
+    // --today-detailed breaks the day down by language, project and editor
+    if cli.today_detailed {
+        let stats = auth_client.get_today_stats().await?;
+        match cli.output.as_deref() {
+            Some("json") | Some("raw-json") => {
+                print!("{}", serde_json::to_string(&stats.data)?);
             }
+            _ => println!(
+                "{}",
+                chronova_cli::api::format_stats_breakdown(
+                    &stats.data,
+                    chronova_cli::api::TODAY_BREAKDOWN_ROWS
+                )
+            ),
         }
+        return Ok(());
+    }
+
+    // With --project, show only that project's total from the summaries endpoint
+    if let Some(project) = &cli.project {
+        let total = auth_client.get_today_project_total(project).await?;
+        if cli
+            .output
+            .as_ref()
+            .is_some_and(|format| format == "json" || format == "raw-json")
+        {
+            print!("{}", serde_json::to_string(&total)?);
+        } else {
+            println!("{}", total.text);
+        }
+        return Ok(());
+    }
+
+    // raw-json passes the server's response through untouched, like wakatime-cli
+    if cli.output.as_deref() == Some("raw-json") {
+        print!("{}", auth_client.get_today_statusbar_raw().await?);
+        return Ok(());
+    }
+
+    // Fetch today's statusbar data using the correct endpoint
+    let statusbar_data = match statusbar {
+        Some(statusbar) => statusbar,
+        None => auth_client.get_today_statusbar().await?,
+    };
+
+    // --today-raw keeps wakatime-cli's empty output when there is no activity
+    let empty_text = if cli.today_raw {
+        None
     } else {
-        // Default text output when no --output flag is provided
-        let output =
-            chronova_cli::api::format_today_output(&statusbar_data, cli.today_hide_categories);
-        println!("{}", output);
+        Some(config.today_empty_text.as_str())
+    };
+    let text = chronova_cli::api::format_today_output(
+        &statusbar_data,
+        cli.today_hide_categories,
+        empty_text,
+    );
+
+    // Handle output format based on --output flag
+    match cli.output.as_deref() {
+        Some("json") => {
+            // Return JSON format expected by VSCode WakaTime extension
+            // When output is JSON, we MUST only output the JSON and nothing else
+            // to avoid breaking VSCode extension parsing
+            let json_output = serde_json::json!({
+                "text": text,
+                "has_team_features": statusbar_data.has_team_features.unwrap_or(false)
+            });
+            // Use print! instead of println! to avoid adding extra newline for JSON output
+            print!("{}", serde_json::to_string(&json_output)?);
+        }
+        _ => {
+            // Default text output
+            println!("{}", text);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the top contributors to `--entity` for `--file-experts`
+async fn fetch_file_experts(config: &Config, cli: &Cli) -> Result<(), anyhow::Error> {
+    let api_key = config.api_key.as_ref().ok_or_else(|| {
+        chronova_cli::api::ApiError::Auth(
+            "API key not found in configuration. Please set api_key in your .chronova.cfg file."
+                .to_string(),
+        )
+    })?;
+    let entity = cli.entity.as_deref().unwrap_or_default();
+
+    let api_client = ApiClient::from_config(config)?;
+    let mut auth_client = api_client.with_api_key(api_key.clone());
+    if let Some(path) = ServerCaps::default_path() {
+        auth_client = auth_client.with_server_caps(ServerCaps::new(path));
     }
 
+    let experts = auth_client.get_file_experts(entity).await?;
+    match cli.output.as_deref() {
+        Some("json") | Some("raw-json") => print!("{}", serde_json::to_string(&experts)?),
+        _ => println!("{}", chronova_cli::api::format_file_experts(&experts)),
+    }
     Ok(())
 }
 
+/// Render a heartbeat time in UTC for the --fix-timestamps preview
+fn format_heartbeat_time(time: f64) -> String {
+    chrono::DateTime::from_timestamp(time.floor() as i64, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| time.to_string())
+}
+
 /// Handle config read/write operations
 async fn handle_config_operations(cli: &Cli) -> Result<(), anyhow::Error> {
     let config_path = chronova_cli::config::Config::resolve_config_path(&cli.config)?;
@@ -524,11 +1135,11 @@ async fn handle_config_operations(cli: &Cli) -> Result<(), anyhow::Error> {
             })?;
         }
 
-        // Set the value in the specified section
+        // Set the value in the specified section, which is created if absent
         ini.set(section, key, Some(value.clone()));
 
-        // Save the config back to file
-        ini.write(&config_path).map_err(|e| {
+        // Save the config back to file, creating its directory for a first write
+        Config::write_file(&config_path, &ini.writes()).map_err(|e| {
             anyhow::anyhow!("Failed to write config to {}: {}", config_path.display(), e)
         })?;
 
@@ -543,7 +1154,6 @@ async fn process_extra_heartbeats(
     heartbeat_manager: HeartbeatManager,
 ) -> Result<(), anyhow::Error> {
     use std::io::{self, Read};
-    use uuid::Uuid;
 
     // Read all input from STDIN
     let mut input = String::new();
@@ -579,102 +1189,11 @@ async fn process_extra_heartbeats(
         }
     }
 
-    // Parse the JSON array of heartbeats, but handle missing id field
-    // External heartbeats (from WakaTime extension) may not include an id field
-    let heartbeats_result: Result<Vec<chronova_cli::heartbeat::Heartbeat>, _> =
-        serde_json::from_str(&input);
-
-    let heartbeats = match heartbeats_result {
-        Ok(heartbeats) => heartbeats,
-        Err(e) => {
-            // If parsing fails due to missing id field, try parsing as a different structure
-            // that doesn't require id, then add the id field manually
-            tracing::warn!("Failed to parse heartbeats with strict validation: {}", e);
-            tracing::info!("Attempting to parse with relaxed validation for external heartbeats");
-
-            // Define a relaxed heartbeat structure that doesn't require id or type
-            // This matches the WakaTime ExtraHeartbeat format where most fields are optional
-            #[derive(Debug, serde::Deserialize)]
-            struct RelaxedHeartbeat {
-                pub entity: String,
-                #[serde(rename = "type", default = "default_entity_type")]
-                pub entity_type: String,
-                pub time: f64,
-                pub project: Option<String>,
-                pub branch: Option<String>,
-                pub language: Option<String>,
-                #[serde(default)]
-                pub is_write: bool,
-                pub lines: Option<i32>,
-                pub lineno: Option<i32>,
-                pub cursorpos: Option<i32>,
-                pub user_agent: Option<String>,
-                pub category: Option<String>,
-                pub machine: Option<String>,
-                #[serde(default)]
-                pub dependencies: Vec<String>,
-            }
-
-            fn default_entity_type() -> String {
-                "file".to_string()
-            }
-
-            // Parse as relaxed heartbeats
-            let relaxed_heartbeats: Vec<RelaxedHeartbeat> =
-                serde_json::from_str(&input).map_err(|e| {
-                    tracing::error!("Failed to parse even with relaxed validation: {}", e);
-                    anyhow::anyhow!("Failed to parse extra heartbeats: {}", e)
-                })?;
-
-            // Convert to proper heartbeats by adding id field
-            let mut heartbeats = Vec::new();
-            for relaxed in relaxed_heartbeats {
-                let heartbeat = chronova_cli::heartbeat::Heartbeat {
-                    id: Uuid::new_v4().to_string(), // Generate UUID for missing id
-                    entity: relaxed.entity,
-                    entity_type: relaxed.entity_type,
-                    time: relaxed.time,
-                    project: relaxed.project,
-                    branch: relaxed.branch,
-                    language: relaxed.language,
-                    is_write: relaxed.is_write,
-                    lines: relaxed.lines,
-                    lineno: relaxed.lineno,
-                    cursorpos: relaxed.cursorpos,
-                    user_agent: Some(chronova_cli::user_agent::generate_user_agent(
-                        relaxed.user_agent.as_deref(),
-                    )),
-                    category: relaxed.category,
-                    machine: relaxed.machine,
-                    editor: None,
-                    operating_system: None,
-                    commit_hash: None,
-                    commit_author: None,
-                    commit_message: None,
-                    repository_url: None,
-                    dependencies: relaxed.dependencies,
-                };
-                heartbeats.push(heartbeat);
-            }
-
-            tracing::info!(
-                "Successfully parsed {} external heartbeats with generated IDs",
-                heartbeats.len()
-            );
-            heartbeats
-        }
-    };
-
-    tracing::info!(
-        "Processing {} extra heartbeats from STDIN",
-        heartbeats.len()
-    );
-
-    for heartbeat in &heartbeats {
-        heartbeat_manager.add_heartbeat_to_queue(heartbeat.clone())?;
-    }
+    // External heartbeats (from WakaTime extensions) may omit id and type;
+    // those are parsed with relaxed validation and completed per entity type
+    let queued = heartbeat_manager.queue_extra_heartbeats(&input).await?;
 
-    tracing::info!("Successfully queued {} extra heartbeats", heartbeats.len());
+    tracing::info!("Successfully queued {} extra heartbeats", queued);
 
     Ok(())
 }
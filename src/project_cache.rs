@@ -0,0 +1,123 @@
+//! Persistent cache of project detection results.
+//!
+//! Editors start a new CLI process for every heartbeat, so each one would walk
+//! the directory tree again. The cache maps an entity's directory to the
+//! project found for it, together with the mtime of the marker file that
+//! identified the project; an entry is only trusted while that mtime is
+//! unchanged.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::collector::ProjectInfo;
+
+/// Once the cache holds more directories than this it starts over
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    name: String,
+    root: PathBuf,
+    marker: PathBuf,
+    /// Marker mtime in nanoseconds since the Unix epoch
+    marker_mtime: u64,
+}
+
+/// Project cache backed by a JSON file, `~/.chronova/project_cache.json` by default
+#[derive(Debug, Clone)]
+pub struct ProjectCache {
+    path: PathBuf,
+}
+
+impl ProjectCache {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Path of the default cache file, `~/.chronova/project_cache.json`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".chronova").join("project_cache.json"))
+    }
+
+    /// Cached project for entities in `dir`, if its marker is unchanged
+    pub fn get(&self, dir: &Path) -> Option<ProjectInfo> {
+        let entries = self.load();
+        let entry = entries.get(&cache_key(dir))?;
+
+        if marker_mtime(&entry.marker)? != entry.marker_mtime {
+            tracing::debug!("Project cache entry for {} is stale", dir.display());
+            return None;
+        }
+
+        Some(ProjectInfo {
+            name: entry.name.clone(),
+            root: entry.root.clone(),
+        })
+    }
+
+    /// Remember `project` for entities in `dir`, identified by `marker`.
+    /// Failures only cost the next run a full detection, so they are logged.
+    pub fn insert(&self, dir: &Path, project: &ProjectInfo, marker: &Path) {
+        let Some(mtime) = marker_mtime(marker) else {
+            return;
+        };
+
+        let mut entries = self.load();
+        if entries.len() >= MAX_ENTRIES {
+            entries.clear();
+        }
+        entries.insert(
+            cache_key(dir),
+            CacheEntry {
+                name: project.name.clone(),
+                root: project.root.clone(),
+                marker: marker.to_path_buf(),
+                marker_mtime: mtime,
+            },
+        );
+
+        if let Err(e) = self.save(&entries) {
+            tracing::debug!(
+                "Failed to write project cache {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    fn load(&self) -> HashMap<String, CacheEntry> {
+        std::fs::read(&self.path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write through a temp file and rename, so concurrent invocations never
+    /// read a half-written cache
+    fn save(&self, entries: &HashMap<String, CacheEntry>) -> std::io::Result<()> {
+        let dir = self
+            .path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+
+        let mut file = tempfile::NamedTempFile::new_in(dir)?;
+        file.write_all(&serde_json::to_vec(entries)?)?;
+        file.persist(&self.path).map_err(|e| e.error)?;
+        Ok(())
+    }
+}
+
+fn cache_key(dir: &Path) -> String {
+    dir.to_string_lossy().into_owned()
+}
+
+fn marker_mtime(marker: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(marker).ok()?.modified().ok()?;
+    let nanos = modified.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+    u64::try_from(nanos).ok()
+}
@@ -0,0 +1,102 @@
+//! Clean-up of detected project names.
+//!
+//! Names taken from folders and remotes sometimes carry noise such as
+//! `my-project.git` or a worktree suffix. Detected names are trimmed, lose a
+//! trailing `.git`, and then go through the `project_name_rewrites` setting,
+//! one `regex => replacement` rule per line, applied in order:
+//!
+//! ```ini
+//! [settings]
+//! project_name_rewrites =
+//!     -(main|master)$ =>
+//!     ^acme-(.*)$ => $1
+//! ```
+//!
+//! A name given with `--project` is used as is.
+
+use regex::Regex;
+
+/// A single `regex => replacement` rule
+#[derive(Debug, Clone)]
+pub struct ProjectNameRewrite {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl ProjectNameRewrite {
+    /// Parse a `regex => replacement` line. The replacement may be empty and
+    /// can refer to capture groups as `$1` or `${name}`.
+    pub fn parse(line: &str) -> Option<Self> {
+        let (pattern, replacement) = line.split_once("=>")?;
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            pattern: Regex::new(pattern).ok()?,
+            replacement: replacement.trim().to_string(),
+        })
+    }
+}
+
+/// Parse the `project_name_rewrites` setting; bad lines are skipped with a warning
+pub fn parse_rewrites(value: &str) -> Vec<ProjectNameRewrite> {
+    value
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| {
+            let rewrite = ProjectNameRewrite::parse(line);
+            if rewrite.is_none() {
+                tracing::warn!("Ignoring invalid project name rewrite: {}", line);
+            }
+            rewrite
+        })
+        .collect()
+}
+
+/// Trim `name`, strip a trailing `.git` and apply `rewrites` in order. A rule
+/// that would leave nothing is ignored.
+pub fn sanitize(name: &str, rewrites: &[ProjectNameRewrite]) -> String {
+    let trimmed = name.trim();
+    let mut name = trimmed
+        .strip_suffix(".git")
+        .filter(|stripped| !stripped.is_empty())
+        .unwrap_or(trimmed)
+        .to_string();
+
+    for rewrite in rewrites {
+        let rewritten = rewrite
+            .pattern
+            .replace_all(&name, rewrite.replacement.as_str());
+        let rewritten = rewritten.trim();
+        if !rewritten.is_empty() {
+            name = rewritten.to_string();
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_whitespace_and_git_suffix() {
+        assert_eq!(sanitize("  my-project.git \n", &[]), "my-project");
+        assert_eq!(sanitize("my-project", &[]), "my-project");
+        assert_eq!(sanitize(".git", &[]), ".git");
+    }
+
+    #[test]
+    fn test_custom_rewrite_rules() {
+        let rewrites = parse_rewrites("-(main|master)$ =>\n^acme-(.*)$ => $1\n[ => broken\n");
+        assert_eq!(rewrites.len(), 2);
+
+        assert_eq!(sanitize("chronova-main", &rewrites), "chronova");
+        assert_eq!(sanitize("acme-billing-master.git", &rewrites), "billing");
+        // A rule leaving nothing behind is skipped
+        assert_eq!(sanitize("-main", &rewrites), "-main");
+    }
+}
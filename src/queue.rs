@@ -1,10 +1,15 @@
-use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use thiserror::Error;
 
-use crate::heartbeat::Heartbeat;
-use crate::sync::{SyncStatus, SyncStatusSummary};
+use crate::heartbeat::{Heartbeat, LogicalKey};
+use crate::sync::{SyncMetadata, SyncStatus, SyncStatusSummary};
 
 #[derive(Error, Debug)]
 pub enum QueueError {
@@ -26,6 +31,69 @@ pub enum QueueError {
     StorageLimitExceeded,
     #[error("Database corruption detected: {0}")]
     DatabaseCorruption(String),
+    #[error("Queue directory {0} is not writable: {1}")]
+    DirectoryNotWritable(String, String),
+    #[error("No queue migration to schema version {0}")]
+    UnknownMigration(i32),
+}
+
+/// A queue handle that several `HeartbeatManager`s can share, so one
+/// long-running process keeps a single SQLite connection open.
+pub type SharedQueue = Arc<Mutex<Queue>>;
+
+/// Read-only diagnostic report for a queue database, printed by `--queue-health`
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueHealth {
+    pub path: PathBuf,
+    /// Rows returned by `PRAGMA integrity_check`; `["ok"]` when healthy
+    pub integrity: Vec<String>,
+    /// Number of rows returned by `PRAGMA foreign_key_check`
+    pub foreign_key_violations: usize,
+    /// Row counts keyed by the raw `sync_status` column value
+    pub counts_by_status: Vec<(String, usize)>,
+    /// Size of the database file in bytes
+    pub size_bytes: u64,
+    /// Latest applied migration, 0 when none is recorded
+    pub schema_version: i32,
+}
+
+impl QueueHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.integrity.len() == 1
+            && self.integrity[0].eq_ignore_ascii_case("ok")
+            && self.foreign_key_violations == 0
+    }
+
+    /// Render the report, with remediation steps when the check failed
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!("Queue database: {}", self.path.display()),
+            format!("Size: {} bytes", self.size_bytes),
+            format!("Schema version: {}", self.schema_version),
+            format!("Integrity check: {}", self.integrity.join("; ")),
+            format!("Foreign key violations: {}", self.foreign_key_violations),
+            "Heartbeats by status:".to_string(),
+        ];
+        if self.counts_by_status.is_empty() {
+            lines.push("  (none)".to_string());
+        }
+        for (status, count) in &self.counts_by_status {
+            lines.push(format!("  {}: {}", status, count));
+        }
+
+        if self.is_healthy() {
+            lines.push("Queue health: OK".to_string());
+        } else {
+            lines.push("Queue health: FAILED".to_string());
+            lines.push(format!(
+                "Remediation: restore {} from {} if it exists, or move the database aside \
+                 so a fresh queue is created on the next run (unsynced heartbeats in it are lost).",
+                self.path.display(),
+                self.path.with_extension("db.backup").display()
+            ));
+        }
+        lines.join("\n")
+    }
 }
 
 /// Represents a queue entry with sync metadata
@@ -35,8 +103,8 @@ pub struct QueueEntry {
     pub heartbeat: Heartbeat,
     /// Current sync status
     pub sync_status: SyncStatus,
-    /// Sync metadata (error messages, retry info, etc.)
-    pub sync_metadata: Option<String>,
+    /// Retry state: last error, attempt and next retry time
+    pub sync_metadata: Option<SyncMetadata>,
     /// Number of sync attempts
     pub retry_count: u32,
     /// Timestamp when this entry was created
@@ -58,11 +126,100 @@ impl QueueEntry {
     }
 }
 
+/// A heartbeat currently marked as syncing, with the age of its last attempt
+#[derive(Debug, Clone)]
+pub struct SyncingEntry {
+    pub id: String,
+    pub entity: String,
+    /// Time since the sync attempt started, if recorded
+    pub last_attempt_age: Option<Duration>,
+}
+
+impl SyncingEntry {
+    /// Whether this entry has been syncing longer than `timeout`, meaning the
+    /// process that claimed it most likely crashed. Entries without a recorded
+    /// attempt are treated as stale.
+    pub fn is_stale(&self, timeout: Duration) -> bool {
+        self.last_attempt_age.is_none_or(|age| age > timeout)
+    }
+}
+
+/// Share of `max_queue_size`, in percent, from which the queue is trimmed
+pub const OUTAGE_TRIM_PERCENT: usize = 80;
+
+/// Heartbeats of one file further apart than this belong to separate sessions
+/// when the queue is trimmed
+const OUTAGE_SESSION_GAP_SECONDS: f64 = 15.0 * 60.0;
+
+/// Schema version `init_database` migrates the queue database to
+pub const SCHEMA_VERSION: i32 = 3;
+
+/// zstd level for compressed rows; heartbeats are small, so higher levels
+/// gain little
+const COMPRESSION_LEVEL: i32 = 3;
+
+type DataError = Box<dyn std::error::Error + Send + Sync>;
+
+/// JSON of a `data` column value, decompressing zstd blobs
+fn data_json(value: ValueRef<'_>) -> Result<String, DataError> {
+    match value {
+        ValueRef::Text(text) => Ok(std::str::from_utf8(text)?.to_string()),
+        ValueRef::Blob(blob) => Ok(String::from_utf8(zstd::decode_all(blob)?)?),
+        other => Err(format!("unexpected {} value in data column", other.data_type()).into()),
+    }
+}
+
+/// Heartbeat stored in column `idx` of `row`, compressed or not
+fn read_heartbeat(row: &rusqlite::Row<'_>, idx: usize) -> rusqlite::Result<Heartbeat> {
+    let value = row.get_ref(idx)?;
+    data_json(value)
+        .and_then(|json| Ok(serde_json::from_str::<Heartbeat>(&json)?))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, value.data_type(), e))
+}
+
+/// Entry from a row of `data, sync_status, sync_metadata, retry_count,
+/// created_at, last_attempt`
+fn read_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<QueueEntry> {
+    let heartbeat = read_heartbeat(row, 0)?;
+    let status: String = row.get(1)?;
+    let created_at: Option<String> = row.get(4)?;
+    let last_attempt: Option<String> = row.get(5)?;
+    Ok(QueueEntry {
+        heartbeat,
+        sync_status: SyncStatus::from(status.as_str()),
+        sync_metadata: row
+            .get::<_, Option<String>>(2)?
+            .map(|raw| SyncMetadata::parse(&raw)),
+        retry_count: row.get(3)?,
+        created_at: created_at
+            .as_deref()
+            .and_then(parse_sqlite_datetime)
+            .unwrap_or_else(chrono::Utc::now),
+        last_attempt: last_attempt.as_deref().and_then(parse_sqlite_datetime),
+    })
+}
+
+/// Parse a `CURRENT_TIMESTAMP` value, which SQLite stores as UTC text
+fn parse_sqlite_datetime(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|naive| naive.and_utc())
+}
+
 /// Trait defining the queue operations for offline heartbeat synchronization
 pub trait QueueOps {
     /// Add a heartbeat to the queue
     fn add(&self, heartbeat: Heartbeat) -> Result<(), QueueError>;
 
+    /// Add a write heartbeat, coalescing it with pending writes to the same
+    /// entity less than `window_seconds` apart so a save storm leaves a single
+    /// row. The most recent write's time wins. Returns the rows coalesced away.
+    fn add_coalescing_writes(
+        &self,
+        heartbeat: Heartbeat,
+        window_seconds: u64,
+    ) -> Result<usize, QueueError>;
+
     /// Add multiple heartbeats in a single transaction for bulk insertion performance
     fn add_batch(&self, heartbeats: Vec<Heartbeat>) -> Result<(), QueueError>;
 
@@ -73,6 +230,29 @@ pub trait QueueOps {
         status_filter: Option<SyncStatus>,
     ) -> Result<Vec<Heartbeat>, QueueError>;
 
+    /// Get heartbeats in any of `statuses` (pending when empty), oldest first
+    fn get_pending_with_statuses(
+        &self,
+        limit: Option<usize>,
+        statuses: &[SyncStatus],
+    ) -> Result<Vec<Heartbeat>, QueueError>;
+
+    /// Like `get_pending_with_statuses`, restricted to heartbeats queued within
+    /// `max_age`; older ones are left untouched. `None` applies no cutoff.
+    fn get_pending_within(
+        &self,
+        limit: Option<usize>,
+        statuses: &[SyncStatus],
+        max_age: Option<Duration>,
+    ) -> Result<Vec<Heartbeat>, QueueError>;
+
+    /// Get every heartbeat in the queue regardless of sync status, oldest first
+    fn get_all(&self) -> Result<Vec<Heartbeat>, QueueError>;
+
+    /// Get the heartbeats with the given IDs regardless of sync status, oldest
+    /// first. IDs not in the queue are skipped.
+    fn get_by_ids(&self, ids: &[String]) -> Result<Vec<Heartbeat>, QueueError>;
+
     /// Remove a heartbeat from the queue by ID
     fn remove(&self, id: &str) -> Result<(), QueueError>;
 
@@ -93,13 +273,25 @@ pub trait QueueOps {
     /// Clean up old entries based on retention policy
     fn cleanup_old_entries(&self, max_age_days: i32) -> Result<usize, QueueError>;
 
+    /// Remove heartbeats past their `expires_at`, whatever their sync status.
+    /// Unlike the retention cleanup this also drops unsynced heartbeats.
+    fn remove_expired(&self) -> Result<usize, QueueError>;
+
     /// Enforce maximum queue size by removing oldest entries
     fn enforce_max_count(&self, max_count: usize) -> Result<usize, QueueError>;
 
+    /// Keep the queue under `max_count` while heartbeats can't be sent. From
+    /// [`OUTAGE_TRIM_PERCENT`] of capacity, unsynced non-write heartbeats are
+    /// coalesced per file to the first and last of each session; if that is
+    /// not enough, synced rows go first, then the oldest reads, then writes.
+    /// Returns the number of heartbeats removed.
+    fn trim_for_outage(&self, max_count: usize) -> Result<usize, QueueError>;
+
     /// Vacuum database to optimize storage
     fn vacuum(&self) -> Result<(), QueueError>;
 
-    /// Deduplicate heartbeats based on entity and time window
+    /// Deduplicate heartbeats sharing a [`Heartbeat::logical_key`] with
+    /// `time_window_seconds` wide time buckets, keeping the latest of each
     fn deduplicate(&self, time_window_seconds: i64) -> Result<usize, QueueError>;
 
     /// Increment retry count for a heartbeat
@@ -110,21 +302,81 @@ pub trait QueueOps {
 
     /// Get total count of heartbeats in queue
     fn count(&self) -> Result<usize, QueueError>;
+
+    /// List heartbeats in `Syncing` status, oldest attempt first
+    fn get_syncing(&self) -> Result<Vec<SyncingEntry>, QueueError>;
+
+    /// List up to `limit` entries (all when `None`) in any of `statuses` with
+    /// their sync state, oldest first
+    fn get_entries(
+        &self,
+        limit: Option<usize>,
+        statuses: &[SyncStatus],
+    ) -> Result<Vec<QueueEntry>, QueueError>;
+
+    /// Move heartbeats stuck in `Syncing` for longer than `timeout` back to pending
+    fn requeue_stale_syncing(&self, timeout: Duration) -> Result<usize, QueueError>;
+
+    /// Mark `ids` as `Syncing` in one transaction before they are sent
+    fn claim_for_sync(&self, ids: &[String]) -> Result<(), QueueError>;
+
+    /// Select up to `limit` pending heartbeats queued within `max_age` and mark
+    /// them `Syncing` in the same transaction, so concurrent syncs never send
+    /// the same heartbeat twice
+    fn claim_pending(
+        &self,
+        limit: usize,
+        max_age: Option<Duration>,
+    ) -> Result<Vec<Heartbeat>, QueueError>;
+
+    /// Record `time` as the latest heartbeat seen and return the previous
+    /// latest, if any. An earlier `time` leaves the record unchanged.
+    fn record_activity(&self, time: f64) -> Result<Option<f64>, QueueError>;
+
+    /// Add `offset_seconds` to the time of the heartbeats with the given IDs in
+    /// one transaction. Returns the number of heartbeats changed.
+    fn shift_times(&self, ids: &[String], offset_seconds: f64) -> Result<usize, QueueError>;
+
+    /// Set the project of each `(id, project)` pair in one transaction.
+    /// Returns the number of heartbeats changed.
+    fn set_projects(&self, projects: &[(String, String)]) -> Result<usize, QueueError>;
+
+    /// Move failed heartbeats with fewer than `max_attempts` attempts back to
+    /// pending and mark the rest permanently failed. Failed rows are scanned
+    /// `page_size` at a time in rowid order. Returns how many became pending.
+    fn prepare_failed_for_retry(
+        &self,
+        max_attempts: u32,
+        page_size: usize,
+    ) -> Result<usize, QueueError>;
+
+    /// Run `f` as one transaction: every queue operation it performs through
+    /// `tx` is committed together, or rolled back if it returns an error.
+    /// Calls nested inside another transaction use a savepoint, so they roll
+    /// back only their own work.
+    fn with_transaction<T, F>(&self, f: F) -> Result<T, QueueError>
+    where
+        F: FnOnce(&Self) -> Result<T, QueueError>;
 }
 
 pub struct Queue {
     conn: Connection,
+    /// Heartbeats added through this handle expire this many days after insert
+    ttl_days: Option<u32>,
+    /// Store heartbeats added through this handle zstd-compressed
+    compress: bool,
 }
 
 impl QueueOps for Queue {
     fn add(&self, heartbeat: Heartbeat) -> Result<(), QueueError> {
-        let data = serde_json::to_string(&heartbeat)?;
+        let data = self.encode(&heartbeat)?;
 
         // Ensure sync_status is explicitly set on insert so rows are queryable
         // regardless of whether the column default is present in the schema.
         self.conn.execute(
-            "INSERT OR REPLACE INTO heartbeats (id, data, sync_status) VALUES (?1, ?2, 'pending')",
-            params![heartbeat.id, data],
+            "INSERT OR REPLACE INTO heartbeats (id, data, sync_status, expires_at)
+             VALUES (?1, ?2, 'pending', datetime('now', ?3))",
+            params![heartbeat.id, data, self.ttl_modifier()],
         )?;
 
         // Log queue operation with metrics
@@ -141,22 +393,67 @@ impl QueueOps for Queue {
         Ok(())
     }
 
+    fn add_coalescing_writes(
+        &self,
+        heartbeat: Heartbeat,
+        window_seconds: u64,
+    ) -> Result<usize, QueueError> {
+        self.with_transaction(|tx| {
+            let nearby: Vec<(String, f64)> = {
+                let mut stmt = tx.conn.prepare(
+                    "SELECT id, json_extract(heartbeat_data(data), '$.time') FROM heartbeats
+                     WHERE sync_status = 'pending'
+                       AND json_extract(heartbeat_data(data), '$.is_write') = 1
+                       AND json_extract(heartbeat_data(data), '$.entity') = ?1
+                       AND ABS(json_extract(heartbeat_data(data), '$.time') - ?2) < ?3
+                       AND id != ?4",
+                )?;
+                let rows = stmt.query_map(
+                    params![
+                        heartbeat.entity,
+                        heartbeat.time,
+                        window_seconds as f64,
+                        heartbeat.id
+                    ],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?;
+                rows.collect::<Result<_, _>>()?
+            };
+
+            // A later write is already queued; this one adds nothing
+            if nearby.iter().any(|(_, time)| *time > heartbeat.time) {
+                tracing::debug!(
+                    entity = %heartbeat.entity,
+                    "Dropping write superseded by a later queued write"
+                );
+                return Ok(1);
+            }
+
+            for (id, _) in &nearby {
+                tx.remove(id)?;
+            }
+            tx.add(heartbeat)?;
+            Ok(nearby.len())
+        })
+    }
+
     fn add_batch(&self, heartbeats: Vec<Heartbeat>) -> Result<(), QueueError> {
         if heartbeats.is_empty() {
             return Ok(());
         }
 
-        let tx = self.conn.unchecked_transaction()?;
-        {
-            let mut stmt = tx.prepare(
-                "INSERT OR REPLACE INTO heartbeats (id, data, sync_status) VALUES (?1, ?2, 'pending')",
+        self.with_transaction(|tx| {
+            let mut stmt = tx.conn.prepare(
+                "INSERT OR REPLACE INTO heartbeats (id, data, sync_status, expires_at)
+                 VALUES (?1, ?2, 'pending', datetime('now', ?3))",
             )?;
+            let ttl = tx.ttl_modifier();
             for heartbeat in &heartbeats {
-                let data = serde_json::to_string(heartbeat)?;
-                stmt.execute(params![heartbeat.id, data])?;
+                let data = tx.encode(heartbeat)?;
+                stmt.execute(params![heartbeat.id, data, ttl])?;
             }
-        }
-        tx.commit()?;
+            Ok(())
+        })?;
 
         tracing::info!(
             operation = "add_batch",
@@ -172,23 +469,101 @@ impl QueueOps for Queue {
         limit: Option<usize>,
         status_filter: Option<SyncStatus>,
     ) -> Result<Vec<Heartbeat>, QueueError> {
-        let limit = limit.unwrap_or(100);
         let status_filter = status_filter.unwrap_or(SyncStatus::Pending);
-        let status_str: String = status_filter.into();
+        self.get_pending_with_statuses(limit, &[status_filter])
+    }
 
-        let mut stmt = self.conn.prepare(
-            "SELECT data FROM heartbeats WHERE sync_status = ?1 ORDER BY created_at ASC LIMIT ?2",
-        )?;
+    fn get_pending_with_statuses(
+        &self,
+        limit: Option<usize>,
+        statuses: &[SyncStatus],
+    ) -> Result<Vec<Heartbeat>, QueueError> {
+        self.get_pending_within(limit, statuses, None)
+    }
 
-        let heartbeats_iter = stmt.query_map(params![status_str, limit], |row| {
-            let data: String = row.get(0)?;
-            serde_json::from_str::<Heartbeat>(&data).map_err(|e| {
-                rusqlite::Error::FromSqlConversionFailure(
-                    0,
-                    rusqlite::types::Type::Text,
-                    Box::new(e),
-                )
-            })
+    fn get_pending_within(
+        &self,
+        limit: Option<usize>,
+        statuses: &[SyncStatus],
+        max_age: Option<Duration>,
+    ) -> Result<Vec<Heartbeat>, QueueError> {
+        let limit = limit.unwrap_or(100);
+        let mut status_strs: Vec<String> = statuses.iter().cloned().map(String::from).collect();
+        if status_strs.is_empty() {
+            status_strs.push(SyncStatus::Pending.into());
+        }
+
+        let placeholders = (1..=status_strs.len())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut values: Vec<rusqlite::types::Value> = status_strs
+            .into_iter()
+            .map(rusqlite::types::Value::Text)
+            .collect();
+
+        let age_filter = match max_age {
+            Some(max_age) => {
+                values.push(rusqlite::types::Value::Text(format!(
+                    "-{} seconds",
+                    max_age.as_secs()
+                )));
+                format!(" AND created_at >= datetime('now', ?{})", values.len())
+            }
+            None => String::new(),
+        };
+        values.push(rusqlite::types::Value::Integer(limit as i64));
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT data FROM heartbeats WHERE sync_status IN ({}){} ORDER BY created_at ASC LIMIT ?{}",
+            placeholders,
+            age_filter,
+            values.len()
+        ))?;
+
+        let heartbeats_iter = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+            read_heartbeat(row, 0)
+        })?;
+
+        let mut heartbeats = Vec::new();
+        for heartbeat in heartbeats_iter {
+            heartbeats.push(heartbeat?);
+        }
+
+        Ok(heartbeats)
+    }
+
+    fn get_all(&self) -> Result<Vec<Heartbeat>, QueueError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT data FROM heartbeats ORDER BY created_at ASC")?;
+
+        let heartbeats_iter = stmt.query_map([], |row| read_heartbeat(row, 0))?;
+
+        let mut heartbeats = Vec::new();
+        for heartbeat in heartbeats_iter {
+            heartbeats.push(heartbeat?);
+        }
+
+        Ok(heartbeats)
+    }
+
+    fn get_by_ids(&self, ids: &[String]) -> Result<Vec<Heartbeat>, QueueError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = (1..=ids.len())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT data FROM heartbeats WHERE id IN ({}) ORDER BY created_at ASC",
+            placeholders
+        ))?;
+
+        let heartbeats_iter = stmt.query_map(rusqlite::params_from_iter(ids), |row| {
+            read_heartbeat(row, 0)
         })?;
 
         let mut heartbeats = Vec::new();
@@ -284,6 +659,13 @@ impl QueueOps for Queue {
         }
 
         summary.total = self.count()?;
+        (summary.stored_bytes, summary.logical_bytes) = self.conn.query_row(
+            "SELECT COALESCE(SUM(length(CAST(data AS BLOB))), 0),
+                    COALESCE(SUM(length(CAST(heartbeat_data(data) AS BLOB))), 0)
+             FROM heartbeats",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
 
         // Get last sync attempt timestamp - handle NULL case properly
         let last_sync: Option<String> = self
@@ -311,6 +693,23 @@ impl QueueOps for Queue {
         Ok(summary)
     }
 
+    fn remove_expired(&self) -> Result<usize, QueueError> {
+        let rows_affected = self.conn.execute(
+            "DELETE FROM heartbeats WHERE expires_at IS NOT NULL AND expires_at <= datetime('now')",
+            [],
+        )?;
+
+        if rows_affected > 0 {
+            tracing::info!(
+                operation = "remove_expired",
+                entries_removed = rows_affected,
+                "Expired heartbeats removed from queue"
+            );
+        }
+
+        Ok(rows_affected)
+    }
+
     fn cleanup_old_entries(&self, max_age_days: i32) -> Result<usize, QueueError> {
         // Handle special case: max_age_days = 0 means remove all entries
         if max_age_days == 0 {
@@ -389,6 +788,85 @@ impl QueueOps for Queue {
         Ok(rows_affected)
     }
 
+    fn trim_for_outage(&self, max_count: usize) -> Result<usize, QueueError> {
+        let current_count = self.count()?;
+        if current_count < (max_count * OUTAGE_TRIM_PERCENT / 100).max(1) {
+            return Ok(0);
+        }
+
+        let (coalesced, evicted) = self.with_transaction(|tx| {
+            // Split each file's unsynced reads into sessions at gaps longer than
+            // OUTAGE_SESSION_GAP_SECONDS, then drop all but a session's first and last
+            let coalesced = tx.conn.execute(
+                "WITH reads AS (
+                    SELECT id,
+                           json_extract(heartbeat_data(data), '$.entity') AS entity,
+                           json_extract(heartbeat_data(data), '$.type') AS entity_type,
+                           json_extract(heartbeat_data(data), '$.project') AS project,
+                           json_extract(heartbeat_data(data), '$.time') AS time
+                    FROM heartbeats
+                    WHERE sync_status IN ('pending', 'failed')
+                      AND COALESCE(json_extract(heartbeat_data(data), '$.is_write'), 0) = 0
+                ),
+                gaps AS (
+                    SELECT *,
+                           COALESCE(time - LAG(time) OVER (
+                               PARTITION BY entity, entity_type, project ORDER BY time
+                           ) > ?1, 0) AS starts_session
+                    FROM reads
+                ),
+                sessions AS (
+                    SELECT *,
+                           SUM(starts_session) OVER (
+                               PARTITION BY entity, entity_type, project ORDER BY time
+                               ROWS UNBOUNDED PRECEDING
+                           ) AS session
+                    FROM gaps
+                ),
+                ranked AS (
+                    SELECT id,
+                           ROW_NUMBER() OVER (
+                               PARTITION BY entity, entity_type, project, session ORDER BY time
+                           ) AS position,
+                           COUNT(*) OVER (
+                               PARTITION BY entity, entity_type, project, session
+                           ) AS size
+                    FROM sessions
+                )
+                DELETE FROM heartbeats
+                WHERE id IN (SELECT id FROM ranked WHERE position > 1 AND position < size)",
+                params![OUTAGE_SESSION_GAP_SECONDS],
+            )?;
+
+            let excess = tx.count()?.saturating_sub(max_count);
+            let evicted = tx.conn.execute(
+                "DELETE FROM heartbeats WHERE id IN (
+                    SELECT id FROM heartbeats
+                    WHERE sync_status != 'syncing'
+                    ORDER BY sync_status != 'synced',
+                             COALESCE(json_extract(heartbeat_data(data), '$.is_write'), 0),
+                             created_at ASC
+                    LIMIT ?1
+                )",
+                params![excess as i64],
+            )?;
+            Ok((coalesced, evicted))
+        })?;
+
+        if coalesced + evicted > 0 {
+            tracing::warn!(
+                operation = "trim_for_outage",
+                max_count = max_count,
+                previous_count = current_count,
+                coalesced = coalesced,
+                evicted = evicted,
+                "Trimmed queue nearing capacity"
+            );
+        }
+
+        Ok(coalesced + evicted)
+    }
+
     fn vacuum(&self) -> Result<(), QueueError> {
         tracing::info!(operation = "vacuum", "Starting database vacuum operation");
 
@@ -403,21 +881,29 @@ impl QueueOps for Queue {
     }
 
     fn deduplicate(&self, time_window_seconds: i64) -> Result<usize, QueueError> {
-        // Remove duplicate heartbeats within the same time window
-        // Keep the most recent heartbeat for each entity within the time window
-        let rows_affected = self.conn.execute(
-            "DELETE FROM heartbeats
-            WHERE id IN (
-                SELECT h1.id
-                FROM heartbeats h1
-                JOIN heartbeats h2 ON
-                    h1.id != h2.id AND
-                    h1.entity = h2.entity AND
-                    ABS(h1.time - h2.time) < ?1
-                WHERE h1.time < h2.time
-            )",
-            params![time_window_seconds],
-        )?;
+        // Heartbeats sharing a logical key within the same time window are
+        // duplicates; keep the most recent one of each
+        let rows_affected = self.with_transaction(|tx| {
+            let heartbeats = tx.get_all()?;
+            let mut latest: HashMap<LogicalKey<'_>, &Heartbeat> = HashMap::new();
+            let mut duplicates = Vec::new();
+            for heartbeat in &heartbeats {
+                let key = heartbeat.logical_key_with_bucket(time_window_seconds);
+                match latest.get_mut(&key) {
+                    Some(kept) if kept.time >= heartbeat.time => duplicates.push(&heartbeat.id),
+                    Some(kept) => duplicates.push(&std::mem::replace(kept, heartbeat).id),
+                    None => {
+                        latest.insert(key, heartbeat);
+                    }
+                }
+            }
+
+            let mut stmt = tx.conn.prepare("DELETE FROM heartbeats WHERE id = ?1")?;
+            for id in &duplicates {
+                stmt.execute(params![id])?;
+            }
+            Ok(duplicates.len())
+        })?;
 
         // Log deduplication results
         if rows_affected > 0 {
@@ -483,6 +969,288 @@ impl QueueOps for Queue {
 
         Ok(count)
     }
+
+    fn get_syncing(&self) -> Result<Vec<SyncingEntry>, QueueError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, json_extract(heartbeat_data(data), '$.entity'),
+                    CAST(strftime('%s', 'now') - strftime('%s', last_attempt) AS INTEGER)
+             FROM heartbeats WHERE sync_status = 'syncing' ORDER BY last_attempt ASC",
+        )?;
+
+        let entries = stmt
+            .query_map([], |row| {
+                let age: Option<i64> = row.get(2)?;
+                Ok(SyncingEntry {
+                    id: row.get(0)?,
+                    entity: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    last_attempt_age: age.map(|secs| Duration::from_secs(secs.max(0) as u64)),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    fn get_entries(
+        &self,
+        limit: Option<usize>,
+        statuses: &[SyncStatus],
+    ) -> Result<Vec<QueueEntry>, QueueError> {
+        if statuses.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = (1..=statuses.len())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut values: Vec<rusqlite::types::Value> = statuses
+            .iter()
+            .map(|status| rusqlite::types::Value::Text(String::from(*status)))
+            .collect();
+        let mut sql = format!(
+            "SELECT data, sync_status, sync_metadata, retry_count, created_at, last_attempt
+             FROM heartbeats WHERE sync_status IN ({}) ORDER BY created_at ASC",
+            placeholders
+        );
+        if let Some(limit) = limit {
+            values.push(rusqlite::types::Value::Integer(limit as i64));
+            sql.push_str(&format!(" LIMIT ?{}", values.len()));
+        }
+
+        let mut stmt = self.conn.prepare(&sql)?;
+
+        let entries = stmt
+            .query_map(rusqlite::params_from_iter(values), read_entry)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    fn requeue_stale_syncing(&self, timeout: Duration) -> Result<usize, QueueError> {
+        let rows_affected = self.conn.execute(
+            "UPDATE heartbeats SET sync_status = 'pending', sync_metadata = json_object('attempt', retry_count, 'note', 'Requeued stale sync attempt')
+             WHERE sync_status = 'syncing'
+               AND (last_attempt IS NULL
+                    OR strftime('%s', 'now') - strftime('%s', last_attempt) > ?1)",
+            params![timeout.as_secs() as i64],
+        )?;
+
+        if rows_affected > 0 {
+            tracing::warn!(
+                operation = "requeue_stale_syncing",
+                requeued = rows_affected,
+                timeout_seconds = timeout.as_secs(),
+                "Requeued heartbeats stuck in syncing"
+            );
+        }
+
+        Ok(rows_affected)
+    }
+
+    fn claim_for_sync(&self, ids: &[String]) -> Result<(), QueueError> {
+        self.with_transaction(|tx| {
+            let mut stmt = tx.conn.prepare(
+                "UPDATE heartbeats SET sync_status = 'syncing', sync_metadata = json_object('attempt', retry_count, 'note', 'Claimed for sync'), last_attempt = CURRENT_TIMESTAMP WHERE id = ?1",
+            )?;
+            for id in ids {
+                stmt.execute(params![id])?;
+            }
+            Ok(())
+        })?;
+
+        tracing::debug!(
+            operation = "claim_for_sync",
+            claimed = ids.len(),
+            "Heartbeats claimed for sync"
+        );
+
+        Ok(())
+    }
+
+    fn claim_pending(
+        &self,
+        limit: usize,
+        max_age: Option<Duration>,
+    ) -> Result<Vec<Heartbeat>, QueueError> {
+        self.with_transaction(|tx| {
+            let heartbeats = tx.get_pending_within(Some(limit), &[SyncStatus::Pending], max_age)?;
+            let ids: Vec<String> = heartbeats.iter().map(|hb| hb.id.clone()).collect();
+            tx.claim_for_sync(&ids)?;
+            Ok(heartbeats)
+        })
+    }
+
+    fn record_activity(&self, time: f64) -> Result<Option<f64>, QueueError> {
+        self.with_transaction(|tx| {
+            let previous = tx
+                .conn
+                .query_row("SELECT last_time FROM activity WHERE id = 0", [], |row| {
+                    row.get(0)
+                })
+                .optional()?;
+            tx.conn.execute(
+                "INSERT INTO activity (id, last_time) VALUES (0, ?1)
+                 ON CONFLICT(id) DO UPDATE SET last_time = max(last_time, excluded.last_time)",
+                params![time],
+            )?;
+            Ok(previous)
+        })
+    }
+
+    fn shift_times(&self, ids: &[String], offset_seconds: f64) -> Result<usize, QueueError> {
+        let shifted = self.with_transaction(|tx| {
+            let mut stmt = tx.conn.prepare(
+                "UPDATE heartbeats
+                 SET data = json_set(heartbeat_data(data), '$.time',
+                                     json_extract(heartbeat_data(data), '$.time') + ?1)
+                 WHERE id = ?2",
+            )?;
+            let mut shifted = 0usize;
+            for id in ids {
+                shifted += stmt.execute(params![offset_seconds, id])?;
+            }
+            // json_set leaves plain JSON behind
+            if tx.compress {
+                tx.compress_existing()?;
+            }
+            Ok(shifted)
+        })?;
+
+        tracing::info!(
+            operation = "shift_times",
+            offset_seconds = offset_seconds,
+            shifted = shifted,
+            "Heartbeat times shifted"
+        );
+
+        Ok(shifted)
+    }
+
+    fn set_projects(&self, projects: &[(String, String)]) -> Result<usize, QueueError> {
+        let updated = self.with_transaction(|tx| {
+            let mut stmt = tx.conn.prepare(
+                "UPDATE heartbeats
+                 SET data = json_set(heartbeat_data(data), '$.project', ?1)
+                 WHERE id = ?2",
+            )?;
+            let mut updated = 0usize;
+            for (id, project) in projects {
+                updated += stmt.execute(params![project, id])?;
+            }
+            // json_set leaves plain JSON behind
+            if tx.compress {
+                tx.compress_existing()?;
+            }
+            Ok(updated)
+        })?;
+
+        tracing::info!(
+            operation = "set_projects",
+            updated = updated,
+            "Heartbeat projects updated"
+        );
+
+        Ok(updated)
+    }
+
+    fn prepare_failed_for_retry(
+        &self,
+        max_attempts: u32,
+        page_size: usize,
+    ) -> Result<usize, QueueError> {
+        let page_size = page_size.max(1);
+        let mut last_rowid = 0i64;
+        let mut prepared = 0usize;
+
+        loop {
+            // Keyset pagination: rows this pass updates leave the failed set,
+            // so an offset would skip some
+            // Rows whose `next_retry_at` is still ahead wait for a later pass
+            let page: Vec<(i64, String, u32, Option<String>)> = {
+                let mut stmt = self.conn.prepare(
+                    "SELECT rowid, id, retry_count, sync_metadata FROM heartbeats
+                     WHERE sync_status = 'failed' AND rowid > ?1
+                       AND (NOT json_valid(COALESCE(sync_metadata, ''))
+                            OR json_extract(sync_metadata, '$.next_retry_at') IS NULL
+                            OR julianday(json_extract(sync_metadata, '$.next_retry_at'))
+                               <= julianday('now'))
+                     ORDER BY rowid LIMIT ?2",
+                )?;
+                let rows = stmt.query_map(params![last_rowid, page_size as i64], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })?;
+                rows.collect::<Result<_, _>>()?
+            };
+            let Some((rowid, ..)) = page.last() else {
+                break;
+            };
+            last_rowid = *rowid;
+
+            prepared += self.with_transaction(|tx| {
+                let mut eligible = 0usize;
+                for (_, id, retry_count, raw_metadata) in &page {
+                    // Keep the last error so --status can still explain the row
+                    let mut metadata = raw_metadata
+                        .as_deref()
+                        .map(SyncMetadata::parse)
+                        .unwrap_or_default();
+                    metadata.attempt = *retry_count;
+                    metadata.next_retry_at = None;
+                    if *retry_count < max_attempts {
+                        metadata.note = Some("Retry eligible".to_string());
+                        tx.update_sync_status(id, SyncStatus::Pending, Some(metadata.to_json()))?;
+                        eligible += 1;
+                    } else {
+                        metadata.note = Some("Out of retry attempts".to_string());
+                        tx.update_sync_status(
+                            id,
+                            SyncStatus::PermanentFailure,
+                            Some(metadata.to_json()),
+                        )?;
+                    }
+                }
+                Ok(eligible)
+            })?;
+
+            if page.len() < page_size {
+                break;
+            }
+        }
+
+        Ok(prepared)
+    }
+
+    fn with_transaction<T, F>(&self, f: F) -> Result<T, QueueError>
+    where
+        F: FnOnce(&Queue) -> Result<T, QueueError>,
+    {
+        let nested = !self.conn.is_autocommit();
+        if nested {
+            self.conn.execute_batch("SAVEPOINT queue_tx")?;
+        } else {
+            self.conn.execute_batch("BEGIN IMMEDIATE")?;
+        }
+
+        match f(self) {
+            Ok(value) => {
+                self.conn
+                    .execute_batch(if nested { "RELEASE queue_tx" } else { "COMMIT" })?;
+                Ok(value)
+            }
+            Err(e) => {
+                let rollback = if nested {
+                    "ROLLBACK TO queue_tx; RELEASE queue_tx"
+                } else {
+                    "ROLLBACK"
+                };
+                if let Err(rollback_err) = self.conn.execute_batch(rollback) {
+                    tracing::error!("Failed to roll back queue transaction: {}", rollback_err);
+                }
+                Err(e)
+            }
+        }
+    }
 }
 
 impl Queue {
@@ -493,7 +1261,7 @@ impl Queue {
         // Initialize the database
         Self::init_database(&conn)?;
 
-        Ok(Self { conn })
+        Self::from_connection(conn)
     }
 
     /// Create a Queue with a custom database path for testing
@@ -503,7 +1271,121 @@ impl Queue {
         // Initialize the database
         Self::init_database(&conn)?;
 
-        Ok(Self { conn })
+        Self::from_connection(conn)
+    }
+
+    /// Create a Queue that lives only in memory, for when the offline queue is
+    /// disabled and nothing should be written to disk
+    pub fn in_memory() -> Result<Self, QueueError> {
+        let conn = Connection::open_in_memory()?;
+        Self::init_database(&conn)?;
+
+        Self::from_connection(conn)
+    }
+
+    /// Wrap an initialized connection. Queries see compressed rows as JSON
+    /// through the `heartbeat_data(data)` SQL function registered here.
+    fn from_connection(conn: Connection) -> Result<Self, QueueError> {
+        conn.create_scalar_function(
+            "heartbeat_data",
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| match ctx.get_raw(0) {
+                ValueRef::Null => Ok(None),
+                value => data_json(value)
+                    .map(Some)
+                    .map_err(rusqlite::Error::UserFunctionError),
+            },
+        )?;
+
+        Ok(Self {
+            conn,
+            ttl_days: None,
+            compress: false,
+        })
+    }
+
+    /// Checkpoint the WAL and close the queue, reporting the error dropping
+    /// it would ignore. Dropping a queue doesn't checkpoint.
+    pub fn close(self) -> Result<(), QueueError> {
+        self.checkpoint()
+    }
+
+    /// Move everything in the `-wal` file into the main database file and
+    /// truncate the WAL, so tools reading only the database see every row.
+    /// Readers still holding an older snapshot leave the WAL in place.
+    pub(crate) fn checkpoint(&self) -> Result<(), QueueError> {
+        let busy: i64 = self
+            .conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+        if busy != 0 {
+            tracing::debug!("WAL checkpoint could not complete while the queue is in use");
+        }
+        Ok(())
+    }
+
+    /// Give heartbeats added through this handle an `expires_at` of `days`
+    /// after insert. `None` keeps them until synced or cleaned up.
+    pub fn with_ttl_days(mut self, days: Option<u32>) -> Self {
+        self.ttl_days = days;
+        self
+    }
+
+    /// Store heartbeats added through this handle as zstd-compressed blobs.
+    /// Rows are read back the same way whether compressed or not.
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// Compress every row still stored as plain JSON, e.g. after turning on
+    /// `compress_queue`. Returns the number of rows compressed.
+    pub fn compress_existing(&self) -> Result<usize, QueueError> {
+        let compressed = self.with_transaction(|tx| {
+            let rows: Vec<(i64, String)> = {
+                let mut stmt = tx
+                    .conn
+                    .prepare("SELECT rowid, data FROM heartbeats WHERE typeof(data) = 'text'")?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<Result<_, _>>()?
+            };
+
+            let mut stmt = tx
+                .conn
+                .prepare("UPDATE heartbeats SET data = ?1 WHERE rowid = ?2")?;
+            for (rowid, data) in &rows {
+                let blob = zstd::encode_all(data.as_bytes(), COMPRESSION_LEVEL)?;
+                stmt.execute(params![blob, rowid])?;
+            }
+            Ok(rows.len())
+        })?;
+
+        if compressed > 0 {
+            tracing::info!(
+                operation = "compress_existing",
+                compressed = compressed,
+                "Compressed queued heartbeats"
+            );
+        }
+
+        Ok(compressed)
+    }
+
+    /// Serialize `heartbeat` for the `data` column: JSON text, or a zstd blob
+    /// of it with compression on
+    fn encode(&self, heartbeat: &Heartbeat) -> Result<rusqlite::types::Value, QueueError> {
+        let json = serde_json::to_string(heartbeat)?;
+        if self.compress {
+            let blob = zstd::encode_all(json.as_bytes(), COMPRESSION_LEVEL)?;
+            Ok(rusqlite::types::Value::Blob(blob))
+        } else {
+            Ok(rusqlite::types::Value::Text(json))
+        }
+    }
+
+    /// `datetime('now', ?)` modifier for `expires_at`; NULL leaves it unset
+    fn ttl_modifier(&self) -> Option<String> {
+        self.ttl_days.map(|days| format!("+{} days", days))
     }
 
     /// Initialize database schema and indexes
@@ -512,6 +1394,9 @@ impl Queue {
         // This is critical on Windows where default rollback journal mode fsyncs on every INSERT.
         conn.pragma_update(None, "journal_mode", "WAL")?;
         conn.pragma_update(None, "synchronous", "NORMAL")?;
+        // Concurrent syncs claim rows with BEGIN IMMEDIATE; wait for the lock
+        // instead of failing with SQLITE_BUSY
+        conn.busy_timeout(Duration::from_secs(5))?;
 
         // Create tables (idempotent)
         conn.execute(
@@ -532,13 +1417,14 @@ impl Queue {
             [],
         )?;
 
-        // Apply migration v1 inside an immediate transaction.
+        // Migrate inside an immediate transaction.
         // BEGIN IMMEDIATE acquires a write lock, so two connections racing
         // to initialize the same database are serialized: the second waits
-        // for the first to COMMIT before it can even check column existence.
+        // for the first to COMMIT and then finds nothing left to migrate.
         conn.execute_batch("BEGIN IMMEDIATE")?;
 
-        let migration_result = Self::apply_migration_v1(conn);
+        let migration_result =
+            Self::schema_version(conn).and_then(|from| Self::migrate(conn, from, SCHEMA_VERSION));
         match migration_result {
             Ok(()) => {
                 conn.execute_batch("COMMIT")?;
@@ -563,52 +1449,91 @@ impl Queue {
             "CREATE INDEX IF NOT EXISTS idx_heartbeats_retry_count ON heartbeats(retry_count)",
             [],
         )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_heartbeats_expires_at ON heartbeats(expires_at)",
+            [],
+        )?;
 
         Ok(())
     }
 
-    /// Migration v1: add sync_status and sync_metadata columns.
-    /// Must be called inside a `BEGIN IMMEDIATE` transaction so that
-    /// concurrent initializers are serialized.
-    fn apply_migration_v1(conn: &Connection) -> Result<(), QueueError> {
-        let current_version: i32 = conn
-            .query_row(
-                "SELECT version FROM schema_version ORDER BY version DESC LIMIT 1",
-                [],
-                |row| row.get(0),
-            )
-            .optional()?
-            .unwrap_or(0);
+    /// Highest migration recorded in `schema_version`, 0 for a fresh or
+    /// pre-versioning database
+    fn schema_version(conn: &Connection) -> Result<i32, QueueError> {
+        Ok(conn.query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+            [],
+            |row| row.get(0),
+        )?)
+    }
 
-        if current_version >= 1 {
-            return Ok(());
+    /// Apply the migrations after `from` up to and including `to`, recording
+    /// each in `schema_version`. Must be called inside a `BEGIN IMMEDIATE`
+    /// transaction so that concurrent initializers are serialized.
+    ///
+    /// A database already at or past `to`, e.g. one written by a newer CLI,
+    /// is left as it is: later migrations only add nullable columns, which
+    /// this version ignores.
+    fn migrate(conn: &Connection, from: i32, to: i32) -> Result<(), QueueError> {
+        if from > to {
+            tracing::debug!(
+                "Queue schema version {} is newer than {}, leaving it as is",
+                from,
+                to
+            );
         }
 
-        // Check if columns already exist before adding (handles partial migration)
-        let columns: Vec<String> = conn
-            .prepare("PRAGMA table_info(heartbeats)")?
-            .query_map([], |row| row.get(1))?
-            .collect::<Result<Vec<_>, _>>()?;
+        for version in (from + 1)..=to {
+            let columns: Vec<String> = conn
+                .prepare("PRAGMA table_info(heartbeats)")?
+                .query_map([], |row| row.get(1))?
+                .collect::<Result<Vec<_>, _>>()?;
+            // Each step checks its columns first, so a migration that was
+            // applied without being recorded is completed rather than repeated
+            let add_column = |name: &str, definition: &str| -> Result<(), QueueError> {
+                if !columns.iter().any(|column| column == name) {
+                    conn.execute(
+                        &format!("ALTER TABLE heartbeats ADD COLUMN {} {}", name, definition),
+                        [],
+                    )?;
+                }
+                Ok(())
+            };
+
+            match version {
+                // v1: sync tracking
+                1 => {
+                    add_column("sync_status", "TEXT DEFAULT 'pending'")?;
+                    add_column("sync_metadata", "TEXT")?;
+                }
+                // v2: nullable expires_at for heartbeat TTLs
+                2 => add_column("expires_at", "DATETIME")?,
+                // v3: time of the latest heartbeat seen, for session-aware sampling
+                3 => conn.execute_batch(
+                    "CREATE TABLE IF NOT EXISTS activity (
+                        id INTEGER PRIMARY KEY CHECK (id = 0),
+                        last_time REAL NOT NULL
+                    )",
+                )?,
+                _ => return Err(QueueError::UnknownMigration(version)),
+            }
 
-        if !columns.contains(&"sync_status".to_string()) {
             conn.execute(
-                "ALTER TABLE heartbeats ADD COLUMN sync_status TEXT DEFAULT 'pending'",
-                [],
+                "INSERT INTO schema_version (version) VALUES (?1)",
+                [version],
             )?;
         }
 
-        if !columns.contains(&"sync_metadata".to_string()) {
-            conn.execute("ALTER TABLE heartbeats ADD COLUMN sync_metadata TEXT", [])?;
-        }
-
-        // Record that migration v1 has been applied
-        conn.execute("INSERT INTO schema_version (version) VALUES (1)", [])?;
-
         Ok(())
     }
 
     /// Open database connection with corruption handling
     fn open_with_corruption_handling(db_path: &PathBuf) -> Result<Connection, QueueError> {
+        // Fail with a clear error before SQLite or recovery trip over permissions
+        if let Some(dir) = db_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            Self::ensure_writable_dir(dir)?;
+        }
+
         // First attempt to open normally
         match Connection::open(db_path) {
             Ok(conn) => {
@@ -705,23 +1630,116 @@ impl Queue {
         Ok(conn)
     }
 
+    /// Inspect the database at `db_path` without modifying it. Unlike opening a
+    /// `Queue`, this never runs migrations or corruption recovery.
+    pub fn check_health(db_path: &Path) -> Result<QueueHealth, QueueError> {
+        let size_bytes = std::fs::metadata(db_path)?.len();
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+        let integrity = conn
+            .prepare("PRAGMA integrity_check")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let foreign_key_violations = conn
+            .prepare("PRAGMA foreign_key_check")?
+            .query_map([], |_| Ok(()))?
+            .count();
+
+        let has_table = |name: &str| -> Result<bool, QueueError> {
+            Ok(conn
+                .query_row(
+                    "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                    params![name],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some())
+        };
+
+        let counts_by_status = if has_table("heartbeats")? {
+            conn.prepare(
+                "SELECT COALESCE(sync_status, 'pending'), COUNT(*) FROM heartbeats
+                 GROUP BY 1 ORDER BY 1",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?
+            .collect::<Result<Vec<_>, _>>()?
+        } else {
+            Vec::new()
+        };
+
+        let schema_version = if has_table("schema_version")? {
+            conn.query_row(
+                "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+                [],
+                |row| row.get(0),
+            )?
+        } else {
+            0
+        };
+
+        Ok(QueueHealth {
+            path: db_path.to_path_buf(),
+            integrity,
+            foreign_key_violations,
+            counts_by_status,
+            size_bytes,
+            schema_version,
+        })
+    }
+
+    /// The `limit` most recently added entries of the database at `db_path`,
+    /// read without modifying it
+    pub fn sample_entries(db_path: &Path, limit: usize) -> Result<Vec<QueueEntry>, QueueError> {
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let entries = conn
+            .prepare(
+                "SELECT data, COALESCE(sync_status, 'pending'), sync_metadata, retry_count,
+                        created_at, last_attempt
+                 FROM heartbeats ORDER BY created_at DESC, rowid DESC LIMIT ?1",
+            )?
+            .query_map([limit as i64], read_entry)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// Path of the default queue database, `~/.chronova/queue.db`
+    pub fn default_db_path() -> Result<PathBuf, QueueError> {
+        Self::get_db_path()
+    }
+
     fn get_db_path() -> Result<PathBuf, QueueError> {
         let mut chronova_dir = dirs::home_dir().ok_or_else(|| {
             rusqlite::Error::InvalidPath("Could not determine home directory".to_string().into())
         })?;
 
         chronova_dir.push(".chronova");
-        std::fs::create_dir_all(&chronova_dir)?;
+        Self::ensure_writable_dir(&chronova_dir)?;
 
         chronova_dir.push("queue.db");
         Ok(chronova_dir)
     }
+
+    /// Create `dir` if needed and check a file can be written there. SQLite
+    /// also needs to create its `-wal` and `-shm` files next to the database.
+    fn ensure_writable_dir(dir: &Path) -> Result<(), QueueError> {
+        let not_writable = |e: std::io::Error| {
+            QueueError::DirectoryNotWritable(dir.display().to_string(), e.to_string())
+        };
+
+        std::fs::create_dir_all(dir).map_err(not_writable)?;
+        tempfile::NamedTempFile::new_in(dir)
+            .map(drop)
+            .map_err(not_writable)
+    }
 }
 
 impl Drop for Queue {
     fn drop(&mut self) {
         // Clean up old entries on shutdown (older than 7 days)
         let _ = self.cleanup_old_entries(7);
+        // Drop heartbeats past their TTL, synced or not
+        let _ = self.remove_expired();
     }
 }
 
@@ -800,6 +1818,188 @@ mod tests {
         assert!(debug_output.contains("QueueFull"));
     }
 
+    #[test]
+    fn test_unwritable_queue_dir_is_reported() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        // A regular file where the queue directory should be
+        let blocker = temp_dir.path().join("blocked");
+        std::fs::write(&blocker, b"").unwrap();
+        let Err(err) = Queue::with_path(blocker.join("queue.db")) else {
+            panic!("opened a queue below a regular file");
+        };
+        assert!(
+            matches!(err, QueueError::DirectoryNotWritable(_, _)),
+            "{}",
+            err
+        );
+        assert!(err.to_string().contains("is not writable"), "{}", err);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let read_only = temp_dir.path().join("read-only");
+            std::fs::create_dir(&read_only).unwrap();
+            std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+            // Permission bits don't bind root, so only assert where they apply
+            if std::fs::write(read_only.join("probe"), b"").is_err() {
+                let Err(err) = Queue::with_path(read_only.join("queue.db")) else {
+                    panic!("opened a queue in a read-only directory");
+                };
+                assert!(
+                    matches!(err, QueueError::DirectoryNotWritable(_, _)),
+                    "{}",
+                    err
+                );
+            }
+            std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_close_checkpoints_wal_into_main_file() -> Result<(), QueueError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let wal_path = temp_dir.path().join("queue.db-wal");
+
+        // A second connection keeps SQLite from checkpointing on its own when
+        // the queue's connection closes
+        let other = Connection::open(&db_path)?;
+        let queue = Queue::with_path(db_path.clone())?;
+        for i in 0..20 {
+            queue.add(Heartbeat::builder(format!("/tmp/file{}.rs", i), 1_700_000_000.0).build())?;
+        }
+        other.query_row("SELECT COUNT(*) FROM heartbeats", [], |row| {
+            row.get::<_, i64>(0)
+        })?;
+        assert!(std::fs::metadata(&wal_path)?.len() > 0);
+
+        queue.close()?;
+        assert_eq!(std::fs::metadata(&wal_path)?.len(), 0);
+
+        // The main file alone holds every row
+        let copy = temp_dir.path().join("copy.db");
+        std::fs::copy(&db_path, &copy)?;
+        let rows: i64 =
+            Connection::open(&copy)?
+                .query_row("SELECT COUNT(*) FROM heartbeats", [], |row| row.get(0))?;
+        assert_eq!(rows, 20);
+        drop(other);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_expired_drops_only_expired_rows() -> Result<(), QueueError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let queue = Queue::with_path(temp_dir.path().join("queue.db"))?.with_ttl_days(Some(3));
+
+        let expired = Heartbeat::builder("/work/old.rs", 1_700_000_000.0).build();
+        let fresh = Heartbeat::builder("/work/new.rs", 1_700_000_000.0).build();
+        queue.add(expired.clone())?;
+        queue.add(fresh.clone())?;
+
+        // Still pending, but its TTL ran out an hour ago
+        queue.conn.execute(
+            "UPDATE heartbeats SET expires_at = datetime('now', '-1 hour') WHERE id = ?1",
+            params![expired.id],
+        )?;
+
+        assert_eq!(queue.remove_expired()?, 1);
+        let remaining: Vec<String> = queue.get_all()?.into_iter().map(|hb| hb.id).collect();
+        assert_eq!(remaining, vec![fresh.id.clone()]);
+
+        let expires_at: Option<String> = queue.conn.query_row(
+            "SELECT expires_at FROM heartbeats WHERE id = ?1",
+            params![fresh.id],
+            |row| row.get(0),
+        )?;
+        assert!(expires_at.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressed_rows_round_trip_and_shrink() -> Result<(), QueueError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let heartbeat = Heartbeat::builder(
+            "/home/dev/projects/chronova-cli/src/heartbeat.rs",
+            1_700_000_000.0,
+        )
+        .project("chronova-cli".to_string())
+        .branch("feature/queue-compression".to_string())
+        .language("Rust".to_string())
+        .category("coding".to_string())
+        .machine("dev-laptop".to_string())
+        .user_agent(
+            "wakatime/v1.90.0 (linux) go1.22 vscode/1.89.0 vscode-wakatime/24.5.0".to_string(),
+        )
+        .lines(1800)
+        .lineno(420)
+        .cursorpos(17)
+        .dependencies(vec![
+            "serde".to_string(),
+            "rusqlite".to_string(),
+            "tokio".to_string(),
+        ])
+        .build();
+
+        // A row queued before compression was turned on stays readable
+        let plain = Heartbeat::builder("/work/old.rs", 1_699_999_000.0).build();
+        Queue::with_path(db_path.clone())?.add(plain.clone())?;
+
+        let queue = Queue::with_path(db_path)?.with_compression(true);
+        queue.add(heartbeat.clone())?;
+
+        let stored: Vec<u8> = queue.conn.query_row(
+            "SELECT data FROM heartbeats WHERE id = ?1",
+            params![heartbeat.id],
+            |row| row.get(0),
+        )?;
+        let json = serde_json::to_string(&heartbeat)?;
+        assert!(
+            stored.len() < json.len(),
+            "{} >= {}",
+            stored.len(),
+            json.len()
+        );
+
+        let read = queue.get_by_ids(std::slice::from_ref(&heartbeat.id))?;
+        assert_eq!(serde_json::to_string(&read[0])?, json);
+        assert_eq!(queue.get_all()?.len(), 2);
+
+        // SQL that looks inside the JSON still sees compressed rows
+        queue.claim_for_sync(std::slice::from_ref(&heartbeat.id))?;
+        assert_eq!(queue.get_syncing()?[0].entity, heartbeat.entity);
+
+        assert_eq!(queue.compress_existing()?, 1);
+        assert_eq!(queue.compress_existing()?, 0);
+        assert_eq!(queue.get_by_ids(&[plain.id])?[0].entity, "/work/old.rs");
+
+        let stats = queue.get_sync_stats()?;
+        assert!(stats.stored_bytes < stats.logical_bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_queue_health_on_healthy_db() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+        queue
+            .add(Heartbeat::builder("/work/main.rs", 1_700_000_000.0).build())
+            .unwrap();
+
+        let health = Queue::check_health(&db_path).unwrap();
+
+        assert!(health.is_healthy(), "{}", health.to_text());
+        assert_eq!(health.schema_version, 3);
+        assert_eq!(health.counts_by_status, vec![("pending".to_string(), 1)]);
+        assert!(health.size_bytes > 0);
+        assert!(health.to_text().contains("Queue health: OK"));
+    }
+
     fn create_test_queue_with_old_schema() -> Result<(tempfile::TempDir, Queue), QueueError> {
         let temp_dir = tempfile::tempdir().unwrap();
         let db_path = temp_dir.path().join("test_queue.db");
@@ -817,7 +2017,7 @@ mod tests {
             [],
         )?;
 
-        Ok((temp_dir, Queue { conn }))
+        Ok((temp_dir, Queue::from_connection(conn)?))
     }
 
     fn create_test_queue_with_new_schema() -> Result<(tempfile::TempDir, Queue), QueueError> {
@@ -839,7 +2039,7 @@ mod tests {
             [],
         )?;
 
-        Ok((temp_dir, Queue { conn }))
+        Ok((temp_dir, Queue::from_connection(conn)?))
     }
 
     #[test]
@@ -956,6 +2156,83 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_v0_database_is_migrated_with_its_rows() -> Result<(), QueueError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+
+        // The schema before sync tracking, without schema_version
+        let conn = Connection::open(&db_path)?;
+        conn.execute(
+            "CREATE TABLE heartbeats (
+                id TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                retry_count INTEGER DEFAULT 0,
+                last_attempt DATETIME
+            )",
+            [],
+        )?;
+        let heartbeats: Vec<Heartbeat> = (0..3)
+            .map(|i| Heartbeat::builder(format!("/tmp/v0_{}.rs", i), 1_700_000_000.0).build())
+            .collect();
+        for heartbeat in &heartbeats {
+            conn.execute(
+                "INSERT INTO heartbeats (id, data, retry_count) VALUES (?1, ?2, 2)",
+                params![heartbeat.id, serde_json::to_string(heartbeat)?],
+            )?;
+        }
+        drop(conn);
+
+        let queue = Queue::with_path(db_path)?;
+        assert_eq!(Queue::schema_version(&queue.conn)?, SCHEMA_VERSION);
+        let columns: Vec<String> = queue
+            .conn
+            .prepare("PRAGMA table_info(heartbeats)")?
+            .query_map([], |row| row.get(1))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for column in ["sync_status", "sync_metadata", "expires_at"] {
+            assert!(columns.iter().any(|c| c == column), "missing {}", column);
+        }
+
+        let pending = queue.get_pending(None, Some(SyncStatus::Pending))?;
+        assert_eq!(pending.len(), 3);
+        for heartbeat in &heartbeats {
+            let migrated = pending.iter().find(|h| h.id == heartbeat.id).unwrap();
+            assert_eq!(migrated.entity, heartbeat.entity);
+            assert_eq!(queue.get_retry_count(&heartbeat.id)?, 2);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_schema_version_is_left_alone() -> Result<(), QueueError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        drop(Queue::with_path(db_path.clone())?);
+
+        // As a future CLI would leave it: a new column and a higher version
+        let conn = Connection::open(&db_path)?;
+        conn.execute("ALTER TABLE heartbeats ADD COLUMN priority INTEGER", [])?;
+        conn.execute(
+            "INSERT INTO schema_version (version) VALUES (?1)",
+            [SCHEMA_VERSION + 1],
+        )?;
+        drop(conn);
+
+        let queue = Queue::with_path(db_path)?;
+        assert_eq!(Queue::schema_version(&queue.conn)?, SCHEMA_VERSION + 1);
+        let heartbeat = Heartbeat::builder("/tmp/newer.rs", 1_700_000_000.0).build();
+        queue.add(heartbeat.clone())?;
+        assert_eq!(queue.get_pending(None, None)?[0].id, heartbeat.id);
+
+        assert!(matches!(
+            Queue::migrate(&queue.conn, SCHEMA_VERSION, SCHEMA_VERSION + 1),
+            Err(QueueError::UnknownMigration(_))
+        ));
+        Ok(())
+    }
+
     #[test]
     fn test_schema_versioning_table() -> Result<(), QueueError> {
         let (_temp_dir, queue) = create_test_queue_with_new_schema()?;
@@ -999,38 +2276,31 @@ mod tests {
                 retry_count INTEGER DEFAULT 0,
                 last_attempt DATETIME,
                 sync_status TEXT DEFAULT 'pending',
-                sync_metadata TEXT
+                sync_metadata TEXT,
+                expires_at DATETIME
             )",
             [],
         )?;
 
-        Ok((temp_dir, Queue { conn }))
+        Ok((temp_dir, Queue::from_connection(conn)?))
     }
 
     fn create_test_heartbeat(id: &str) -> Heartbeat {
-        Heartbeat {
-            id: id.to_string(),
-            entity: format!("/path/to/file_{}.rs", id),
-            entity_type: "file".to_string(),
-            time: chrono::Utc::now().timestamp_millis() as f64 / 1000.0,
-            project: Some("test-project".to_string()),
-            branch: Some("main".to_string()),
-            language: Some("Rust".to_string()),
-            is_write: false,
-            lines: Some(100),
-            lineno: Some(10),
-            cursorpos: Some(5),
-            user_agent: Some("test/1.0".to_string()),
-            category: Some("coding".to_string()),
-            machine: Some("test-machine".to_string()),
-            editor: None,
-            operating_system: None,
-            commit_hash: None,
-            commit_author: None,
-            commit_message: None,
-            repository_url: None,
-            dependencies: Vec::new(),
-        }
+        Heartbeat::builder(
+            format!("/path/to/file_{}.rs", id),
+            chrono::Utc::now().timestamp_millis() as f64 / 1000.0,
+        )
+        .id(id)
+        .project("test-project".to_string())
+        .branch("main".to_string())
+        .language("Rust".to_string())
+        .lines(100)
+        .lineno(10)
+        .cursorpos(5)
+        .user_agent("test/1.0".to_string())
+        .category("coding".to_string())
+        .machine("test-machine".to_string())
+        .build()
     }
 
     #[test]
@@ -1047,6 +2317,271 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_deduplicate_keeps_latest_per_entity() -> Result<(), QueueError> {
+        let (_temp_dir, queue) = create_test_queue()?;
+        // Start of a 60 second bucket
+        let bucket = 1_700_000_040.0;
+        queue.add(
+            Heartbeat::builder("/src/a.rs", bucket + 10.0)
+                .id("a-old")
+                .build(),
+        )?;
+        queue.add(
+            Heartbeat::builder("/src/a.rs", bucket + 50.0)
+                .id("a-new")
+                .build(),
+        )?;
+        queue.add(
+            Heartbeat::builder("/src/b.rs", bucket + 5.0)
+                .id("b")
+                .build(),
+        )?;
+        queue.add(
+            Heartbeat::builder("/src/a.rs", bucket - 600.0)
+                .id("a-earlier")
+                .build(),
+        )?;
+        queue.add(
+            Heartbeat::builder("/src/a.rs", bucket + 20.0)
+                .id("a-write")
+                .is_write(true)
+                .build(),
+        )?;
+
+        assert_eq!(queue.deduplicate(60)?, 1);
+
+        let mut ids: Vec<String> = queue.get_all()?.into_iter().map(|hb| hb.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["a-earlier", "a-new", "a-write", "b"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_rolls_back_on_error() -> Result<(), QueueError> {
+        let (_temp_dir, queue) = create_test_queue()?;
+        for id in ["tx-1", "tx-2", "tx-3"] {
+            queue.add(create_test_heartbeat(id))?;
+        }
+
+        let result: Result<(), QueueError> = queue.with_transaction(|tx| {
+            tx.update_sync_status("tx-1", SyncStatus::Synced, Some("synced".to_string()))?;
+            tx.remove("tx-1")?;
+            tx.claim_for_sync(&["tx-2".to_string()])?;
+            Err(QueueError::Database(rusqlite::Error::InvalidQuery))
+        });
+        assert!(result.is_err());
+
+        // Nothing from the failed batch stuck, including the nested claim
+        assert_eq!(queue.count()?, 3);
+        assert_eq!(queue.count_by_status(Some(SyncStatus::Pending))?, 3);
+
+        queue.with_transaction(|tx| {
+            tx.update_sync_status("tx-1", SyncStatus::Synced, Some("synced".to_string()))?;
+            tx.remove("tx-1")
+        })?;
+        assert_eq!(queue.count()?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_claims_never_overlap() -> Result<(), QueueError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("claims.db");
+        let queue = Queue::with_path(db_path.clone())?;
+        for i in 0..40 {
+            queue.add(create_test_heartbeat(&format!("claim-{}", i)))?;
+        }
+
+        let claimers: Vec<_> = (0..2)
+            .map(|_| {
+                let db_path = db_path.clone();
+                std::thread::spawn(move || -> Result<Vec<String>, QueueError> {
+                    let queue = Queue::with_path(db_path)?;
+                    let mut claimed = Vec::new();
+                    loop {
+                        let batch = queue.claim_pending(3, None)?;
+                        if batch.is_empty() {
+                            return Ok(claimed);
+                        }
+                        claimed.extend(batch.into_iter().map(|hb| hb.id));
+                    }
+                })
+            })
+            .collect();
+
+        let mut claimed = Vec::new();
+        for claimer in claimers {
+            claimed.extend(claimer.join().unwrap()?);
+        }
+        // Every heartbeat was claimed, and by exactly one of the two
+        assert_eq!(claimed.len(), 40);
+        claimed.sort();
+        claimed.dedup();
+        assert_eq!(claimed.len(), 40);
+        assert_eq!(queue.count_by_status(Some(SyncStatus::Syncing))?, 40);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_failed_set_is_prepared_for_retry_in_pages() -> Result<(), QueueError> {
+        let (_temp_dir, queue) = create_test_queue()?;
+        let heartbeats: Vec<Heartbeat> = (0..1200)
+            .map(|i| create_test_heartbeat(&format!("failed-{}", i)))
+            .collect();
+        queue.add_batch(heartbeats)?;
+        queue.with_transaction(|tx| {
+            for i in 0..1200 {
+                let id = format!("failed-{}", i);
+                tx.increment_retry(&id)?;
+                if i % 100 == 0 {
+                    // Out of attempts
+                    tx.increment_retry(&id)?;
+                    tx.increment_retry(&id)?;
+                }
+                tx.update_sync_status(&id, SyncStatus::Failed, None)?;
+            }
+            Ok(())
+        })?;
+
+        assert_eq!(queue.prepare_failed_for_retry(3, 500)?, 1188);
+        assert_eq!(queue.count_by_status(Some(SyncStatus::Failed))?, 0);
+        assert_eq!(queue.count_by_status(Some(SyncStatus::Pending))?, 1188);
+        assert_eq!(
+            queue.count_by_status(Some(SyncStatus::PermanentFailure))?,
+            12
+        );
+
+        // Nothing is left to scan on the next cycle
+        assert_eq!(queue.prepare_failed_for_retry(3, 500)?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_heartbeat_waits_for_next_retry_at() -> Result<(), QueueError> {
+        let (_temp_dir, queue) = create_test_queue()?;
+        queue.add(create_test_heartbeat("later"))?;
+        queue.add(create_test_heartbeat("due"))?;
+        queue.increment_retry("later")?;
+        queue.increment_retry("due")?;
+
+        let later = SyncMetadata::failure("Network error", 1, Some(Duration::from_secs(3600)));
+        queue.update_sync_status("later", SyncStatus::Failed, Some(later.to_json()))?;
+        let due = SyncMetadata::failure("Network error", 1, None);
+        queue.update_sync_status("due", SyncStatus::Failed, Some(due.to_json()))?;
+
+        assert_eq!(queue.prepare_failed_for_retry(3, 10)?, 1);
+        let pending = queue.get_entries(None, &[SyncStatus::Pending])?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].heartbeat.id, "due");
+        let metadata = pending[0].sync_metadata.clone().unwrap();
+        assert_eq!(metadata.last_error.as_deref(), Some("Network error"));
+        assert_eq!(metadata.note.as_deref(), Some("Retry eligible"));
+
+        let failed = queue.get_entries(None, &[SyncStatus::Failed])?;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].heartbeat.id, "later");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_outage_trim_coalesces_same_file_heartbeats() -> Result<(), QueueError> {
+        let (_temp_dir, queue) = create_test_queue()?;
+        let start = 1_700_000_000.0;
+
+        // Two sessions on one file, an hour apart, while every sync fails
+        let mut heartbeats: Vec<Heartbeat> = (0..90)
+            .map(|i| {
+                let offset = if i < 45 { 0.0 } else { 3600.0 };
+                Heartbeat::builder("/src/busy.rs", start + offset + i as f64 * 10.0).build()
+            })
+            .collect();
+        heartbeats.extend(
+            (0..5).map(|i| {
+                Heartbeat::builder(format!("/src/other_{}.rs", i), start + i as f64).build()
+            }),
+        );
+        let saved = Heartbeat::builder("/src/busy.rs", start + 5.0)
+            .is_write(true)
+            .build();
+        heartbeats.push(saved.clone());
+        queue.add_batch(heartbeats)?;
+        let failed = queue.get_pending(Some(10), None)?;
+        for heartbeat in &failed {
+            queue.update_sync_status(&heartbeat.id, SyncStatus::Failed, None)?;
+        }
+
+        // Below the threshold nothing happens
+        assert_eq!(queue.trim_for_outage(1000)?, 0);
+
+        assert_eq!(queue.trim_for_outage(100)?, 86);
+        assert!(queue.count()? <= 100);
+
+        let remaining = queue.get_all()?;
+        let busy_reads: Vec<f64> = remaining
+            .iter()
+            .filter(|hb| hb.entity == "/src/busy.rs" && !hb.is_write)
+            .map(|hb| hb.time)
+            .collect();
+        assert_eq!(busy_reads.len(), 4);
+        assert!(busy_reads.contains(&start));
+        assert!(busy_reads.contains(&(start + 3600.0 + 890.0)));
+        assert!(remaining.iter().any(|hb| hb.id == saved.id));
+        for i in 0..5 {
+            let entity = format!("/src/other_{}.rs", i);
+            assert!(remaining.iter().any(|hb| hb.entity == entity));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rapid_writes_coalesce_to_latest() -> Result<(), QueueError> {
+        let (_temp_dir, queue) = create_test_queue()?;
+        let now = chrono::Utc::now().timestamp() as f64;
+        let write = |id: &str, time: f64| {
+            Heartbeat::builder("/src/autosaved.rs", time)
+                .id(id)
+                .is_write(true)
+                .build()
+        };
+
+        assert_eq!(queue.add_coalescing_writes(write("w1", now - 20.0), 30)?, 0);
+        assert_eq!(queue.add_coalescing_writes(write("w3", now), 30)?, 1);
+        // Arrives late but is older than the queued write
+        assert_eq!(queue.add_coalescing_writes(write("w2", now - 10.0), 30)?, 1);
+        // Reads and writes to other files are left alone
+        queue.add(
+            Heartbeat::builder("/src/autosaved.rs", now - 5.0)
+                .id("r1")
+                .build(),
+        )?;
+        let other = Heartbeat::builder("/src/other.rs", now)
+            .id("o1")
+            .is_write(true)
+            .build();
+        assert_eq!(queue.add_coalescing_writes(other, 30)?, 0);
+
+        let all = queue.get_all()?;
+        let writes: Vec<&Heartbeat> = all
+            .iter()
+            .filter(|hb| hb.is_write && hb.entity == "/src/autosaved.rs")
+            .collect();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(writes[0].id, "w3");
+        assert_eq!(writes[0].time, now);
+        assert_eq!(all.len(), 3);
+
+        // Writes further apart than the window are kept
+        assert_eq!(queue.add_coalescing_writes(write("w4", now + 60.0), 30)?, 0);
+
+        Ok(())
+    }
+
     #[test]
     fn test_remove_heartbeat() -> Result<(), QueueError> {
         let (_temp_dir, queue) = create_test_queue()?;
@@ -1319,6 +2854,57 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_stale_syncing_is_flagged_and_requeued() -> Result<(), QueueError> {
+        let (_temp_dir, queue) = create_test_queue()?;
+        queue.add(create_test_heartbeat("fresh"))?;
+        queue.add(create_test_heartbeat("stale"))?;
+        queue.update_sync_status("fresh", SyncStatus::Syncing, None)?;
+        queue.update_sync_status("stale", SyncStatus::Syncing, None)?;
+        queue.conn.execute(
+            "UPDATE heartbeats SET last_attempt = datetime('now', '-1 hour') WHERE id = 'stale'",
+            [],
+        )?;
+
+        let timeout = Duration::from_secs(300);
+        let syncing = queue.get_syncing()?;
+        assert_eq!(syncing.len(), 2);
+        assert_eq!(syncing[0].id, "stale");
+        assert!(syncing[0].is_stale(timeout));
+        assert_eq!(syncing[0].entity, "/path/to/file_stale.rs");
+        assert!(!syncing[1].is_stale(timeout));
+
+        assert_eq!(queue.requeue_stale_syncing(timeout)?, 1);
+        assert_eq!(queue.count_by_status(Some(SyncStatus::Syncing))?, 1);
+        assert_eq!(queue.count_by_status(Some(SyncStatus::Pending))?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_pending_with_multiple_statuses() -> Result<(), QueueError> {
+        let (_temp_dir, queue) = create_test_queue()?;
+        for id in ["pending", "failed", "synced", "permanent"] {
+            queue.add(create_test_heartbeat(id))?;
+        }
+        queue.update_sync_status("failed", SyncStatus::Failed, None)?;
+        queue.update_sync_status("synced", SyncStatus::Synced, None)?;
+        queue.update_sync_status("permanent", SyncStatus::PermanentFailure, None)?;
+
+        let combined =
+            queue.get_pending_with_statuses(None, &[SyncStatus::Pending, SyncStatus::Failed])?;
+        let mut ids: Vec<&str> = combined.iter().map(|hb| hb.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["failed", "pending"]);
+
+        // The single-status form still filters to exactly one status
+        let failed = queue.get_pending(None, Some(SyncStatus::Failed))?;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].id, "failed");
+
+        Ok(())
+    }
+
     #[test]
     fn test_queue_ops_trait_completeness() -> Result<(), QueueError> {
         let (_temp_dir, queue) = create_test_queue()?;
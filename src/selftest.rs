@@ -0,0 +1,167 @@
+//! Offline self-test of the local heartbeat pipeline.
+//!
+//! Exercises config loading, the data directory, a throwaway queue and entity
+//! detection without touching the network, so environment problems (unwritable
+//! home directory, SQLite permissions, git2 failures) surface quickly.
+
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::collector::DataCollector;
+use crate::config::Config;
+use crate::heartbeat::Heartbeat;
+use crate::queue::{Queue, QueueOps};
+
+/// Outcome of a single self-test stage
+#[derive(Debug, Clone, Serialize)]
+pub struct StageResult {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Structured report printed by `--selftest`
+#[derive(Debug, Clone, Serialize)]
+pub struct SelftestReport {
+    pub passed: bool,
+    pub stages: Vec<StageResult>,
+}
+
+impl SelftestReport {
+    /// Render one `PASS`/`FAIL` line per stage
+    pub fn to_text(&self) -> String {
+        let mut lines: Vec<String> = self
+            .stages
+            .iter()
+            .map(|stage| {
+                let status = if stage.passed { "PASS" } else { "FAIL" };
+                format!("{}  {:<10} {}", status, stage.name, stage.detail)
+            })
+            .collect();
+        lines.push(format!(
+            "Selftest {}",
+            if self.passed { "passed" } else { "failed" }
+        ));
+        lines.join("\n")
+    }
+}
+
+/// Run every stage and collect the results; stages never abort each other
+pub async fn run(config_path: &str) -> SelftestReport {
+    let stages = vec![
+        stage("config", check_config(config_path)),
+        stage("data_dir", check_data_dir()),
+        stage("queue", check_queue()),
+        stage("detection", check_detection().await),
+    ];
+
+    SelftestReport {
+        passed: stages.iter().all(|s| s.passed),
+        stages,
+    }
+}
+
+fn stage(name: &str, result: Result<String, String>) -> StageResult {
+    let (passed, detail) = match result {
+        Ok(detail) => (true, detail),
+        Err(detail) => (false, detail),
+    };
+    StageResult {
+        name: name.to_string(),
+        passed,
+        detail,
+    }
+}
+
+fn check_config(config_path: &str) -> Result<String, String> {
+    let path = Config::resolve_config_path(config_path).map_err(|e| e.to_string())?;
+    let (_, warnings) = Config::load_with_warnings(config_path).map_err(|e| e.to_string())?;
+
+    if !warnings.is_empty() {
+        Ok(format!(
+            "loaded {} skipping {} malformed lines",
+            path.display(),
+            warnings.len()
+        ))
+    } else if path.exists() {
+        Ok(format!("loaded {}", path.display()))
+    } else {
+        Ok(format!("{} not found, using defaults", path.display()))
+    }
+}
+
+fn check_data_dir() -> Result<String, String> {
+    let mut dir = dirs::home_dir().ok_or("could not determine home directory")?;
+    dir.push(".chronova");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+
+    let probe = dir.join(".selftest");
+    std::fs::write(&probe, b"ok")
+        .map_err(|e| format!("{} is not writable: {}", dir.display(), e))?;
+    let _ = std::fs::remove_file(&probe);
+
+    Ok(format!("{} is writable", dir.display()))
+}
+
+fn check_queue() -> Result<String, String> {
+    let temp_dir = tempfile::tempdir().map_err(|e| format!("temp dir: {}", e))?;
+    let queue = Queue::with_path(temp_dir.path().join("selftest.db")).map_err(|e| e.to_string())?;
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default();
+    let heartbeat = Heartbeat::builder("selftest.rs", time).build();
+    let id = heartbeat.id.clone();
+
+    queue.add(heartbeat).map_err(|e| e.to_string())?;
+    let pending = queue.get_pending(None, None).map_err(|e| e.to_string())?;
+    if !pending.iter().any(|hb| hb.id == id) {
+        return Err("synthetic heartbeat was not read back".to_string());
+    }
+
+    Ok("inserted and read back a synthetic heartbeat".to_string())
+}
+
+async fn check_detection() -> Result<String, String> {
+    let temp_dir = tempfile::tempdir().map_err(|e| format!("temp dir: {}", e))?;
+    let file = temp_dir.path().join("main.rs");
+    std::fs::write(&file, "fn main() {}\n").map_err(|e| e.to_string())?;
+    let entity = file.to_string_lossy().to_string();
+
+    let collector = DataCollector::new();
+    let language = collector.detect_language(&entity).await;
+    if language.as_deref() != Some("Rust") {
+        return Err(format!("expected Rust, detected {:?}", language));
+    }
+
+    // A bare temp dir has no repository; this only proves git2 loads and runs
+    let git = collector.detect_git_info(&entity).await;
+
+    Ok(format!(
+        "language Rust, git {}",
+        if git.is_some() {
+            "repository found"
+        } else {
+            "available"
+        }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_selftest_passes_in_clean_environment() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join(".chronova.cfg");
+        std::fs::write(&config_path, "[settings]\napi_key = test\n").unwrap();
+
+        let report = run(config_path.to_str().unwrap()).await;
+
+        assert!(report.passed, "{}", report.to_text());
+        let names: Vec<&str> = report.stages.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["config", "data_dir", "queue", "detection"]);
+    }
+}
@@ -0,0 +1,148 @@
+//! Cache of what each API server accepts.
+//!
+//! Authenticated requests try Bearer, Basic and `X-API-Key` authentication in
+//! turn. The method a server accepted is remembered per `api_url` in
+//! `~/.chronova/server_caps.json`, so later invocations go straight to it; an
+//! auth failure with the remembered method drops it and probes again. The
+//! path prefix found by probing `/api/v1` after a 404 is kept there as well.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Once the cache holds more servers than this it starts over
+const MAX_ENTRIES: usize = 100;
+
+/// How an authenticated request presents the API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// `Authorization: Bearer <key>`, Chronova's native scheme
+    Bearer,
+    /// `Authorization: Basic base64(<key>:)`, as sent by wakatime-cli
+    Basic,
+    /// `X-API-Key: <key>`
+    ApiKeyHeader,
+}
+
+impl AuthMethod {
+    /// Probe order when nothing is cached
+    pub const ALL: [AuthMethod; 3] = [
+        AuthMethod::Bearer,
+        AuthMethod::Basic,
+        AuthMethod::ApiKeyHeader,
+    ];
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ServerEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auth: Option<AuthMethod>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    api_prefix: Option<String>,
+}
+
+/// Server capability cache backed by a JSON file
+#[derive(Debug, Clone)]
+pub struct ServerCaps {
+    path: PathBuf,
+}
+
+impl ServerCaps {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Path of the default cache file, `~/.chronova/server_caps.json`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".chronova").join("server_caps.json"))
+    }
+
+    /// Auth method `api_url` accepted last time, if known
+    pub fn auth_method(&self, api_url: &str) -> Option<AuthMethod> {
+        self.load()
+            .get(&cache_key(api_url))
+            .and_then(|entry| entry.auth)
+    }
+
+    /// Remember that `api_url` accepts `auth`
+    pub fn record(&self, api_url: &str, auth: AuthMethod) {
+        self.update(api_url, |entry| entry.auth = Some(auth));
+    }
+
+    /// Forget the auth method `api_url` accepts, e.g. after it rejected the
+    /// cached one
+    pub fn invalidate(&self, api_url: &str) {
+        let mut entries = self.load();
+        let key = cache_key(api_url);
+        let Some(entry) = entries.get_mut(&key) else {
+            return;
+        };
+        entry.auth = None;
+        if entry.api_prefix.is_none() {
+            entries.remove(&key);
+        }
+        self.save_logged(&entries);
+    }
+
+    /// Path prefix such as `/api/v1` that `api_url` needs, if one was found
+    pub fn api_prefix(&self, api_url: &str) -> Option<String> {
+        self.load()
+            .get(&cache_key(api_url))
+            .and_then(|entry| entry.api_prefix.clone())
+    }
+
+    /// Remember that `api_url` serves the API under `prefix`
+    pub fn record_api_prefix(&self, api_url: &str, prefix: &str) {
+        self.update(api_url, |entry| entry.api_prefix = Some(prefix.to_string()));
+    }
+
+    fn update(&self, api_url: &str, change: impl FnOnce(&mut ServerEntry)) {
+        let mut entries = self.load();
+        let key = cache_key(api_url);
+        if entries.len() >= MAX_ENTRIES && !entries.contains_key(&key) {
+            entries.clear();
+        }
+        change(entries.entry(key).or_default());
+        self.save_logged(&entries);
+    }
+
+    fn load(&self) -> HashMap<String, ServerEntry> {
+        std::fs::read(&self.path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    /// Failures only cost the next run a probe, so they are logged
+    fn save_logged(&self, entries: &HashMap<String, ServerEntry>) {
+        if let Err(e) = self.save(entries) {
+            tracing::debug!(
+                "Failed to write server capability cache {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    /// Write through a temp file and rename, so concurrent invocations never
+    /// read a half-written cache
+    fn save(&self, entries: &HashMap<String, ServerEntry>) -> std::io::Result<()> {
+        let dir = self
+            .path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+
+        let mut file = tempfile::NamedTempFile::new_in(dir)?;
+        file.write_all(&serde_json::to_vec(entries)?)?;
+        file.persist(&self.path).map_err(|e| e.error)?;
+        Ok(())
+    }
+}
+
+fn cache_key(api_url: &str) -> String {
+    api_url.trim_end_matches('/').to_string()
+}
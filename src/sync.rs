@@ -1,12 +1,86 @@
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
 use tokio::sync::RwLock;
 
 use crate::api::ApiClient;
-use crate::queue::QueueOps;
+use crate::queue::{Queue, QueueOps};
+
+/// Heartbeats left in `Syncing` longer than this are assumed to belong to a
+/// crashed run and are moved back to pending before the next sync.
+pub const SYNCING_STALE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Open the queue at `path`, or the default queue when `None`
+fn open_queue(path: Option<&Path>) -> Result<Queue, SyncError> {
+    match path {
+        Some(path) => Queue::with_path(path.to_path_buf()),
+        None => Queue::new(),
+    }
+    .map_err(|e| SyncError::Database(format!("{}", e)))
+}
+
+/// Mark `ids` synced and remove them in one transaction, so a crash midway
+/// never leaves part of an accepted batch behind to be sent again
+pub(crate) fn finalize_synced(queue: &Queue, ids: &[String]) -> Result<(), SyncError> {
+    queue
+        .with_transaction(|tx| {
+            for id in ids {
+                tx.update_sync_status(id, SyncStatus::Synced, None)?;
+                tx.remove(id)?;
+            }
+            Ok(())
+        })
+        .map_err(|e| SyncError::Database(format!("{}", e)))
+}
+
+/// Count a failed attempt for each `(id, error)` in one transaction, marking
+/// heartbeats that reached the strategy's `max_attempts`, or all of them when
+/// the error isn't `retryable`, as permanently failed.
+/// The error, attempt and next retry time are stored as [`SyncMetadata`].
+fn record_failed_attempts(
+    queue: &Queue,
+    failures: &[(String, String)],
+    strategy: &RetryStrategy,
+    retryable: bool,
+    field_errors: &[(String, String)],
+) -> Result<(), SyncError> {
+    queue
+        .with_transaction(|tx| {
+            for (id, err_meta) in failures {
+                tx.increment_retry(id)?;
+                let rc = tx.get_retry_count(id).unwrap_or(0);
+                let (status, next_retry_in) = if !retryable || rc >= strategy.max_attempts {
+                    (SyncStatus::PermanentFailure, None)
+                } else {
+                    (SyncStatus::Failed, Some(strategy.calculate_delay(rc)))
+                };
+                let metadata = SyncMetadata {
+                    field_errors: field_errors.to_vec(),
+                    ..SyncMetadata::failure(err_meta, rc, next_retry_in)
+                };
+                tx.update_sync_status(id, status, Some(metadata.to_json()))?;
+            }
+            Ok(())
+        })
+        .map_err(|e| SyncError::Database(format!("{}", e)))
+}
+
+/// Hand claimed heartbeats back to pending without counting an attempt, for
+/// batches a rate limit kept from being sent
+fn release_to_pending(queue: &Queue, ids: &[String]) -> Result<(), SyncError> {
+    queue
+        .with_transaction(|tx| {
+            for id in ids {
+                tx.update_sync_status(id, SyncStatus::Pending, None)?;
+            }
+            Ok(())
+        })
+        .map_err(|e| SyncError::Database(format!("{}", e)))
+}
 
 /// Represents the synchronization status of a heartbeat
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -49,27 +123,158 @@ impl From<SyncStatus> for String {
     }
 }
 
-/// Represents the result of a sync operation
-#[derive(Debug, Clone, Default)]
+/// Retry state stored in a heartbeat's `sync_metadata` column as JSON.
+///
+/// Older queues hold free-form strings such as `"Sync failed (attempt 2): ..."`;
+/// [`SyncMetadata::parse`] reads those too, keeping anything it can't place in
+/// `note`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncMetadata {
+    /// Error from the most recent failed attempt
+    pub last_error: Option<String>,
+    /// Number of failed attempts so far
+    pub attempt: u32,
+    /// Earliest time the heartbeat will be retried; `None` once out of attempts
+    /// or when it is simply retried on the next run
+    pub next_retry_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Free-form status text, e.g. a legacy value that could not be parsed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// `(field, message)` pairs from a server validation error
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub field_errors: crate::api::FieldErrors,
+}
+
+impl SyncMetadata {
+    /// Metadata for failed attempt number `attempt`, retried after `next_retry_in`
+    pub fn failure(error: &str, attempt: u32, next_retry_in: Option<Duration>) -> Self {
+        Self {
+            last_error: Some(error.to_string()),
+            attempt,
+            next_retry_at: next_retry_in.and_then(|delay| {
+                chrono::Duration::from_std(delay)
+                    .ok()
+                    .map(|delay| chrono::Utc::now() + delay)
+            }),
+            note: None,
+            field_errors: Vec::new(),
+        }
+    }
+
+    /// Metadata for a status change that isn't a failure, e.g. a retry being
+    /// scheduled, after `attempt` failed attempts
+    pub fn note(note: &str, attempt: u32) -> Self {
+        Self {
+            attempt,
+            note: Some(note.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Serialize for the `sync_metadata` column
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Parse a `sync_metadata` value, either JSON or a legacy free-form string
+    pub fn parse(raw: &str) -> Self {
+        if let Ok(metadata) = serde_json::from_str::<Self>(raw) {
+            return metadata;
+        }
+
+        let legacy = raw
+            .strip_prefix("Sync failed (attempt ")
+            .and_then(|rest| rest.split_once("): "))
+            .or_else(|| {
+                raw.strip_prefix("Permanent failure after ")
+                    .and_then(|rest| rest.split_once(" attempts: "))
+            });
+        if let Some((attempt, error)) = legacy {
+            if let Ok(attempt) = attempt.parse() {
+                return Self {
+                    last_error: Some(error.to_string()),
+                    attempt,
+                    ..Default::default()
+                };
+            }
+        }
+
+        // e.g. "Attempting sync (attempt 2)" or "Permanent failure after 3 attempts"
+        let attempt = raw
+            .split_once("attempt ")
+            .or_else(|| raw.split_once("after "))
+            .and_then(|(_, rest)| {
+                rest.split(|c: char| !c.is_ascii_digit())
+                    .next()
+                    .and_then(|digits| digits.parse().ok())
+            })
+            .unwrap_or(0);
+        Self {
+            attempt,
+            note: Some(raw.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Represents the result of a sync operation.
+///
+/// Serializes for `--output json` with durations in milliseconds and
+/// timestamps as Unix seconds.
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct SyncResult {
     /// Number of heartbeats successfully synced
+    #[serde(rename = "synced")]
     pub synced_count: usize,
     /// Number of heartbeats that failed to sync
+    #[serde(rename = "failed")]
     pub failed_count: usize,
     /// Total number of heartbeats processed
+    #[serde(rename = "total")]
     pub total_count: usize,
     /// Duration of the sync operation
+    #[serde(rename = "duration_ms", serialize_with = "serialize_duration_ms")]
     pub duration: std::time::Duration,
     /// Error if the sync operation failed completely
+    #[serde(serialize_with = "serialize_error")]
     pub error: Option<SyncError>,
     /// Timestamp when the sync operation started
+    #[serde(rename = "start", serialize_with = "serialize_unix_time")]
     pub start_time: Option<SystemTime>,
     /// Timestamp when the sync operation ended
+    #[serde(rename = "end", serialize_with = "serialize_unix_time")]
     pub end_time: Option<SystemTime>,
     /// Average sync latency per heartbeat in milliseconds
     pub avg_latency_ms: Option<f64>,
 }
 
+fn serialize_duration_ms<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+fn serialize_unix_time<S: serde::Serializer>(
+    time: &Option<SystemTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match time.and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok()) {
+        Some(since_epoch) => serializer.serialize_some(&since_epoch.as_secs_f64()),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn serialize_error<S: serde::Serializer>(
+    error: &Option<SyncError>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match error {
+        Some(error) => serializer.serialize_some(&error.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
 /// Represents a summary of sync status
 #[derive(Debug, Clone, Default)]
 pub struct SyncStatusSummary {
@@ -85,6 +290,10 @@ pub struct SyncStatusSummary {
     pub permanent_failures: usize,
     /// Total number of heartbeats
     pub total: usize,
+    /// Bytes the heartbeat data takes in the queue, after any compression
+    pub stored_bytes: u64,
+    /// Bytes the heartbeat data takes as plain JSON
+    pub logical_bytes: u64,
     /// Last sync attempt timestamp
     pub last_sync: Option<SystemTime>,
 }
@@ -104,10 +313,37 @@ pub enum SyncError {
     Serialization(String),
     #[error("Invalid configuration: {0}")]
     Config(String),
+    /// The server refused the request with a 4xx status; resending won't help
+    #[error("Request rejected ({0}): {1}")]
+    Rejected(u16, String),
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
 
+impl From<crate::api::ApiError> for SyncError {
+    fn from(err: crate::api::ApiError) -> Self {
+        use crate::api::ApiError;
+        match err {
+            // Auth failures are fixed by the user, so unlike Rejected they keep retrying
+            ApiError::Auth(msg) => SyncError::Auth(msg),
+            ApiError::RateLimit(msg, _) => SyncError::RateLimit(msg),
+            ApiError::Network(err) => SyncError::Network(err.to_string()),
+            // 429 arrives as RateLimit, so a 4xx here is down to the request itself
+            ApiError::Api {
+                status: Some(status @ 400..=499),
+                message,
+                body,
+            } => SyncError::Rejected(status, format!("{}: {}", message, body)),
+            ApiError::Api { message, body, .. } => {
+                SyncError::Network(format!("{}: {}", message, body))
+            }
+            ApiError::Tls(msg) => SyncError::Config(msg),
+            err @ ApiError::NotFound(_) => SyncError::Rejected(404, err.to_string()),
+            err @ ApiError::Validation(_) => SyncError::Rejected(422, err.to_string()),
+        }
+    }
+}
+
 /// Configuration for retry strategy with exponential backoff and jitter
 #[derive(Debug, Clone)]
 pub struct RetryStrategy {
@@ -170,6 +406,7 @@ impl RetryStrategy {
             SyncError::Unknown(_) => true,
             SyncError::Auth(_) => false, // Auth errors are not retryable
             SyncError::Config(_) => false, // Config errors are not retryable
+            SyncError::Rejected(..) => false,
         }
     }
 }
@@ -197,6 +434,30 @@ pub struct SyncConfig {
     pub retention_days: u32,
     /// Enable automatic background sync
     pub background_sync: bool,
+    /// Consecutive empty background cycles before the interval starts growing
+    pub idle_backoff_after_cycles: u32,
+    /// Factor applied to the interval for each further empty cycle
+    pub idle_backoff_multiplier: u32,
+    /// Upper bound for the idle background sync interval in seconds
+    pub idle_backoff_max_seconds: u64,
+    /// Only sync heartbeats queued within this many days; older ones stay pending
+    pub max_age_days: Option<u32>,
+    /// How long every batch holds off after the server answers 429, in seconds
+    pub rate_limit_pause_seconds: u64,
+    /// Skip the connectivity probe and always attempt background sync, leaving
+    /// offline detection to send failures and retries
+    pub assume_online: bool,
+    /// Failed heartbeats read per page when preparing retries at the start of
+    /// a sync cycle
+    pub retry_scan_limit: usize,
+}
+
+impl SyncConfig {
+    /// `max_age_days` as a cutoff for queue queries
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age_days
+            .map(|days| Duration::from_secs(u64::from(days) * 24 * 60 * 60))
+    }
 }
 
 impl Default for SyncConfig {
@@ -212,6 +473,13 @@ impl Default for SyncConfig {
             retry_use_jitter: true,
             retention_days: 7,
             background_sync: true,
+            idle_backoff_after_cycles: 3,
+            idle_backoff_multiplier: 2,
+            idle_backoff_max_seconds: 3600, // 1 hour
+            max_age_days: None,
+            rate_limit_pause_seconds: 60,
+            assume_online: false,
+            retry_scan_limit: 1000,
         }
     }
 }
@@ -258,6 +526,17 @@ pub struct ChronovaSyncManager {
     pub total_sync_latency_ms: Arc<AtomicU64>,
     /// Performance metrics: queue size monitoring
     pub last_queue_size: Arc<RwLock<Option<usize>>>,
+    /// Queue database to sync from; the default `~/.chronova/queue.db` when `None`
+    pub queue_path: Option<PathBuf>,
+    /// Performance metrics: latency histograms per operation
+    pub latency_histograms: Arc<Mutex<HashMap<SyncOperation, LatencyHistogram>>>,
+    /// Consecutive background cycles that found an empty queue
+    pub idle_cycles: Arc<AtomicU32>,
+    /// Rate-limit gate shared by every batch: no request is sent before this instant
+    pub rate_limited_until: Arc<RwLock<Option<Instant>>>,
+    /// Config file whose sync settings background sync reloads when it changes;
+    /// `~/.chronova.cfg` when `None`
+    pub config_path: Option<PathBuf>,
 }
 
 impl ChronovaSyncManager {
@@ -282,6 +561,11 @@ impl ChronovaSyncManager {
             failed_sync_operations: Arc::new(AtomicU64::new(0)),
             total_sync_latency_ms: Arc::new(AtomicU64::new(0)),
             last_queue_size: Arc::new(RwLock::new(None)),
+            queue_path: None,
+            latency_histograms: Arc::new(Mutex::new(HashMap::new())),
+            idle_cycles: Arc::new(AtomicU32::new(0)),
+            rate_limited_until: Arc::new(RwLock::new(None)),
+            config_path: None,
         }
     }
 
@@ -305,6 +589,11 @@ impl ChronovaSyncManager {
             failed_sync_operations: Arc::new(AtomicU64::new(0)),
             total_sync_latency_ms: Arc::new(AtomicU64::new(0)),
             last_queue_size: Arc::new(RwLock::new(None)),
+            queue_path: None,
+            latency_histograms: Arc::new(Mutex::new(HashMap::new())),
+            idle_cycles: Arc::new(AtomicU32::new(0)),
+            rate_limited_until: Arc::new(RwLock::new(None)),
+            config_path: None,
         }
     }
 
@@ -325,6 +614,57 @@ impl ChronovaSyncManager {
             failed_sync_operations: Arc::new(AtomicU64::new(0)),
             total_sync_latency_ms: Arc::new(AtomicU64::new(0)),
             last_queue_size: Arc::new(RwLock::new(None)),
+            queue_path: None,
+            latency_histograms: Arc::new(Mutex::new(HashMap::new())),
+            idle_cycles: Arc::new(AtomicU32::new(0)),
+            rate_limited_until: Arc::new(RwLock::new(None)),
+            config_path: None,
+        }
+    }
+
+    /// Sync from the queue database at `path` instead of the default one
+    pub fn with_queue_path(mut self, path: PathBuf) -> Self {
+        self.queue_path = Some(path);
+        self
+    }
+
+    /// Reload sync settings from the config file at `path`, instead of
+    /// `~/.chronova.cfg`, whenever it changes while background sync runs
+    pub fn with_config_reload(mut self, path: PathBuf) -> Self {
+        self.config_path = Some(path);
+        self
+    }
+
+    /// Switch to the sync settings in `config`, logging each one that changed.
+    /// `enabled` and `background_sync` only take effect on restart, since the
+    /// background task is already running.
+    pub fn apply_sync_config(&mut self, mut config: SyncConfig) {
+        config.enabled = self.config.enabled;
+        config.background_sync = self.config.background_sync;
+        let changes = crate::config_watch::sync_config_changes(&self.config, &config);
+        if changes.is_empty() {
+            return;
+        }
+        tracing::info!("Reloaded sync settings: {}", changes.join(", "));
+
+        self.retry_strategy = RetryStrategy {
+            base_delay_seconds: config.retry_base_delay_seconds,
+            max_attempts: config.max_retry_attempts,
+            max_delay_seconds: config.retry_max_delay_seconds,
+            use_jitter: config.retry_use_jitter,
+        };
+        self.config = config;
+    }
+
+    /// Apply the sync settings `watcher` reloaded since the last call, if any.
+    /// Returns whether the config file changed.
+    pub fn reload_config(&mut self, watcher: &crate::config_watch::SyncConfigWatcher) -> bool {
+        match watcher.take_update() {
+            Some(config) => {
+                self.apply_sync_config(config);
+                true
+            }
+            None => false,
         }
     }
 
@@ -368,53 +708,266 @@ impl ChronovaSyncManager {
         Ok(())
     }
 
-    /// Start background sync thread that automatically syncs pending heartbeats
+    /// Run one background sync cycle and return how long to sleep before the next.
+    /// Cycles that find an empty queue count towards the idle backoff; any
+    /// pending heartbeat resets it to the base interval.
+    pub async fn run_background_cycle(&self) -> Duration {
+        // Check if we're connected before attempting sync
+        let connectivity = if self.config.assume_online {
+            Ok(true)
+        } else {
+            self.check_connectivity().await
+        };
+        match connectivity {
+            Ok(is_connected) => {
+                if is_connected {
+                    tracing::debug!("Network connected, attempting background sync");
+
+                    // Perform sync operation
+                    match self.sync_pending().await {
+                        Ok(result) => {
+                            if result.synced_count > 0 {
+                                tracing::info!(
+                                    "Background sync completed: {} heartbeats synced, {} failed",
+                                    result.synced_count,
+                                    result.failed_count
+                                );
+                            } else {
+                                tracing::debug!("Background sync: no heartbeats to sync");
+                            }
+                            self.record_background_cycle(result.total_count == 0);
+                        }
+                        Err(e) => {
+                            tracing::warn!("Background sync failed: {}", e);
+                            self.record_background_cycle(false);
+                        }
+                    }
+                } else {
+                    tracing::debug!("Network disconnected, skipping background sync");
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Connectivity check failed for background sync: {}", e);
+            }
+        }
+
+        self.effective_sync_interval()
+    }
+
+    /// Count an empty background cycle, or reset the count when there was work
+    fn record_background_cycle(&self, was_empty: bool) {
+        if was_empty {
+            self.idle_cycles.fetch_add(1, Ordering::SeqCst);
+        } else {
+            self.idle_cycles.store(0, Ordering::SeqCst);
+        }
+    }
+
+    /// Background sync interval after the current run of empty cycles: the base
+    /// `sync_interval_seconds` until `idle_backoff_after_cycles` is reached, then
+    /// multiplied once per further empty cycle up to `idle_backoff_max_seconds`
+    pub fn effective_sync_interval(&self) -> Duration {
+        let base = self.config.sync_interval_seconds;
+        let idle_cycles = self.idle_cycles.load(Ordering::SeqCst);
+        let after = self.config.idle_backoff_after_cycles;
+
+        if after == 0 || idle_cycles < after || self.config.idle_backoff_multiplier <= 1 {
+            return Duration::from_secs(base);
+        }
+
+        let cap = self.config.idle_backoff_max_seconds.max(base);
+        let exponent = idle_cycles - after + 1;
+        let factor = (self.config.idle_backoff_multiplier as u64)
+            .checked_pow(exponent)
+            .unwrap_or(u64::MAX);
+        Duration::from_secs(base.saturating_mul(factor).min(cap))
+    }
+
+    /// Wait until the shared rate-limit window, if any, has passed
+    pub async fn wait_for_rate_limit(&self) {
+        let until = *self.rate_limited_until.read().await;
+        if let Some(until) = until {
+            let now = Instant::now();
+            if until > now {
+                tracing::info!(
+                    "Rate limited, waiting {}ms before sending",
+                    (until - now).as_millis()
+                );
+                tokio::time::sleep(until - now).await;
+            }
+        }
+    }
+
+    /// Open or extend the shared rate-limit window after a 429, for as long
+    /// as the server's `Retry-After` asked or `rate_limit_pause_seconds`
+    async fn note_rate_limit(&self, retry_after: Option<Duration>) {
+        let pause =
+            retry_after.unwrap_or(Duration::from_secs(self.config.rate_limit_pause_seconds));
+        let until = Instant::now() + pause;
+        let mut gate = self.rate_limited_until.write().await;
+        if gate.is_none_or(|current| current < until) {
+            *gate = Some(until);
+        }
+    }
+
+    /// Sync exactly the heartbeats with `ids`, whatever their current status,
+    /// e.g. to resend entries fixed by hand after `--print-offline-heartbeats`.
+    /// IDs that are not in the queue are skipped.
+    #[tracing::instrument(name = "sync", skip_all, fields(sync_id = %uuid::Uuid::new_v4()))]
+    pub async fn sync_ids(&self, ids: &[String]) -> Result<SyncResult, SyncError> {
+        let start = self.log_sync_start("sync_ids", Some(ids.len()));
+        let mut result = SyncResult {
+            start_time: Some(SystemTime::now()),
+            ..Default::default()
+        };
+
+        // Wait out a rate limit before claiming, so the rows stay pending meanwhile
+        self.wait_for_rate_limit().await;
+
+        let queue_path = self.queue_path.clone();
+        let ids = ids.to_vec();
+        let selected = tokio::task::spawn_blocking(
+            move || -> Result<Vec<crate::heartbeat::Heartbeat>, SyncError> {
+                let queue = open_queue(queue_path.as_deref())?;
+                queue
+                    .with_transaction(|tx| {
+                        let hbs = tx.get_by_ids(&ids)?;
+                        let found: Vec<String> = hbs.iter().map(|hb| hb.id.clone()).collect();
+                        tx.claim_for_sync(&found)?;
+                        Ok(hbs)
+                    })
+                    .map_err(|e| SyncError::Database(format!("{}", e)))
+            },
+        )
+        .await
+        .map_err(|e| SyncError::Unknown(format!("Join error: {}", e)))??;
+
+        if !selected.is_empty() {
+            self.send_claimed(&selected, &mut result).await?;
+        }
+
+        result.duration = Instant::now().duration_since(start);
+        result.end_time = Some(SystemTime::now());
+
+        self.log_sync_completion("sync_ids", &result, start);
+        self.record_sync_metrics(&result);
+
+        Ok(result)
+    }
+
+    /// Send heartbeats already claimed for sync and record the outcome: accepted
+    /// rows are removed, rejected or failed ones go back for retry
+    async fn send_claimed(
+        &self,
+        pending: &[crate::heartbeat::Heartbeat],
+        result: &mut SyncResult,
+    ) -> Result<(), SyncError> {
+        result.total_count = pending.len();
+
+        let send_start = Instant::now();
+        let send_result = self.api_client.send_heartbeats_batch(pending).await;
+        self.record_latency(SyncOperation::SendHeartbeat, send_start.elapsed());
+
+        match send_result {
+            Ok(response) => {
+                let rejections = crate::api::parse_batch_rejections(response).await;
+                let (ids, rejected) = crate::api::partition_batch(pending, rejections);
+                let synced = ids.len();
+
+                // Mark and remove accepted entries in a single blocking operation
+                let queue_path = self.queue_path.clone();
+                tokio::task::spawn_blocking(move || -> Result<(), SyncError> {
+                    let q = open_queue(queue_path.as_deref())?;
+                    finalize_synced(&q, &ids)
+                })
+                .await
+                .map_err(|e| SyncError::Unknown(format!("Join error: {}", e)))??;
+                result.synced_count = synced;
+                result.failed_count = rejected.len();
+                self.record_rejections(rejected).await?;
+            }
+            Err(crate::api::ApiError::RateLimit(msg, retry_after)) => {
+                // Not the batch's fault, so it goes back to pending without using up an attempt
+                self.note_rate_limit(retry_after).await;
+                let ids: Vec<String> = pending.iter().map(|hb| hb.id.clone()).collect();
+                let queue_path = self.queue_path.clone();
+                tokio::task::spawn_blocking(move || -> Result<(), SyncError> {
+                    let q = open_queue(queue_path.as_deref())?;
+                    release_to_pending(&q, &ids)
+                })
+                .await
+                .map_err(|e| SyncError::Unknown(format!("Join error: {}", e)))??;
+                result.error = Some(SyncError::RateLimit(msg));
+            }
+            Err(api_err) => {
+                let field_errors = match &api_err {
+                    crate::api::ApiError::Validation(errors) => errors.clone(),
+                    _ => Vec::new(),
+                };
+                let mapped = SyncError::from(api_err);
+
+                // Consolidate retry updates into one blocking operation
+                let err_meta = format!("{}", mapped);
+                let retryable = !matches!(mapped, SyncError::Rejected(..));
+                let failures: Vec<(String, String)> = pending
+                    .iter()
+                    .map(|hb| (hb.id.clone(), err_meta.clone()))
+                    .collect();
+                let strategy = self.retry_strategy.clone();
+
+                let queue_path = self.queue_path.clone();
+                tokio::task::spawn_blocking(move || -> Result<(), SyncError> {
+                    let q = open_queue(queue_path.as_deref())?;
+                    record_failed_attempts(&q, &failures, &strategy, retryable, &field_errors)
+                })
+                .await
+                .map_err(|e| SyncError::Unknown(format!("Join error: {}", e)))??;
+
+                result.failed_count = pending.len();
+                result.error = Some(mapped);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start background sync thread that automatically syncs pending heartbeats,
+    /// picking up sync settings from the config file as it changes
     pub async fn start_background_sync(&self) -> Result<(), SyncError> {
+        if !self.config.enabled {
+            tracing::info!("Offline sync is disabled in configuration");
+            return Ok(());
+        }
         if !self.config.background_sync {
             tracing::info!("Background sync is disabled in configuration");
             return Ok(());
         }
 
-        let sync_manager = self.clone();
-        let sync_interval = Duration::from_secs(self.config.sync_interval_seconds);
+        let mut sync_manager = self.clone();
+        let config_path = match &self.config_path {
+            Some(path) => Some(path.clone()),
+            None => crate::config::Config::resolve_config_path("~/.chronova.cfg").ok(),
+        };
+        let watcher = config_path.as_deref().and_then(|path| {
+            crate::config_watch::SyncConfigWatcher::new(path)
+                .map_err(|e| {
+                    tracing::warn!("Not watching {} for changes: {}", path.display(), e);
+                })
+                .ok()
+        });
 
         tokio::spawn(async move {
             tracing::info!(
                 "Starting background sync with interval: {} seconds",
-                sync_interval.as_secs()
+                sync_manager.config.sync_interval_seconds
             );
 
             loop {
-                // Check if we're connected before attempting sync
-                match sync_manager.check_connectivity().await {
-                    Ok(is_connected) => {
-                        if is_connected {
-                            tracing::debug!("Network connected, attempting background sync");
-
-                            // Perform sync operation
-                            match sync_manager.sync_pending().await {
-                                Ok(result) => {
-                                    if result.synced_count > 0 {
-                                        tracing::info!("Background sync completed: {} heartbeats synced, {} failed",
-                                            result.synced_count, result.failed_count);
-                                    } else {
-                                        tracing::debug!("Background sync: no heartbeats to sync");
-                                    }
-                                }
-                                Err(e) => {
-                                    tracing::warn!("Background sync failed: {}", e);
-                                }
-                            }
-                        } else {
-                            tracing::debug!("Network disconnected, skipping background sync");
-                        }
-                    }
-                    Err(e) => {
-                        tracing::warn!("Connectivity check failed for background sync: {}", e);
-                    }
+                // Settings changed during the last sleep apply from this cycle on
+                if let Some(watcher) = &watcher {
+                    sync_manager.reload_config(watcher);
                 }
-
-                // Wait for next sync interval
+                let sync_interval = sync_manager.run_background_cycle().await;
                 tokio::time::sleep(sync_interval).await;
             }
         });
@@ -475,6 +1028,11 @@ impl ChronovaSyncManager {
 
     /// Start both connectivity monitoring and background sync
     pub async fn start_all_services(&self) -> Result<(), SyncError> {
+        if !self.config.enabled {
+            tracing::info!("Offline sync is disabled in configuration");
+            return Ok(());
+        }
+
         // Start connectivity monitoring
         self.start_connectivity_monitoring().await?;
 
@@ -561,6 +1119,24 @@ impl ChronovaSyncManager {
         }
     }
 
+    /// Record the latency of one `operation` in its histogram
+    pub fn record_latency(&self, operation: SyncOperation, latency: Duration) {
+        let mut histograms = self
+            .latency_histograms
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        histograms.entry(operation).or_default().record(latency);
+    }
+
+    /// Snapshot of the latency histogram for `operation`, if it has samples
+    pub fn latency_histogram(&self, operation: SyncOperation) -> Option<LatencyHistogram> {
+        let histograms = self
+            .latency_histograms
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        histograms.get(&operation).cloned()
+    }
+
     /// Update queue size monitoring
     pub async fn update_queue_size(&self, queue_size: usize) {
         let mut last_size_guard = self.last_queue_size.write().await;
@@ -592,6 +1168,27 @@ impl ChronovaSyncManager {
         *last_size_guard
     }
 
+    /// Apply retry accounting to heartbeats the server rejected inside an accepted batch
+    async fn record_rejections(&self, rejected: Vec<(String, String)>) -> Result<(), SyncError> {
+        if rejected.is_empty() {
+            return Ok(());
+        }
+
+        tracing::warn!(
+            "Server rejected {} heartbeats in accepted batch",
+            rejected.len()
+        );
+
+        let strategy = self.retry_strategy.clone();
+        let queue_path = self.queue_path.clone();
+        tokio::task::spawn_blocking(move || -> Result<(), SyncError> {
+            let q = open_queue(queue_path.as_deref())?;
+            record_failed_attempts(&q, &rejected, &strategy, true, &[])
+        })
+        .await
+        .map_err(|e| SyncError::Unknown(format!("Join error: {}", e)))?
+    }
+
     /// Calculate and log sync latency metrics
     pub fn calculate_latency_metrics(
         &self,
@@ -653,6 +1250,85 @@ impl ChronovaSyncManager {
     }
 }
 
+/// Upper bounds of the latency histogram buckets in milliseconds
+pub const LATENCY_BUCKETS_MS: [u64; 11] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Operations whose latency is tracked separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyncOperation {
+    SyncPending,
+    SyncBatch,
+    Connectivity,
+    SendHeartbeat,
+}
+
+impl SyncOperation {
+    pub const ALL: [SyncOperation; 4] = [
+        SyncOperation::SyncPending,
+        SyncOperation::SyncBatch,
+        SyncOperation::Connectivity,
+        SyncOperation::SendHeartbeat,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncOperation::SyncPending => "sync_pending",
+            SyncOperation::SyncBatch => "sync_batch",
+            SyncOperation::Connectivity => "connectivity",
+            SyncOperation::SendHeartbeat => "send_heartbeat",
+        }
+    }
+}
+
+/// Fixed-bucket latency histogram. Percentiles resolve to the upper bound of
+/// the bucket holding the requested sample, or the largest sample seen when it
+/// falls past the last bucket.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    /// Non-cumulative sample counts per entry in `LATENCY_BUCKETS_MS`
+    pub buckets: [u64; LATENCY_BUCKETS_MS.len()],
+    pub count: u64,
+    pub sum_ms: u64,
+    pub max_ms: u64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let ms = latency.as_millis() as u64;
+        if let Some(index) = LATENCY_BUCKETS_MS.iter().position(|bound| ms <= *bound) {
+            self.buckets[index] += 1;
+        }
+        self.count += 1;
+        self.sum_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    /// Latency in milliseconds at percentile `p` (0.0..=1.0), `None` without samples
+    pub fn percentile(&self, p: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = ((p.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS_MS.iter().zip(self.buckets.iter()) {
+            cumulative += count;
+            if cumulative >= rank {
+                return Some(*bound);
+            }
+        }
+        Some(self.max_ms)
+    }
+
+    pub fn p50(&self) -> Option<u64> {
+        self.percentile(0.50)
+    }
+
+    pub fn p95(&self) -> Option<u64> {
+        self.percentile(0.95)
+    }
+}
+
 /// Performance metrics for sync operations
 #[derive(Debug, Clone)]
 pub struct PerformanceMetrics {
@@ -693,9 +1369,9 @@ impl Default for ChronovaSyncManager {
 
 #[async_trait::async_trait]
 impl SyncManager for ChronovaSyncManager {
+    #[tracing::instrument(name = "sync", skip_all, fields(sync_id = %uuid::Uuid::new_v4()))]
     async fn sync_pending(&self) -> Result<SyncResult, SyncError> {
         use crate::heartbeat::Heartbeat;
-        use crate::queue::Queue;
 
         let start = self.log_sync_start("sync_pending", None);
         let mut sync_result = SyncResult {
@@ -707,14 +1383,24 @@ impl SyncManager for ChronovaSyncManager {
         let batch_size = std::cmp::min(self.config.batch_size, self.config.max_queue_size);
 
         loop {
+            // Wait out a rate limit before claiming, so the rows stay pending meanwhile
+            self.wait_for_rate_limit().await;
+
             // Fetch a batch of pending heartbeats from the on-disk queue inside a blocking thread
+            let queue_path = self.queue_path.clone();
+            let max_age = self.config.max_age();
             let pending_res = tokio::task::spawn_blocking({
                 move || -> Result<Vec<Heartbeat>, SyncError> {
-                    let queue = Queue::new().map_err(|e| SyncError::Database(format!("{}", e)))?;
-                    let hbs = queue
-                        .get_pending(Some(batch_size), Some(SyncStatus::Pending))
+                    let queue = open_queue(queue_path.as_deref())?;
+                    // Recover batches claimed by a run that crashed before finalizing
+                    queue
+                        .requeue_stale_syncing(SYNCING_STALE_TIMEOUT)
                         .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                    Ok(hbs)
+                    // Claim the batch as it is selected so a concurrent sync cannot pick it
+                    // up too, and a crash mid-send leaves it in syncing for the reaper
+                    queue
+                        .claim_pending(batch_size, max_age)
+                        .map_err(|e| SyncError::Database(format!("{}", e)))
                 }
             })
             .await
@@ -729,44 +1415,52 @@ impl SyncManager for ChronovaSyncManager {
             sync_result.total_count += pending_res.len();
             let batch_start = Instant::now();
 
-            match self.api_client.send_heartbeats_batch(&pending_res).await {
-                Ok(_response) => {
-                    // Mark and remove all entries in a single blocking operation to avoid
+            let send_result = self.api_client.send_heartbeats_batch(&pending_res).await;
+            self.record_latency(SyncOperation::SendHeartbeat, batch_start.elapsed());
+
+            match send_result {
+                Ok(response) => {
+                    // A 202 may accept the batch while rejecting individual items
+                    let rejections = crate::api::parse_batch_rejections(response).await;
+                    let (ids, rejected) = crate::api::partition_batch(&pending_res, rejections);
+                    let synced = ids.len();
+
+                    // Mark and remove accepted entries in a single blocking operation to avoid
                     // repeated DB opens and visibility issues.
-                    let ids: Vec<String> = pending_res.iter().map(|hb| hb.id.clone()).collect();
+                    let queue_path = self.queue_path.clone();
                     let _ = tokio::task::spawn_blocking(move || -> Result<(), SyncError> {
-                        let q = Queue::new().map_err(|e| SyncError::Database(format!("{}", e)))?;
-                        for id in ids {
-                            q.update_sync_status(
-                                &id,
-                                SyncStatus::Synced,
-                                Some("synced".to_string()),
-                            )
-                            .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                            q.remove(&id)
-                                .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                        }
-                        Ok(())
+                        let q = open_queue(queue_path.as_deref())?;
+                        finalize_synced(&q, &ids)
                     })
                     .await
                     .map_err(|e| SyncError::Unknown(format!("Join error: {}", e)))??;
 
-                    sync_result.synced_count += pending_res.len();
+                    sync_result.synced_count += synced;
+                    sync_result.failed_count += rejected.len();
+                    self.record_rejections(rejected).await?;
+                }
+                Err(crate::api::ApiError::RateLimit(_, retry_after)) => {
+                    // Not the batch's fault: hand it back to pending without using up an
+                    // attempt and leave the rest of the queue for the next cycle
+                    self.note_rate_limit(retry_after).await;
+                    tracing::warn!("Rate limited, leaving queued heartbeats for the next sync");
+                    let ids: Vec<String> = pending_res.iter().map(|hb| hb.id.clone()).collect();
+                    let queue_path = self.queue_path.clone();
+                    tokio::task::spawn_blocking(move || -> Result<(), SyncError> {
+                        let q = open_queue(queue_path.as_deref())?;
+                        release_to_pending(&q, &ids)
+                    })
+                    .await
+                    .map_err(|e| SyncError::Unknown(format!("Join error: {}", e)))??;
+                    break;
                 }
                 Err(api_err) => {
-                    // Map ApiError to SyncError for metrics/logging
-                    let mapped = match api_err {
-                        crate::api::ApiError::Auth(msg) => SyncError::Auth(msg.to_string()),
-                        crate::api::ApiError::RateLimit(msg) => {
-                            SyncError::RateLimit(msg.to_string())
-                        }
-                        crate::api::ApiError::Network(err) => {
-                            SyncError::Network(format!("{}", err))
-                        }
-                        crate::api::ApiError::Api(a, b) => {
-                            SyncError::Network(format!("{}: {}", a, b))
-                        }
+                    let field_errors = match &api_err {
+                        crate::api::ApiError::Validation(errors) => errors.clone(),
+                        _ => Vec::new(),
                     };
+                    // Map ApiError to SyncError for metrics/logging
+                    let mapped = SyncError::from(api_err);
 
                     tracing::warn!(
                         "Batch sync failed with error: {}. Processing per-heartbeat retry logic.",
@@ -775,33 +1469,18 @@ impl SyncManager for ChronovaSyncManager {
 
                     // Consolidate per-heartbeat retry handling into a single blocking operation
                     // to avoid multiple DB opens and improve atomicity.
-                    let ids: Vec<String> = pending_res.iter().map(|hb| hb.id.clone()).collect();
                     let err_meta = format!("{}", mapped);
-                    let max_attempts = self.retry_strategy.max_attempts;
-
+                    let retryable = !matches!(mapped, SyncError::Rejected(..));
+                    let failures: Vec<(String, String)> = pending_res
+                        .iter()
+                        .map(|hb| (hb.id.clone(), err_meta.clone()))
+                        .collect();
+                    let strategy = self.retry_strategy.clone();
+
+                    let queue_path = self.queue_path.clone();
                     let _ = tokio::task::spawn_blocking(move || -> Result<(), SyncError> {
-                        let q = Queue::new().map_err(|e| SyncError::Database(format!("{}", e)))?;
-                        for id in ids {
-                            q.increment_retry(&id)
-                                .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                            let rc = q.get_retry_count(&id).unwrap_or(0);
-                            if rc >= max_attempts {
-                                q.update_sync_status(
-                                    &id,
-                                    SyncStatus::PermanentFailure,
-                                    Some(err_meta.clone()),
-                                )
-                                .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                            } else {
-                                q.update_sync_status(
-                                    &id,
-                                    SyncStatus::Failed,
-                                    Some(err_meta.clone()),
-                                )
-                                .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                            }
-                        }
-                        Ok(())
+                        let q = open_queue(queue_path.as_deref())?;
+                        record_failed_attempts(&q, &failures, &strategy, retryable, &field_errors)
                     })
                     .await
                     .map_err(|e| SyncError::Unknown(format!("Join error: {}", e)))??;
@@ -827,13 +1506,14 @@ impl SyncManager for ChronovaSyncManager {
 
         self.log_sync_completion("sync_pending", &sync_result, start);
         self.record_sync_metrics(&sync_result);
+        self.record_latency(SyncOperation::SyncPending, sync_result.duration);
 
         Ok(sync_result)
     }
 
+    #[tracing::instrument(name = "sync", skip_all, fields(sync_id = %uuid::Uuid::new_v4()))]
     async fn sync_batch(&self, batch_size: usize) -> Result<SyncResult, SyncError> {
         use crate::heartbeat::Heartbeat;
-        use crate::queue::Queue;
 
         let start = self.log_sync_start("sync_batch", Some(batch_size));
         let mut result = SyncResult {
@@ -841,14 +1521,24 @@ impl SyncManager for ChronovaSyncManager {
             ..Default::default()
         };
 
+        // Wait out a rate limit before claiming, so the rows stay pending meanwhile
+        self.wait_for_rate_limit().await;
+
         // Fetch up to batch_size pending heartbeats
+        let queue_path = self.queue_path.clone();
+        let max_age = self.config.max_age();
         let pending = tokio::task::spawn_blocking({
             move || -> Result<Vec<Heartbeat>, SyncError> {
-                let queue = Queue::new().map_err(|e| SyncError::Database(format!("{}", e)))?;
-                let hbs = queue
-                    .get_pending(Some(batch_size), Some(SyncStatus::Pending))
+                let queue = open_queue(queue_path.as_deref())?;
+                // Recover batches claimed by a run that crashed before finalizing
+                queue
+                    .requeue_stale_syncing(SYNCING_STALE_TIMEOUT)
                     .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                Ok(hbs)
+                // Claim the batch as it is selected so a concurrent sync cannot pick it
+                // up too, and a crash mid-send leaves it in syncing for the reaper
+                queue
+                    .claim_pending(batch_size, max_age)
+                    .map_err(|e| SyncError::Database(format!("{}", e)))
             }
         })
         .await
@@ -862,66 +1552,7 @@ impl SyncManager for ChronovaSyncManager {
             return Ok(result);
         }
 
-        result.total_count = pending.len();
-
-        match self.api_client.send_heartbeats_batch(&pending).await {
-            Ok(_) => {
-                // Mark and remove all entries in a single blocking operation
-                let ids: Vec<String> = pending.iter().map(|hb| hb.id.clone()).collect();
-                let _ = tokio::task::spawn_blocking(move || -> Result<(), SyncError> {
-                    let q = Queue::new().map_err(|e| SyncError::Database(format!("{}", e)))?;
-                    for id in ids {
-                        q.update_sync_status(&id, SyncStatus::Synced, Some("synced".to_string()))
-                            .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                        q.remove(&id)
-                            .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                    }
-                    Ok(())
-                })
-                .await
-                .map_err(|e| SyncError::Unknown(format!("Join error: {}", e)))??;
-                result.synced_count = pending.len();
-            }
-            Err(api_err) => {
-                let mapped = match api_err {
-                    crate::api::ApiError::Auth(msg) => SyncError::Auth(msg.to_string()),
-                    crate::api::ApiError::RateLimit(msg) => SyncError::RateLimit(msg.to_string()),
-                    crate::api::ApiError::Network(err) => SyncError::Network(format!("{}", err)),
-                    crate::api::ApiError::Api(a, b) => SyncError::Network(format!("{}: {}", a, b)),
-                };
-
-                // Consolidate retry updates into one blocking operation
-                let ids: Vec<String> = pending.iter().map(|hb| hb.id.clone()).collect();
-                let err_meta = format!("{}", mapped);
-                let max_attempts = self.retry_strategy.max_attempts;
-
-                let _ = tokio::task::spawn_blocking(move || -> Result<(), SyncError> {
-                    let q = Queue::new().map_err(|e| SyncError::Database(format!("{}", e)))?;
-                    for id in ids {
-                        q.increment_retry(&id)
-                            .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                        let rc = q.get_retry_count(&id).unwrap_or(0);
-                        if rc >= max_attempts {
-                            q.update_sync_status(
-                                &id,
-                                SyncStatus::PermanentFailure,
-                                Some(err_meta.clone()),
-                            )
-                            .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                        } else {
-                            q.update_sync_status(&id, SyncStatus::Failed, Some(err_meta.clone()))
-                                .map_err(|e| SyncError::Database(format!("{}", e)))?;
-                        }
-                    }
-                    Ok(())
-                })
-                .await
-                .map_err(|e| SyncError::Unknown(format!("Join error: {}", e)))??;
-
-                result.failed_count = pending.len();
-                result.error = Some(mapped);
-            }
-        }
+        self.send_claimed(&pending, &mut result).await?;
 
         let end = Instant::now();
         result.duration = end.duration_since(start);
@@ -929,6 +1560,7 @@ impl SyncManager for ChronovaSyncManager {
 
         self.log_sync_completion("sync_batch", &result, start);
         self.record_sync_metrics(&result);
+        self.record_latency(SyncOperation::SyncBatch, result.duration);
 
         Ok(result)
     }
@@ -945,11 +1577,13 @@ impl SyncManager for ChronovaSyncManager {
         }
 
         // If no recent cache or cache is stale, perform a fresh check
+        let check_start = Instant::now();
         let result = self
             .api_client
             .check_connectivity()
             .await
             .map_err(|e| SyncError::Network(format!("Connectivity check failed: {}", e)));
+        self.record_latency(SyncOperation::Connectivity, check_start.elapsed());
 
         // Update cache with fresh result
         if let Ok(is_connected) = &result {
@@ -1096,7 +1730,7 @@ mod tests {
         let mock_server = MockServer::start().await;
 
         // Mock a successful response for connectivity check
-        Mock::given(method("HEAD"))
+        Mock::given(method("GET"))
             .respond_with(ResponseTemplate::new(200))
             .mount(&mock_server)
             .await;
@@ -1130,7 +1764,7 @@ mod tests {
 
         let mock_server = MockServer::start().await;
 
-        Mock::given(method("HEAD"))
+        Mock::given(method("GET"))
             .respond_with(ResponseTemplate::new(200))
             .mount(&mock_server)
             .await;
@@ -1162,7 +1796,7 @@ mod tests {
 
         let mock_server = MockServer::start().await;
 
-        Mock::given(method("HEAD"))
+        Mock::given(method("GET"))
             .respond_with(ResponseTemplate::new(200))
             .mount(&mock_server)
             .await;
@@ -1192,6 +1826,564 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_crash_after_claim_recovers_without_duplicate_send() {
+        use crate::heartbeat::Heartbeat;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+        let mut ids = Vec::new();
+        for i in 0..2 {
+            let heartbeat =
+                Heartbeat::builder(format!("/work/file_{}.rs", i), 1_700_000_000.0 + i as f64)
+                    .build();
+            ids.push(heartbeat.id.clone());
+            queue.add(heartbeat).unwrap();
+        }
+
+        // A previous run claimed the batch and crashed before finalizing
+        queue.claim_for_sync(&ids).unwrap();
+
+        let sync_manager = ChronovaSyncManager::new(ApiClient::new(mock_server.uri()))
+            .with_queue_path(db_path.clone());
+
+        // Fresh claims may still be in flight elsewhere and must not be resent
+        let result = sync_manager.sync_pending().await.unwrap();
+        assert_eq!(result.synced_count, 0);
+        assert_eq!(queue.count_by_status(Some(SyncStatus::Syncing)).unwrap(), 2);
+
+        // Once the claim is stale the reaper requeues it and it is sent exactly once
+        rusqlite::Connection::open(&db_path)
+            .unwrap()
+            .execute(
+                "UPDATE heartbeats SET last_attempt = datetime('now', '-1 hour')",
+                [],
+            )
+            .unwrap();
+        let result = sync_manager.sync_pending().await.unwrap();
+        assert_eq!(result.synced_count, 2);
+        assert_eq!(queue.count().unwrap(), 0);
+
+        let result = sync_manager.sync_pending().await.unwrap();
+        assert_eq!(result.total_count, 0);
+        mock_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_sync_ids_sends_only_selected_heartbeats() {
+        use crate::heartbeat::Heartbeat;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+        let mut ids = Vec::new();
+        for i in 0..4 {
+            let heartbeat =
+                Heartbeat::builder(format!("/work/file_{}.rs", i), 1_700_000_000.0 + i as f64)
+                    .build();
+            ids.push(heartbeat.id.clone());
+            queue.add(heartbeat).unwrap();
+        }
+        // Selected ids are sent even when they are no longer pending
+        queue
+            .update_sync_status(&ids[2], SyncStatus::PermanentFailure, None)
+            .unwrap();
+
+        let sync_manager =
+            ChronovaSyncManager::new(ApiClient::new(mock_server.uri())).with_queue_path(db_path);
+        let selected = vec![ids[0].clone(), ids[2].clone(), "missing".to_string()];
+
+        let result = sync_manager.sync_ids(&selected).await.unwrap();
+        assert_eq!(result.synced_count, 2);
+
+        let remaining: Vec<String> = queue
+            .get_all()
+            .unwrap()
+            .into_iter()
+            .map(|hb| hb.id)
+            .collect();
+        assert_eq!(remaining, vec![ids[1].clone(), ids[3].clone()]);
+        mock_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_sync_result_json_fields() {
+        use crate::heartbeat::Heartbeat;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+        for i in 0..2 {
+            queue
+                .add(Heartbeat::builder(format!("/work/file_{}.rs", i), 1_700_000_000.0).build())
+                .unwrap();
+        }
+
+        let sync_manager =
+            ChronovaSyncManager::new(ApiClient::new(mock_server.uri())).with_queue_path(db_path);
+        let result = sync_manager.sync_pending().await.unwrap();
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["synced"], 2);
+        assert_eq!(json["failed"], 0);
+        assert_eq!(json["total"], 2);
+        assert!(json["duration_ms"].is_f64());
+        assert!(json["start"].as_f64().unwrap() > 1_700_000_000.0);
+        assert!(json["end"].as_f64().unwrap() >= json["start"].as_f64().unwrap());
+        assert!(json["error"].is_null());
+        assert!(json.get("avg_latency_ms").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_failed_sync_writes_structured_metadata() {
+        use crate::heartbeat::Heartbeat;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+        queue
+            .add(Heartbeat::builder("/work/file.rs", 1_700_000_000.0).build())
+            .unwrap();
+
+        let sync_manager =
+            ChronovaSyncManager::new(ApiClient::new(mock_server.uri())).with_queue_path(db_path);
+        let before = chrono::Utc::now();
+        let result = sync_manager.sync_pending().await.unwrap();
+        assert_eq!(result.failed_count, 1);
+
+        let entries = queue.get_entries(Some(10), &[SyncStatus::Failed]).unwrap();
+        assert_eq!(entries.len(), 1);
+        let metadata = entries[0].sync_metadata.clone().unwrap();
+        assert_eq!(metadata.attempt, 1);
+        assert!(metadata
+            .last_error
+            .as_deref()
+            .unwrap()
+            .contains("Network error"));
+        assert!(metadata.next_retry_at.unwrap() >= before);
+        assert_eq!(SyncMetadata::parse(&metadata.to_json()), metadata);
+    }
+
+    #[tokio::test]
+    async fn test_client_error_fails_permanently() {
+        use crate::heartbeat::Heartbeat;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+        queue
+            .add(Heartbeat::builder("/work/file.rs", 1_700_000_000.0).build())
+            .unwrap();
+
+        let sync_manager =
+            ChronovaSyncManager::new(ApiClient::new(mock_server.uri())).with_queue_path(db_path);
+        let result = sync_manager.sync_pending().await.unwrap();
+        assert_eq!(result.failed_count, 1);
+
+        // Unlike a 5xx, resending a request the server refused can't succeed
+        let entries = queue
+            .get_entries(Some(10), &[SyncStatus::PermanentFailure])
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        let metadata = entries[0].sync_metadata.clone().unwrap();
+        assert_eq!(metadata.attempt, 1);
+        assert!(metadata
+            .last_error
+            .as_deref()
+            .unwrap()
+            .starts_with("Request rejected (400)"));
+    }
+
+    #[tokio::test]
+    async fn test_validation_error_fails_permanently_with_field_errors() {
+        use crate::heartbeat::Heartbeat;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(serde_json::json!({
+                "errors": {"time": ["must be in the past"]}
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+        queue
+            .add(Heartbeat::builder("/work/file.rs", 1_700_000_000.0).build())
+            .unwrap();
+
+        let sync_manager =
+            ChronovaSyncManager::new(ApiClient::new(mock_server.uri())).with_queue_path(db_path);
+        let result = sync_manager.sync_pending().await.unwrap();
+        assert_eq!(result.failed_count, 1);
+
+        let entries = queue
+            .get_entries(Some(10), &[SyncStatus::PermanentFailure])
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        let metadata = entries[0].sync_metadata.clone().unwrap();
+        assert_eq!(
+            metadata.field_errors,
+            vec![("time".to_string(), "must be in the past".to_string())]
+        );
+        assert!(metadata.next_retry_at.is_none());
+    }
+
+    #[test]
+    fn test_sync_metadata_parses_legacy_strings() {
+        let failed = SyncMetadata::parse("Sync failed (attempt 2): Network error: timeout");
+        assert_eq!(failed.attempt, 2);
+        assert_eq!(failed.last_error.as_deref(), Some("Network error: timeout"));
+        assert!(failed.next_retry_at.is_none());
+
+        let permanent = SyncMetadata::parse("Permanent failure after 3 attempts: 500: boom");
+        assert_eq!(permanent.attempt, 3);
+        assert_eq!(permanent.last_error.as_deref(), Some("500: boom"));
+
+        let note = SyncMetadata::parse("Retry eligible (attempt 1)");
+        assert_eq!(note.attempt, 1);
+        assert!(note.last_error.is_none());
+        assert_eq!(note.note.as_deref(), Some("Retry eligible (attempt 1)"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_gate_shared_across_batches() {
+        use crate::heartbeat::Heartbeat;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(429))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+        let mut ids = Vec::new();
+        for i in 0..4 {
+            let heartbeat =
+                Heartbeat::builder(format!("/work/file_{}.rs", i), 1_700_000_000.0 + i as f64)
+                    .build();
+            ids.push(heartbeat.id.clone());
+            queue.add(heartbeat).unwrap();
+        }
+
+        let config = SyncConfig {
+            rate_limit_pause_seconds: 1,
+            ..Default::default()
+        };
+        let sync_manager =
+            ChronovaSyncManager::with_config(config, ApiClient::new(mock_server.uri()))
+                .with_queue_path(db_path);
+
+        let limited = sync_manager.sync_ids(&ids[..1]).await.unwrap();
+        assert_eq!(limited.failed_count, 0);
+        assert!(matches!(limited.error, Some(SyncError::RateLimit(_))));
+
+        // The rate-limited heartbeat is pending again without a counted attempt
+        assert_eq!(queue.get_retry_count(&ids[0]).unwrap(), 0);
+        assert_eq!(queue.count_by_status(Some(SyncStatus::Pending)).unwrap(), 4);
+
+        // Three concurrent batches wait out the same window instead of one each
+        let (second, third) = (sync_manager.clone(), sync_manager.clone());
+        let started = Instant::now();
+        let (a, b, c) = tokio::join!(
+            sync_manager.sync_ids(&ids[1..2]),
+            second.sync_ids(&ids[2..3]),
+            third.sync_ids(&ids[3..4]),
+        );
+        let elapsed = started.elapsed();
+
+        let synced: usize = [a, b, c].into_iter().map(|r| r.unwrap().synced_count).sum();
+        assert_eq!(synced, 3);
+        assert!(elapsed >= Duration::from_millis(500), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_config_file_change_updates_sync_interval() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(".chronova.cfg");
+        std::fs::write(
+            &path,
+            "[settings]\nsync_interval = 300\napi_key = old-key\n",
+        )
+        .unwrap();
+
+        let mut sync_manager = ChronovaSyncManager::new(ApiClient::new("http://localhost".into()));
+        let watcher = crate::config_watch::SyncConfigWatcher::new(&path).unwrap();
+        assert!(!sync_manager.reload_config(&watcher));
+
+        std::fs::write(
+            &path,
+            "[settings]\nsync_interval = 5\nsync_batch_size = 10\napi_key = new-key\n",
+        )
+        .unwrap();
+        let mut reloaded = false;
+        for _ in 0..50 {
+            if sync_manager.reload_config(&watcher) {
+                reloaded = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        assert!(reloaded);
+        assert_eq!(
+            sync_manager.effective_sync_interval(),
+            Duration::from_secs(5)
+        );
+        assert_eq!(sync_manager.config.batch_size, 10);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_overrides_rate_limit_pause() {
+        use crate::heartbeat::Heartbeat;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+        let mut ids = Vec::new();
+        for i in 0..2 {
+            let heartbeat =
+                Heartbeat::builder(format!("/work/file_{}.rs", i), 1_700_000_000.0 + i as f64)
+                    .build();
+            ids.push(heartbeat.id.clone());
+            queue.add(heartbeat).unwrap();
+        }
+
+        // Without the header the second sync would wait out the full hour
+        let config = SyncConfig {
+            rate_limit_pause_seconds: 3600,
+            ..Default::default()
+        };
+        let sync_manager =
+            ChronovaSyncManager::with_config(config, ApiClient::new(mock_server.uri()))
+                .with_queue_path(db_path);
+
+        let limited = sync_manager.sync_ids(&ids[..1]).await.unwrap();
+        assert_eq!(limited.failed_count, 0);
+
+        let synced = tokio::time::timeout(Duration::from_secs(5), sync_manager.sync_ids(&ids[1..]))
+            .await
+            .expect("Retry-After: 0 should not wait out rate_limit_pause_seconds")
+            .unwrap();
+        assert_eq!(synced.synced_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_max_age_leaves_old_heartbeats_pending() {
+        use crate::heartbeat::Heartbeat;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+        let old = Heartbeat::builder("/work/old.rs", 1_700_000_000.0).build();
+        let recent = Heartbeat::builder("/work/recent.rs", 1_700_000_001.0).build();
+        let old_id = old.id.clone();
+        queue.add(old).unwrap();
+        queue.add(recent).unwrap();
+
+        rusqlite::Connection::open(&db_path)
+            .unwrap()
+            .execute(
+                "UPDATE heartbeats SET created_at = datetime('now', '-3 days') WHERE id = ?1",
+                [&old_id],
+            )
+            .unwrap();
+
+        let config = SyncConfig {
+            max_age_days: Some(1),
+            ..Default::default()
+        };
+        let sync_manager =
+            ChronovaSyncManager::with_config(config, ApiClient::new(mock_server.uri()))
+                .with_queue_path(db_path);
+
+        let result = sync_manager.sync_pending().await.unwrap();
+        assert_eq!(result.synced_count, 1);
+
+        let remaining = queue.get_pending(None, Some(SyncStatus::Pending)).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, old_id);
+        mock_server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn test_idle_backoff_grows_and_resets_after_insert() {
+        use crate::heartbeat::Heartbeat;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+
+        let config = SyncConfig {
+            sync_interval_seconds: 1,
+            idle_backoff_after_cycles: 2,
+            idle_backoff_multiplier: 2,
+            idle_backoff_max_seconds: 4,
+            ..Default::default()
+        };
+        let sync_manager =
+            ChronovaSyncManager::with_config(config, ApiClient::new(mock_server.uri()))
+                .with_queue_path(db_path);
+
+        let mut sleeps = Vec::new();
+        for _ in 0..5 {
+            sleeps.push(sync_manager.run_background_cycle().await.as_secs());
+        }
+        assert_eq!(sleeps, vec![1, 2, 4, 4, 4]);
+
+        queue
+            .add(Heartbeat::builder("/work/main.rs", 1_700_000_000.0).build())
+            .unwrap();
+        assert_eq!(
+            sync_manager.run_background_cycle().await,
+            Duration::from_secs(1)
+        );
+        assert_eq!(queue.count().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_assume_online_syncs_despite_offline_probe() {
+        use crate::heartbeat::Heartbeat;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/users/current/heartbeats"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("queue.db");
+        let queue = Queue::with_path(db_path.clone()).unwrap();
+        queue
+            .add(Heartbeat::builder("/work/main.rs", 1_700_000_000.0).build())
+            .unwrap();
+
+        let offline_manager = |assume_online: bool| {
+            let config = SyncConfig {
+                assume_online,
+                ..Default::default()
+            };
+            let manager =
+                ChronovaSyncManager::with_config(config, ApiClient::new(mock_server.uri()))
+                    .with_queue_path(db_path.clone());
+            // A fresh probe result that says offline
+            manager.connectivity_state.store(false, Ordering::SeqCst);
+            manager
+        };
+
+        let gated = offline_manager(false);
+        *gated.last_connectivity_check.write().await = Some(SystemTime::now());
+        gated.run_background_cycle().await;
+        assert_eq!(queue.count().unwrap(), 1);
+
+        let optimistic = offline_manager(true);
+        *optimistic.last_connectivity_check.write().await = Some(SystemTime::now());
+        optimistic.run_background_cycle().await;
+        assert_eq!(queue.count().unwrap(), 0);
+    }
+
     #[test]
     fn test_cached_connectivity_default() {
         let api_client = ApiClient::new("http://localhost:8080".to_string());
@@ -1319,6 +2511,32 @@ mod tests {
         assert!(!RetryStrategy::is_retryable_error(&SyncError::Config(
             "test".to_string()
         )));
+
+        // 5xx responses are worth retrying, 4xx ones aren't
+        let server_error = crate::api::ApiError::Api {
+            status: Some(503),
+            message: "Server error: 503".to_string(),
+            body: String::new(),
+        };
+        assert!(RetryStrategy::is_retryable_error(&SyncError::from(
+            server_error
+        )));
+        let client_error = crate::api::ApiError::Api {
+            status: Some(400),
+            message: "Client error: 400".to_string(),
+            body: String::new(),
+        };
+        assert!(!RetryStrategy::is_retryable_error(&SyncError::from(
+            client_error
+        )));
+        assert!(matches!(
+            SyncError::from(crate::api::ApiError::NotFound("/heartbeats".to_string())),
+            SyncError::Rejected(404, _)
+        ));
+        assert!(matches!(
+            SyncError::from(crate::api::ApiError::Validation(Vec::new())),
+            SyncError::Rejected(422, _)
+        ));
     }
 
     #[test]
@@ -1373,8 +2591,10 @@ mod tests {
     #[tokio::test]
     async fn test_background_sync_start_disabled() {
         let api_client = ApiClient::new("http://localhost:8080".to_string());
-        let mut config = SyncConfig::default();
-        config.background_sync = false;
+        let config = SyncConfig {
+            background_sync: false,
+            ..SyncConfig::default()
+        };
         let sync_manager = ChronovaSyncManager::with_config(config, api_client);
 
         let result = sync_manager.start_background_sync().await;
@@ -1391,7 +2611,7 @@ mod tests {
 
         let mock_server = MockServer::start().await;
 
-        Mock::given(method("HEAD"))
+        Mock::given(method("GET"))
             .respond_with(ResponseTemplate::new(200))
             .mount(&mock_server)
             .await;
@@ -1413,7 +2633,7 @@ mod tests {
 
         let mock_server = MockServer::start().await;
 
-        Mock::given(method("HEAD"))
+        Mock::given(method("GET"))
             .respond_with(ResponseTemplate::new(200))
             .mount(&mock_server)
             .await;
@@ -1437,8 +2657,10 @@ mod tests {
     #[tokio::test]
     async fn test_sync_interval_configuration() {
         let api_client = ApiClient::new("http://localhost:8080".to_string());
-        let mut config = SyncConfig::default();
-        config.sync_interval_seconds = 60; // 1 minute
+        let config = SyncConfig {
+            sync_interval_seconds: 60, // 1 minute
+            ..SyncConfig::default()
+        };
         let sync_manager = ChronovaSyncManager::with_config(config, api_client);
 
         // Verify the configuration is properly set
@@ -4,8 +4,61 @@
 //! `chronova/{version} ({os}-{core}-{platform}) {runtime} {plugin}`
 
 use std::env;
+use std::fmt;
 use sysinfo::System;
 
+/// Editor and plugin named by a `--plugin` string in the canonical
+/// `editor/version plugin/version` format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginInfo {
+    pub editor: String,
+    pub editor_version: Option<String>,
+    pub plugin: String,
+    pub plugin_version: Option<String>,
+}
+
+/// The two `name[/version]` tokens, as sent in the user agent
+impl fmt::Display for PluginInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let token = |name: &str, version: &Option<String>| match version {
+            Some(version) => format!("{}/{}", name, version),
+            None => name.to_string(),
+        };
+        write!(
+            f,
+            "{} {}",
+            token(&self.editor, &self.editor_version),
+            token(&self.plugin, &self.plugin_version)
+        )
+    }
+}
+
+/// Parse a `--plugin` string such as `vscode/1.106.3 vscode-wakatime/25.5.0`.
+///
+/// Surrounding double quotes are dropped and tokens after the second are
+/// ignored. A single token names both the editor and the plugin. Returns
+/// `None` for blank input or a token without a name, like `/1.0`.
+pub fn parse_plugin(s: &str) -> Option<PluginInfo> {
+    let sanitized = sanitize_plugin_string(s.trim());
+    let mut tokens = sanitized.split_whitespace().map(|token| {
+        let (name, version) = token.split_once('/').unwrap_or((token, ""));
+        let version = (!version.is_empty()).then(|| version.to_string());
+        (!name.is_empty()).then(|| (name.to_string(), version))
+    });
+
+    let (editor, editor_version) = tokens.next()??;
+    let (plugin, plugin_version) = match tokens.next() {
+        Some(token) => token?,
+        None => (editor.clone(), editor_version.clone()),
+    };
+    Some(PluginInfo {
+        editor,
+        editor_version,
+        plugin,
+        plugin_version,
+    })
+}
+
 /// Generates a user agent string compatible with Wakatime's format
 ///
 /// Format: `chronova/{version} ({os}-{core}-{platform}) {runtime} {plugin}`
@@ -23,26 +76,12 @@ pub fn generate_user_agent(plugin: Option<&str>) -> String {
     let os_info = get_os_info();
     let runtime = get_runtime_info();
 
-    // Build plugin part:
-    // If plugin provided and contains at least two whitespace-separated parts,
-    // use the first two (ide/version and plugin/version). If only one part is present,
-    // duplicate it to form two parts. If no plugin is provided, default to
-    // duplicating the cli identifier: "chronova-cli/{version} chronova-cli/{version}"
-    let plugin_part = match plugin {
-        Some(p) => {
-            let s = sanitize_plugin_string(p);
-            let parts: Vec<&str> = s.split_whitespace().collect();
-            if parts.len() >= 2 {
-                format!("{} {}", parts[0], parts[1])
-            } else if parts.len() == 1 {
-                format!("{} {}", parts[0], parts[0])
-            } else {
-                // If plugin string was empty after sanitization, fall back to duplicated CLI token
-                // Don't include the 'v' prefix to match Wakatime-style tokens (e.g. "chronova-cli/0.1.0")
-                format!("chronova-cli/{} chronova-cli/{}", version, version)
-            }
-        }
-        // When no plugin is provided, produce a two-part duplicated CLI token to match Wakatime-style plugin token
+    // Build plugin part from the ide/version and plugin/version tokens. Without
+    // a usable plugin, default to duplicating the cli identifier:
+    // "chronova-cli/{version} chronova-cli/{version}", without a 'v' prefix to
+    // match Wakatime-style tokens
+    let plugin_part = match plugin.and_then(parse_plugin) {
+        Some(info) => info.to_string(),
         None => format!("chronova-cli/{} chronova-cli/{}", version, version),
     };
 
@@ -142,6 +181,56 @@ mod tests {
         assert!(ua.ends_with("vscode/1.106.3 vscode-wakatime/25.5.0"));
     }
 
+    #[test]
+    fn test_parse_well_formed_plugin() {
+        let info = parse_plugin("vscode/1.106.3 vscode-wakatime/25.5.0").unwrap();
+        assert_eq!(
+            info,
+            PluginInfo {
+                editor: "vscode".to_string(),
+                editor_version: Some("1.106.3".to_string()),
+                plugin: "vscode-wakatime".to_string(),
+                plugin_version: Some("25.5.0".to_string()),
+            }
+        );
+        assert_eq!(info.to_string(), "vscode/1.106.3 vscode-wakatime/25.5.0");
+
+        // Quoted, with extra tokens and a version containing a slash
+        let info = parse_plugin("\"jetbrains/2024.1/IU wakatime/15.0 extra/1\"").unwrap();
+        assert_eq!(info.editor_version.as_deref(), Some("2024.1/IU"));
+        assert_eq!(info.plugin, "wakatime");
+    }
+
+    #[test]
+    fn test_parse_malformed_plugin() {
+        assert_eq!(parse_plugin(""), None);
+        assert_eq!(parse_plugin("  \"\"  "), None);
+        assert_eq!(parse_plugin("/1.0 vim-wakatime/11.0"), None);
+        assert_eq!(parse_plugin("vim/9.0 /11.0"), None);
+
+        // One token names both editor and plugin
+        let info = parse_plugin("sublime").unwrap();
+        assert_eq!(info.editor, "sublime");
+        assert_eq!(info.editor_version, None);
+        assert_eq!(info.plugin, "sublime");
+        assert_eq!(info.to_string(), "sublime sublime");
+
+        // A trailing slash is no version
+        assert_eq!(
+            parse_plugin("vim/ vim-wakatime/11.0")
+                .unwrap()
+                .editor_version,
+            None
+        );
+
+        // The user agent falls back to the CLI token
+        let version = env!("CARGO_PKG_VERSION");
+        assert!(generate_user_agent(Some("/broken")).ends_with(&format!(
+            "chronova-cli/{} chronova-cli/{}",
+            version, version
+        )));
+    }
+
     #[test]
     fn test_sanitize_plugin_string() {
         // Test with quotes
@@ -0,0 +1,211 @@
+//! `--watch` mode: turn file saves under a directory into write heartbeats,
+//! for editors that have no Chronova plugin.
+//!
+//! Editors save in different ways: some write in place, others write a
+//! temporary file and rename it over the original. Both end up as one save of
+//! the real path; temporary and swap files are never reported.
+
+use notify::event::{CreateKind, ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Saves of the same file closer together than this count as one heartbeat
+pub const SAVE_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Directories whose contents are never reported
+const IGNORED_DIRS: &[&str] = &[".git", ".hg", ".svn", "node_modules", "target"];
+
+/// Watches a directory tree and yields the files that were saved
+pub struct DirWatcher {
+    // Dropping the watcher stops the event stream
+    _watcher: RecommendedWatcher,
+    events: mpsc::UnboundedReceiver<notify::Result<Event>>,
+    debouncer: SaveDebouncer,
+}
+
+impl DirWatcher {
+    /// Start watching `dir` recursively
+    pub fn new(dir: &Path) -> notify::Result<Self> {
+        let (tx, events) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            // The receiver is gone once the watcher is dropped
+            let _ = tx.send(event);
+        })?;
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            debouncer: SaveDebouncer::new(SAVE_DEBOUNCE),
+        })
+    }
+
+    /// Wait for the next event that saved at least one file and return the
+    /// saved paths. Returns `None` once the watcher has shut down.
+    pub async fn next_saves(&mut self) -> Option<Vec<PathBuf>> {
+        loop {
+            let event = match self.events.recv().await? {
+                Ok(event) => event,
+                Err(e) => {
+                    tracing::warn!("File watcher error: {}", e);
+                    continue;
+                }
+            };
+
+            let now = Instant::now();
+            let saves: Vec<PathBuf> = saved_paths(&event)
+                .into_iter()
+                .filter(|path| self.debouncer.should_emit(path, now))
+                .collect();
+            if !saves.is_empty() {
+                return Some(saves);
+            }
+        }
+    }
+}
+
+/// Files an event leaves saved on disk. For a rename only the destination
+/// counts, which is how atomic saves replace the original file.
+fn saved_paths(event: &Event) -> Vec<PathBuf> {
+    let candidates: Vec<&PathBuf> = match event.kind {
+        EventKind::Create(CreateKind::File | CreateKind::Any)
+        | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any)
+        | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => event.paths.iter().collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            event.paths.last().into_iter().collect()
+        }
+        _ => Vec::new(),
+    };
+
+    candidates
+        .into_iter()
+        .filter(|path| path.is_file() && !is_ignored_path(path))
+        .cloned()
+        .collect()
+}
+
+/// Editor scratch files and anything inside version control or build directories
+fn is_ignored_path(path: &Path) -> bool {
+    let in_ignored_dir = path.components().any(|component| {
+        component
+            .as_os_str()
+            .to_str()
+            .is_some_and(|name| IGNORED_DIRS.contains(&name))
+    });
+    if in_ignored_dir {
+        return true;
+    }
+
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return true;
+    };
+    name.ends_with('~')
+        || name.ends_with(".swp")
+        || name.ends_with(".swx")
+        || name.ends_with(".tmp")
+        || name.starts_with(".#")
+        || name.starts_with(".goutputstream-")
+        // Vim's write-permission probe
+        || name == "4913"
+}
+
+/// Drops repeat saves of a path within the debounce window, so the several
+/// events one save produces yield a single heartbeat
+#[derive(Debug)]
+struct SaveDebouncer {
+    window: Duration,
+    last_emitted: HashMap<PathBuf, Instant>,
+}
+
+impl SaveDebouncer {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_emitted: HashMap::new(),
+        }
+    }
+
+    fn should_emit(&mut self, path: &Path, now: Instant) -> bool {
+        if let Some(last) = self.last_emitted.get(path) {
+            if now.duration_since(*last) < self.window {
+                return false;
+            }
+        }
+        self.last_emitted.insert(path.to_path_buf(), now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::heartbeat::HeartbeatManager;
+    use crate::queue::{Queue, QueueOps};
+    use crate::Cli;
+    use clap::Parser;
+
+    #[test]
+    fn test_atomic_save_counts_once() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("main.rs");
+        let scratch = temp_dir.path().join(".main.rs.swp");
+        std::fs::write(&file, "fn main() {}\n").unwrap();
+        std::fs::write(&scratch, "").unwrap();
+
+        let rename = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(temp_dir.path().join("main.rs.tmp1234"))
+            .add_path(file.clone());
+        let write = Event::new(EventKind::Modify(ModifyKind::Any)).add_path(file.clone());
+        let swap = Event::new(EventKind::Modify(ModifyKind::Any)).add_path(scratch);
+
+        assert_eq!(saved_paths(&rename), vec![file.clone()]);
+        assert!(saved_paths(&swap).is_empty());
+
+        let mut debouncer = SaveDebouncer::new(SAVE_DEBOUNCE);
+        let now = Instant::now();
+        let emitted: Vec<PathBuf> = [rename, write]
+            .iter()
+            .flat_map(saved_paths)
+            .filter(|path| debouncer.should_emit(path, now))
+            .collect();
+        assert_eq!(emitted, vec![file.clone()]);
+        assert!(debouncer.should_emit(&file, now + SAVE_DEBOUNCE));
+    }
+
+    #[tokio::test]
+    async fn test_modified_file_queues_write_heartbeat() {
+        let watched = tempfile::tempdir().unwrap();
+        let db_dir = tempfile::tempdir().unwrap();
+        let db_path = db_dir.path().join("queue.db");
+        let manager = HeartbeatManager::new_with_queue(
+            Config::default(),
+            Queue::with_path(db_path.clone()).unwrap(),
+        )
+        .unwrap();
+
+        let file = watched.path().join("notes.md");
+        std::fs::write(&file, "# notes\n").unwrap();
+        let mut watcher = DirWatcher::new(watched.path()).unwrap();
+        std::fs::write(&file, "# notes\n\nmore\n").unwrap();
+
+        let saves = tokio::time::timeout(Duration::from_secs(10), watcher.next_saves())
+            .await
+            .expect("no save event within 10s")
+            .unwrap();
+        let saved = saves[0].canonicalize().unwrap();
+        assert_eq!(saved, file.canonicalize().unwrap());
+
+        let cli = Cli::parse_from(["chronova-cli", "--watch", "."]);
+        assert!(manager.queue_file_write(&cli, &saves[0]).await.unwrap());
+
+        let heartbeats = Queue::with_path(db_path).unwrap().get_all().unwrap();
+        assert_eq!(heartbeats.len(), 1);
+        assert!(heartbeats[0].is_write);
+        assert!(heartbeats[0].entity.ends_with("notes.md"));
+        assert_eq!(heartbeats[0].language.as_deref(), Some("Markdown"));
+    }
+}
@@ -10,6 +10,130 @@ fn test_wakatime_entity_argument() {
     assert_eq!(cli.entity, Some("/tmp/test.rs".to_string()));
 }
 
+#[test]
+fn test_entity_from_stdin_reader() {
+    let entity = cli::read_entity(&b"/tmp/my project/it's here.rs\r\n"[..]).unwrap();
+    assert_eq!(entity, "/tmp/my project/it's here.rs");
+    assert!(cli::read_entity(&b"\n"[..]).is_err());
+
+    // Anything after the first line is left unread
+    let entity = cli::read_entity(&b"/tmp/first.rs\n/tmp/second.rs\n"[..]).unwrap();
+    assert_eq!(entity, "/tmp/first.rs");
+
+    let args = [
+        "chronova-cli",
+        "--entity-from-stdin",
+        "--entity",
+        "/tmp/a.rs",
+    ];
+    assert!(cli::Cli::try_parse_from(args).is_err());
+}
+
+#[test]
+fn test_entity_from_argfile() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let argfile = temp_dir.path().join("entity.args");
+    std::fs::write(&argfile, "/tmp/my project/src/main file.rs\n--write\n").unwrap();
+
+    let args = cli::expand_argfiles(vec![
+        "chronova-cli".to_string(),
+        "--entity".to_string(),
+        format!("@{}", argfile.display()),
+    ])
+    .unwrap();
+
+    let cli = cli::Cli::parse_from(args);
+    assert_eq!(
+        cli.entity,
+        Some("/tmp/my project/src/main file.rs".to_string())
+    );
+    assert_eq!(cli.write, Some(true));
+
+    let args = cli::expand_argfiles(vec![
+        "chronova-cli".to_string(),
+        format!("--entity=@{}", argfile.display()),
+    ])
+    .unwrap();
+    assert_eq!(args[1], "--entity=/tmp/my project/src/main file.rs");
+
+    // Escaped and missing argfiles are passed through
+    let args = cli::expand_argfiles(vec![
+        "chronova-cli".to_string(),
+        "--entity".to_string(),
+        "@@scope/pkg.ts".to_string(),
+    ])
+    .unwrap();
+    assert_eq!(args[2], "@scope/pkg.ts");
+    let args = cli::expand_argfiles(vec![
+        "chronova-cli".to_string(),
+        "--entity".to_string(),
+        "@/nonexistent/argfile".to_string(),
+    ])
+    .unwrap();
+    assert_eq!(args[2], "@/nonexistent/argfile");
+
+    // Only the entity is read from a file
+    let args = cli::expand_argfiles(vec![
+        "chronova-cli".to_string(),
+        "--project".to_string(),
+        format!("@{}", argfile.display()),
+    ])
+    .unwrap();
+    assert_eq!(args[2], format!("@{}", argfile.display()));
+}
+
+#[test]
+fn test_file_alias_argfile_and_empty_argfile() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let argfile = temp_dir.path().join("entity.args");
+    std::fs::write(
+        &argfile,
+        "/tmp/my project/main.rs
+",
+    )
+    .unwrap();
+
+    let args = cli::expand_argfiles(vec![
+        "chronova-cli".to_string(),
+        "--file".to_string(),
+        format!("@{}", argfile.display()),
+    ])
+    .unwrap();
+    assert_eq!(args[2], "/tmp/my project/main.rs");
+
+    let args = cli::expand_argfiles(vec![
+        "chronova-cli".to_string(),
+        format!("--file=@{}", argfile.display()),
+    ])
+    .unwrap();
+    assert_eq!(args[1], "--file=/tmp/my project/main.rs");
+
+    // An argfile with only blank lines would otherwise leave --entity without a value
+    std::fs::write(&argfile, "\n  \n").unwrap();
+    let err = cli::expand_argfiles(vec![
+        "chronova-cli".to_string(),
+        "--entity".to_string(),
+        format!("@{}", argfile.display()),
+    ])
+    .unwrap_err();
+    assert!(err.to_string().contains("argfile is empty"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_entity_is_converted_lossily() {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    let args = cli::args_lossy([
+        OsString::from("chronova-cli"),
+        OsString::from("--entity"),
+        OsString::from_vec(b"/tmp/\xff.rs".to_vec()),
+    ]);
+    let cli = cli::Cli::parse_from(args);
+    assert_eq!(cli.entity, Some("/tmp/\u{FFFD}.rs".to_string()));
+}
+
 #[test]
 fn test_wakatime_key_argument() {
     let args = vec![
@@ -262,3 +386,73 @@ fn test_wakatime_branch_argument() {
     let cli = cli::Cli::parse_from(args);
     assert_eq!(cli.branch, Some("main".to_string()));
 }
+
+#[test]
+fn test_fix_timestamps_accepts_negative_offset() {
+    let cli = cli::Cli::parse_from(["chronova-cli", "--fix-timestamps", "--offset", "-3600"]);
+    assert!(cli.fix_timestamps);
+    assert_eq!(cli.offset, Some(-3600.0));
+
+    assert!(cli::Cli::try_parse_from(["chronova-cli", "--fix-timestamps"]).is_err());
+}
+
+#[test]
+fn test_file_experts_requires_entity() {
+    let cli = cli::Cli::parse_from(["chronova-cli", "--file-experts", "--entity", "/src/main.rs"]);
+    assert!(cli.file_experts);
+
+    assert!(cli::Cli::try_parse_from(["chronova-cli", "--file-experts"]).is_err());
+}
+
+#[test]
+fn test_catch_up_requires_sync_offline_activity() {
+    let cli = cli::Cli::parse_from([
+        "chronova-cli",
+        "--sync-offline-activity",
+        "1000",
+        "--catch-up",
+    ]);
+    assert!(cli.catch_up);
+
+    assert!(cli::Cli::try_parse_from(["chronova-cli", "--catch-up"]).is_err());
+}
+
+#[test]
+fn test_repair_worktree_paths_with_dry_run() {
+    let cli = cli::Cli::parse_from(["chronova-cli", "--repair-worktree-paths", "--dry-run"]);
+    assert!(cli.repair_worktree_paths);
+    assert!(cli.dry_run);
+}
+
+#[test]
+fn test_api_url_validation() {
+    for url in [
+        "https://chronova.dev/api/v1",
+        "http://localhost:8080",
+        "HTTP://127.0.0.1/api",
+        "http://[::1]:3000/",
+        "https://user@example.com",
+    ] {
+        assert_eq!(cli::parse_api_url(url).as_deref(), Ok(url), "{}", url);
+    }
+    for url in [
+        "chronova.dev/api/v1",
+        "htps://chronova.dev",
+        "https://",
+        "https:///api/v1",
+        "http://localhost:80a0",
+        "https://chronova .dev",
+        "http://[::1/api",
+    ] {
+        assert!(cli::parse_api_url(url).is_err(), "{}", url);
+    }
+
+    let args = [
+        "chronova-cli",
+        "--entity",
+        "/tmp/a.rs",
+        "--api-url",
+        "localhost",
+    ];
+    assert!(cli::Cli::try_parse_from(args).is_err());
+}
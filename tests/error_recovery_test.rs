@@ -1,11 +1,20 @@
 // Integration test for error recovery functionality
 
-// Note: These tests need to be run from within the crate context
-// Since this is a binary crate, we'll focus on integration tests that can be run
-// through the main test suite
+use chronova_cli::sync::{RetryStrategy, SyncError};
 
 #[test]
-fn test_error_recovery_placeholder() {
-    // This is a placeholder test to verify the test framework works
-    assert!(true);
+fn test_transient_errors_are_retried_and_auth_errors_are_not() {
+    assert!(RetryStrategy::is_retryable_error(&SyncError::Network(
+        "connection reset".to_string()
+    )));
+    assert!(RetryStrategy::is_retryable_error(&SyncError::RateLimit(
+        "429".to_string()
+    )));
+    assert!(!RetryStrategy::is_retryable_error(&SyncError::Auth(
+        "invalid api key".to_string()
+    )));
+    assert!(!RetryStrategy::is_retryable_error(&SyncError::Rejected(
+        400,
+        "bad request".to_string()
+    )));
 }
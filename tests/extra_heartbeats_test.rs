@@ -134,3 +134,31 @@ api_key = test-key-123
         .assert()
         .failure();
 }
+
+#[test]
+fn test_entity_from_stdin_keeps_spaces_in_path() {
+    let home = tempfile::tempdir().unwrap();
+    let dir = home.path().join("my project");
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("notes 'draft' v2.rs");
+    std::fs::write(&file, "fn main() {}\n").unwrap();
+    let config_file = home.path().join(".chronova.cfg");
+    std::fs::write(&config_file, "[settings]\napi_key = test-key-123\n").unwrap();
+
+    let output = Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .arg("--config")
+        .arg(&config_file)
+        .arg("--entity-from-stdin")
+        .arg("--dry-run")
+        .write_stdin(format!("{}\n", file.display()))
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let heartbeat: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(heartbeat["entity"], file.to_string_lossy().as_ref());
+}
@@ -1,44 +1,48 @@
 // Simple integration test that doesn't import the entire module structure
 // This tests the public API of the chronova-cli crate
 
+use chronova_cli::heartbeat::{HeartbeatManager, HeartbeatManagerExt};
+
+fn assert_ext<T: HeartbeatManagerExt>() {}
+
 #[tokio::test]
 async fn test_heartbeat_manager_ext_trait_implementation() {
     // This test verifies that the trait is implemented
     // We'll test the actual implementation in unit tests within the heartbeat module
-    assert!(true, "HeartbeatManagerExt trait should be implemented");
+    assert_ext::<HeartbeatManager>();
 }
 
 #[tokio::test]
 async fn test_process_offline_first() {
     // This test verifies the method exists and returns the correct type
     // We'll test the actual implementation in unit tests within the heartbeat module
-    assert!(true, "process_offline_first method should exist");
+    let _ = <HeartbeatManager as HeartbeatManagerExt>::process_offline_first;
 }
 
 #[tokio::test]
 async fn test_get_queue_stats() {
     // This test verifies the method exists and returns the correct type
     // We'll test the actual implementation in unit tests within the heartbeat module
-    assert!(true, "get_queue_stats method should exist");
+    let _ = <HeartbeatManager as HeartbeatManagerExt>::get_queue_stats;
 }
 
 #[tokio::test]
 async fn test_manual_sync() {
     // This test verifies the method exists and returns the correct type
     // We'll test the actual implementation in unit tests within the heartbeat module
-    assert!(true, "manual_sync method should exist");
+    let _ = <HeartbeatManager as HeartbeatManagerExt>::manual_sync;
 }
 
 #[tokio::test]
 async fn test_offline_first_strategy_in_process() {
     // Test that process method uses offline-first strategy
     // This test verifies the integration without actually sending heartbeats
-    assert!(true, "Process method should use offline-first strategy");
+    let _ = HeartbeatManager::process;
 }
 
 #[tokio::test]
 async fn test_queue_processing_with_sync_status() {
     // Test that process_queue handles sync status properly
     // This test verifies the integration without actual queue operations
-    assert!(true, "Queue processing should handle sync status");
+    let _ = <HeartbeatManager as HeartbeatManagerExt>::get_queue_stats;
 }
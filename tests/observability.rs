@@ -1,5 +1,5 @@
 use chronova_cli::api::ApiClient;
-use chronova_cli::sync::{ChronovaSyncManager, PerformanceMetrics, SyncResult};
+use chronova_cli::sync::{ChronovaSyncManager, PerformanceMetrics, SyncOperation, SyncResult};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
@@ -346,3 +346,43 @@ async fn test_observability_integration() {
     let queue_size = sync_manager.get_last_queue_size().await;
     assert!(queue_size.is_none()); // Should be None initially
 }
+
+#[test]
+fn test_latency_histograms_per_operation() {
+    let api_client = ApiClient::new("http://localhost:8080".to_string());
+    let sync_manager = ChronovaSyncManager::new(api_client);
+
+    // 10 fast sends and one slow outlier
+    for _ in 0..10 {
+        sync_manager.record_latency(SyncOperation::SendHeartbeat, Duration::from_millis(20));
+    }
+    sync_manager.record_latency(SyncOperation::SendHeartbeat, Duration::from_millis(800));
+
+    for ms in [40, 60, 70, 90, 200] {
+        sync_manager.record_latency(SyncOperation::SyncPending, Duration::from_millis(ms));
+    }
+    sync_manager.record_latency(SyncOperation::Connectivity, Duration::from_millis(15_000));
+
+    let send = sync_manager
+        .latency_histogram(SyncOperation::SendHeartbeat)
+        .unwrap();
+    assert_eq!(send.count, 11);
+    assert_eq!(send.p50(), Some(25));
+    assert_eq!(send.p95(), Some(1000));
+
+    let pending = sync_manager
+        .latency_histogram(SyncOperation::SyncPending)
+        .unwrap();
+    assert_eq!(pending.p50(), Some(100));
+    assert_eq!(pending.p95(), Some(250));
+
+    // Samples past the last bucket report the largest observed latency
+    let connectivity = sync_manager
+        .latency_histogram(SyncOperation::Connectivity)
+        .unwrap();
+    assert_eq!(connectivity.p50(), Some(15_000));
+
+    assert!(sync_manager
+        .latency_histogram(SyncOperation::SyncBatch)
+        .is_none());
+}
@@ -11,36 +11,27 @@ use tempfile::TempDir;
 use uuid::Uuid;
 
 fn create_test_heartbeat(id: &str, time: f64) -> Heartbeat {
-    Heartbeat {
-        id: id.to_string(),
-        entity: format!("/path/to/file_{}.rs", id),
-        entity_type: "file".to_string(),
-        time,
-        project: Some(format!("test-project-{}", id)),
-        branch: Some("main".to_string()),
-        language: Some("Rust".to_string()),
-        is_write: false,
-        lines: Some(100),
-        lineno: Some(42),
-        cursorpos: Some(10),
-        user_agent: Some("test/1.0.0".to_string()),
-        category: Some("coding".to_string()),
-        machine: Some("test-machine".to_string()),
-        editor: Some(chronova_cli::heartbeat::EditorInfo {
+    Heartbeat::builder(format!("/path/to/file_{}.rs", id), time)
+        .id(id)
+        .project(format!("test-project-{}", id))
+        .branch("main".to_string())
+        .language("Rust".to_string())
+        .lines(100)
+        .lineno(42)
+        .cursorpos(10)
+        .user_agent("test/1.0.0".to_string())
+        .category("coding".to_string())
+        .machine("test-machine".to_string())
+        .editor(chronova_cli::heartbeat::EditorInfo {
             name: "test-editor".to_string(),
             version: Some("1.0".to_string()),
-        }),
-        operating_system: Some(chronova_cli::heartbeat::OsInfo {
+        })
+        .operating_system(chronova_cli::heartbeat::OsInfo {
             name: "test-os".to_string(),
             title: Some("Test OS".to_string()),
             version: Some("1.0".to_string()),
-        }),
-        commit_hash: None,
-        commit_author: None,
-        commit_message: None,
-        repository_url: None,
-        dependencies: Vec::new(),
-    }
+        })
+        .build()
 }
 
 /// Test adding a large number of heartbeats to the queue
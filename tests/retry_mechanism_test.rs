@@ -179,34 +179,28 @@ async fn test_retry_mechanism_successful_retry() {
 }
 
 fn create_test_heartbeat(id: &str) -> chronova_cli::heartbeat::Heartbeat {
-    chronova_cli::heartbeat::Heartbeat {
-        id: id.to_string(),
-        entity: format!("/path/to/file_{}.rs", id),
-        entity_type: "file".to_string(),
-        time: chrono::Utc::now().timestamp_millis() as f64 / 1000.0,
-        project: Some("test-project".to_string()),
-        branch: Some("main".to_string()),
-        language: Some("Rust".to_string()),
-        is_write: false,
-        lines: Some(100),
-        lineno: Some(10),
-        cursorpos: Some(5),
-        user_agent: Some("test/1.0".to_string()),
-        category: Some("coding".to_string()),
-        machine: Some("test-machine".to_string()),
-        editor: Some(chronova_cli::heartbeat::EditorInfo {
-            name: "test-editor".to_string(),
-            version: Some("1.0".to_string()),
-        }),
-        operating_system: Some(chronova_cli::heartbeat::OsInfo {
-            name: "test-os".to_string(),
-            title: Some("Test OS".to_string()),
-            version: Some("1.0".to_string()),
-        }),
-        commit_hash: None,
-        commit_author: None,
-        commit_message: None,
-        repository_url: None,
-        dependencies: Vec::new(),
-    }
+    chronova_cli::heartbeat::Heartbeat::builder(
+        format!("/path/to/file_{}.rs", id),
+        chrono::Utc::now().timestamp_millis() as f64 / 1000.0,
+    )
+    .id(id)
+    .project("test-project".to_string())
+    .branch("main".to_string())
+    .language("Rust".to_string())
+    .lines(100)
+    .lineno(10)
+    .cursorpos(5)
+    .user_agent("test/1.0".to_string())
+    .category("coding".to_string())
+    .machine("test-machine".to_string())
+    .editor(chronova_cli::heartbeat::EditorInfo {
+        name: "test-editor".to_string(),
+        version: Some("1.0".to_string()),
+    })
+    .operating_system(chronova_cli::heartbeat::OsInfo {
+        name: "test-os".to_string(),
+        title: Some("Test OS".to_string()),
+        version: Some("1.0".to_string()),
+    })
+    .build()
 }
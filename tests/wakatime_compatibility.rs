@@ -114,6 +114,600 @@ api_url = {}
         .stdout(predicate::str::contains("min").or(predicate::str::contains("hour")));
 }
 
+#[tokio::test]
+async fn test_today_auth_error_exit_code() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/users/current/statusbar/today"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&mock_server)
+        .await;
+
+    let config_file = NamedTempFile::new().unwrap();
+    let config_content = format!(
+        r#"[settings]
+api_key = invalid_key
+api_url = {}
+"#,
+        mock_server.uri()
+    );
+    fs::write(config_file.path(), config_content).unwrap();
+
+    let mut cmd = Command::cargo_bin("chronova-cli").unwrap();
+    cmd.arg("--today").arg("--config").arg(config_file.path());
+
+    // Editor plugins prompt for a new API key only on exit code 3
+    cmd.assert().code(3);
+}
+
+#[tokio::test]
+async fn test_today_detailed_prints_breakdown() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let stat = |name: &str, seconds: f64, text: &str, percent: f64| {
+        serde_json::json!({
+            "name": name, "total_seconds": seconds, "percent": percent,
+            "digital": "0:00", "text": text, "hours": 0, "minutes": 0
+        })
+    };
+    let stats = serde_json::json!({
+        "data": {
+            "range": "today",
+            "total_seconds": 9000.0,
+            "human_readable_total": "2 hrs 30 mins",
+            "human_readable_daily_average": "2 hrs 30 mins",
+            "languages": [stat("Rust", 6000.0, "1 hr 40 mins", 66.7), stat("Markdown", 3000.0, "50 mins", 33.3)],
+            "projects": [stat("chronova-cli", 9000.0, "2 hrs 30 mins", 100.0)],
+            "editors": [stat("Zed", 9000.0, "2 hrs 30 mins", 100.0)],
+            "operating_systems": [],
+            "categories": [],
+            "best_day": {"date": "2024-01-01", "total_seconds": 9000.0, "text": "2 hrs 30 mins"},
+            "daily_stats": []
+        }
+    });
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/users/current/stats/today"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(stats))
+        .mount(&mock_server)
+        .await;
+
+    let config_file = NamedTempFile::new().unwrap();
+    let config_content = format!(
+        r#"[settings]
+api_key = test_key
+api_url = {}
+"#,
+        mock_server.uri()
+    );
+    fs::write(config_file.path(), config_content).unwrap();
+
+    let mut cmd = Command::cargo_bin("chronova-cli").unwrap();
+    cmd.arg("--today-detailed")
+        .arg("--config")
+        .arg(config_file.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Today: 2 hrs 30 mins"))
+        .stdout(predicate::str::contains("Languages"))
+        .stdout(predicate::str::contains("Rust"))
+        .stdout(predicate::str::contains("1 hr 40 mins"))
+        .stdout(predicate::str::contains("chronova-cli"))
+        .stdout(predicate::str::contains("Editors"))
+        .stdout(predicate::str::contains("Zed"));
+}
+
+#[tokio::test]
+async fn test_today_raw_json_passes_server_body_through() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    // Unusual spacing and key order must survive untouched
+    let body = r#"{"data": {"grand_total": {"total_seconds": 10800, "text": "3 hrs"},  "range": {"text": "Today"}}, "extra": [1, 2]}"#;
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/users/current/statusbar/today"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+        .mount(&mock_server)
+        .await;
+
+    let config_file = NamedTempFile::new().unwrap();
+    let config_content = format!(
+        r#"[settings]
+api_key = test_key
+api_url = {}
+"#,
+        mock_server.uri()
+    );
+    fs::write(config_file.path(), config_content).unwrap();
+
+    let output = Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .args(["--today", "--output", "raw-json", "--config"])
+        .arg(config_file.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, body.as_bytes());
+
+    // json keeps the shaped form
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .args(["--today", "--output", "json", "--config"])
+        .arg(config_file.path())
+        .assert()
+        .success()
+        .stdout(r#"{"has_team_features":false,"text":"3 hrs"}"#);
+}
+
+#[tokio::test]
+async fn test_today_team_prints_member_breakdown() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let team_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/users/current/statusbar/today"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"data":{"grand_total":{"text":"2 hrs","total_seconds":7200}},"has_team_features":true}"#,
+        ))
+        .mount(&team_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/users/current/teams/team-1/statusbar/today"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"data":{
+                "grand_total":{"text":"5 hrs","total_seconds":18000},
+                "members":[
+                    {"name":"bob","total_seconds":3600,"text":"1 hr"},
+                    {"name":"alice","total_seconds":14400,"text":"4 hrs"}
+                ]
+            }}"#,
+        ))
+        .expect(2)
+        .mount(&team_server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let config_path = home.path().join(".chronova.cfg");
+    let write_config = |uri: String| {
+        fs::write(
+            &config_path,
+            format!("[settings]\napi_key = test_key\napi_url = {}\n", uri),
+        )
+        .unwrap()
+    };
+    write_config(team_server.uri());
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .args(["--today-team", "team-1", "--config"])
+        .arg(&config_path)
+        .assert()
+        .success()
+        .stdout("Team team-1: 5 hrs\n  alice  4 hrs\n  bob    1 hr\n");
+
+    let output = Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .args(["--today-team", "team-1", "--output", "json", "--config"])
+        .arg(&config_path)
+        .output()
+        .unwrap();
+    let team: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(team["team_id"], "team-1");
+    assert_eq!(team["members"].as_array().unwrap().len(), 2);
+
+    // Accounts without team features get their own total and a warning
+    let solo_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/users/current/statusbar/today"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"text":"4 mins","has_team_features":false}"#),
+        )
+        .mount(&solo_server)
+        .await;
+    write_config(solo_server.uri());
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .args(["--today-team", "team-1", "--config"])
+        .arg(&config_path)
+        .assert()
+        .success()
+        .stdout("4 mins\n")
+        .stderr(predicate::str::contains("no team features"));
+}
+
+#[tokio::test]
+async fn test_today_goal_prints_progress() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/users/current/goals/goal-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"data":{"title":"Code 2 hrs per day","chart_data":[
+                {"actual_seconds":2700,"actual_seconds_text":"45 mins","goal_seconds":7200}
+            ]}}"#,
+        ))
+        .mount(&mock_server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let config_path = home.path().join(".chronova.cfg");
+    fs::write(
+        &config_path,
+        format!(
+            "[settings]\napi_key = test_key\napi_url = {}\n",
+            mock_server.uri()
+        ),
+    )
+    .unwrap();
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .args(["--today-goal", "goal-1", "--config"])
+        .arg(&config_path)
+        .assert()
+        .success()
+        .stdout("45 mins\n");
+
+    let output = Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .args(["--today-goal", "goal-1", "--output", "json", "--config"])
+        .arg(&config_path)
+        .output()
+        .unwrap();
+    let goal: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(goal["text"], "45 mins");
+    assert_eq!(goal["title"], "Code 2 hrs per day");
+    assert_eq!(goal["seconds_target"], 7200.0);
+}
+
+#[test]
+fn test_config_write_and_read_custom_section() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    // Neither the file nor its directory exist yet
+    let config_path = temp_dir.path().join("nested").join(".chronova.cfg");
+
+    let mut cmd = Command::cargo_bin("chronova-cli").unwrap();
+    cmd.arg("--config")
+        .arg(&config_path)
+        .args(["--config-write", "/home/user/work/.*", "Acme"])
+        .args(["--config-section", "projectmap"])
+        .assert()
+        .success();
+
+    let mut cmd = Command::cargo_bin("chronova-cli").unwrap();
+    cmd.arg("--config")
+        .arg(&config_path)
+        .args([
+            "--config-section",
+            "headers",
+            "--config-write",
+            "x-team",
+            "core",
+        ])
+        .assert()
+        .success();
+
+    let content = fs::read_to_string(&config_path).unwrap();
+    assert!(content.contains("[projectmap]"));
+    assert!(content.contains("[headers]"));
+
+    let mut cmd = Command::cargo_bin("chronova-cli").unwrap();
+    cmd.arg("--config")
+        .arg(&config_path)
+        .args([
+            "--config-read",
+            "/home/user/work/.*",
+            "--config-section",
+            "projectmap",
+        ])
+        .assert()
+        .success()
+        .stdout("Acme\n");
+
+    // The key only exists in [projectmap]
+    let mut cmd = Command::cargo_bin("chronova-cli").unwrap();
+    cmd.arg("--config")
+        .arg(&config_path)
+        .args(["--config-read", "/home/user/work/.*"])
+        .assert()
+        .success()
+        .stdout("\n");
+}
+
+#[tokio::test]
+async fn test_single_instance_only_queues_while_lock_is_held() {
+    use chronova_cli::instance_lock::SyncLock;
+    use chronova_cli::queue::{Queue, QueueOps};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&mock_server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let config_path = home.path().join(".chronova.cfg");
+    fs::write(
+        &config_path,
+        format!(
+            "[settings]\napi_key = test_key\napi_url = {}\nsingle_instance = true\n",
+            mock_server.uri()
+        ),
+    )
+    .unwrap();
+    let run = || {
+        Command::cargo_bin("chronova-cli")
+            .unwrap()
+            .env("HOME", home.path())
+            .arg("--config")
+            .arg(&config_path)
+            .args(["--entity", "/tmp/single_instance.rs"])
+            .assert()
+            .success();
+    };
+
+    // Another "process" is mid-sync
+    let lock = SyncLock::try_acquire(&home.path().join(".chronova").join("cli.lock"))
+        .unwrap()
+        .unwrap();
+    run();
+    assert!(mock_server.received_requests().await.unwrap().is_empty());
+    let queue = Queue::with_path(home.path().join(".chronova").join("queue.db")).unwrap();
+    assert_eq!(queue.get_pending(None, None).unwrap().len(), 1);
+    drop(queue);
+
+    // Once the lock is free the next invocation syncs the backlog
+    drop(lock);
+    run();
+    assert!(!mock_server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_sync_disabled_sends_directly_without_queue_db() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/users/current/heartbeats"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let config_path = home.path().join(".chronova.cfg");
+    fs::write(
+        &config_path,
+        format!(
+            "[settings]\napi_key = test_key\napi_url = {}\nsync_enabled = false\n",
+            mock_server.uri()
+        ),
+    )
+    .unwrap();
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .arg("--config")
+        .arg(&config_path)
+        .args(["--entity", "/tmp/sync_disabled.rs"])
+        .assert()
+        .success();
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(sent["entity"], "/tmp/sync_disabled.rs");
+    assert!(!home.path().join(".chronova").join("queue.db").exists());
+}
+
+#[tokio::test]
+async fn test_sync_disabled_git_hook_sends_directly_without_queue_db() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/users/current/heartbeats"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let config_path = home.path().join(".chronova.cfg");
+    fs::write(
+        &config_path,
+        format!(
+            "[settings]\napi_key = test_key\napi_url = {}\nsync_enabled = false\n",
+            mock_server.uri()
+        ),
+    )
+    .unwrap();
+
+    let repo_dir = tempfile::tempdir().unwrap();
+    let repo = git2::Repository::init(repo_dir.path()).unwrap();
+    fs::write(repo_dir.path().join("lib.rs"), "// committed\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(std::path::Path::new("lib.rs")).unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = git2::Signature::now("Tester", "tester@example.com").unwrap();
+    repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+        .unwrap();
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .current_dir(repo_dir.path())
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--from-git-hook")
+        .assert()
+        .success();
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert!(sent["entity"].as_str().unwrap().ends_with("lib.rs"));
+    assert!(!home.path().join(".chronova").join("queue.db").exists());
+}
+
+/// Run one heartbeat against `mock_server` with `dispatch_mode` set, returning
+/// the temp HOME and how many heartbeats it left in the queue
+fn run_with_dispatch_mode(
+    mock_server: &wiremock::MockServer,
+    mode: &str,
+) -> (tempfile::TempDir, usize) {
+    use chronova_cli::queue::QueueOps;
+
+    let home = tempfile::tempdir().unwrap();
+    let config_path = home.path().join(".chronova.cfg");
+    fs::write(
+        &config_path,
+        format!(
+            "[settings]\napi_key = test_key\napi_url = {}\ndispatch_mode = {}\n",
+            mock_server.uri(),
+            mode
+        ),
+    )
+    .unwrap();
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .arg("--config")
+        .arg(&config_path)
+        .args(["--entity", "/tmp/dispatch_mode.rs"])
+        .assert()
+        .success();
+
+    let queued =
+        chronova_cli::queue::Queue::with_path(home.path().join(".chronova").join("queue.db"))
+            .unwrap()
+            .get_all()
+            .unwrap()
+            .len();
+    (home, queued)
+}
+
+#[tokio::test]
+async fn test_queued_dispatch_mode_never_touches_network() {
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(wiremock::matchers::any())
+        .respond_with(ResponseTemplate::new(201))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let (_home, queued) = run_with_dispatch_mode(&mock_server, "queued");
+    assert_eq!(queued, 1);
+    mock_server.verify().await;
+}
+
+#[tokio::test]
+async fn test_immediate_dispatch_mode_sends_without_queueing() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/users/current/heartbeats"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let (_home, queued) = run_with_dispatch_mode(&mock_server, "immediate");
+    assert_eq!(queued, 0);
+    mock_server.verify().await;
+}
+
+#[tokio::test]
+async fn test_validation_error_marks_heartbeat_permanently_failed() {
+    use chronova_cli::queue::QueueOps;
+    use chronova_cli::sync::SyncStatus;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/users/current/heartbeats"))
+        .respond_with(
+            ResponseTemplate::new(422)
+                .set_body_string(r#"{"errors": {"time": ["must be in the past"]}}"#),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let config_path = home.path().join(".chronova.cfg");
+    fs::write(
+        &config_path,
+        format!(
+            "[settings]\napi_key = test_key\napi_url = {}\n",
+            mock_server.uri()
+        ),
+    )
+    .unwrap();
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .arg("--config")
+        .arg(&config_path)
+        .args(["--entity", "/tmp/invalid.rs"])
+        .assert()
+        .success();
+    mock_server.verify().await;
+
+    let queue =
+        chronova_cli::queue::Queue::with_path(home.path().join(".chronova").join("queue.db"))
+            .unwrap();
+    let entries = queue
+        .get_entries(Some(10), &[SyncStatus::PermanentFailure])
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    let metadata = entries[0].sync_metadata.clone().unwrap();
+    assert_eq!(
+        metadata.field_errors,
+        vec![("time".to_string(), "must be in the past".to_string())]
+    );
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("time: must be in the past"));
+}
+
 #[test]
 fn test_wakatime_write_flag() {
     let mut cmd = Command::cargo_bin("chronova-cli").unwrap();
@@ -127,3 +721,269 @@ fn test_wakatime_write_flag() {
         .success()
         .stdout(predicate::str::contains("Heartbeat added to queue"));
 }
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_pre_send_hook_enriches_queued_heartbeat() {
+    use chronova_cli::queue::QueueOps;
+
+    let home = tempfile::tempdir().unwrap();
+    let hook_path = home.path().join("add_ticket.sh");
+    fs::write(
+        &hook_path,
+        "#!/bin/sh\nsed 's/}$/,\"ticket\":\"ABC-123\"}/'\n",
+    )
+    .unwrap();
+    let config_path = home.path().join(".chronova.cfg");
+    fs::write(
+        &config_path,
+        format!(
+            "[settings]\napi_key = test_key\napi_url = http://127.0.0.1:9\ndispatch_mode = queued\npre_send_hook = sh {}\n",
+            hook_path.display()
+        ),
+    )
+    .unwrap();
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .arg("--config")
+        .arg(&config_path)
+        .args(["--entity", "/tmp/pre_send_hook.rs"])
+        .assert()
+        .success();
+
+    let queued =
+        chronova_cli::queue::Queue::with_path(home.path().join(".chronova").join("queue.db"))
+            .unwrap()
+            .get_all()
+            .unwrap();
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].entity, "/tmp/pre_send_hook.rs");
+    assert_eq!(queued[0].extra["ticket"], "ABC-123");
+}
+
+#[test]
+fn test_malformed_api_url_is_a_config_error() {
+    let home = tempfile::tempdir().unwrap();
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .args([
+            "--entity",
+            "/tmp/api_url.rs",
+            "--api-url",
+            "chronova.dev/api/v1",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("missing scheme"));
+    assert!(!home.path().join(".chronova").join("queue.db").exists());
+}
+
+#[tokio::test]
+async fn test_verbose_reports_api_reachability() {
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(wiremock::matchers::any())
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&mock_server)
+        .await;
+
+    let home = tempfile::tempdir().unwrap();
+    let config_path = home.path().join(".chronova.cfg");
+    fs::write(&config_path, "[settings]\napi_key = test_key\n").unwrap();
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .arg("--config")
+        .arg(&config_path)
+        .args(["--entity", "/tmp/api_url.rs", "--verbose", "--api-url"])
+        .arg(mock_server.uri())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "API server {} is reachable",
+            mock_server.uri()
+        )));
+}
+
+#[test]
+fn test_missing_ssl_certs_file_is_a_config_error() {
+    let home = tempfile::tempdir().unwrap();
+    let config_path = home.path().join(".chronova.cfg");
+    fs::write(
+        &config_path,
+        format!(
+            "[settings]\napi_key = test_key\nssl_certs_file = {}\n",
+            home.path().join("missing-ca.pem").display()
+        ),
+    )
+    .unwrap();
+
+    Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .arg("--config")
+        .arg(&config_path)
+        .args(["--entity", "/tmp/tls.rs"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Failed to read ssl_certs_file"));
+}
+
+/// Seed `count` heartbeats into a fresh HOME and run `--sync-offline-activity`
+/// with `extra_args` against `mock_server_uri`. Returns the command's stdout
+/// and how many heartbeats it left in the queue.
+fn sync_seeded_queue(mock_server_uri: &str, count: usize, extra_args: &[&str]) -> (String, usize) {
+    use chronova_cli::heartbeat::Heartbeat;
+    use chronova_cli::queue::{Queue, QueueOps};
+
+    let home = tempfile::tempdir().unwrap();
+    let config_path = home.path().join(".chronova.cfg");
+    fs::write(
+        &config_path,
+        format!(
+            "[settings]\napi_key = test_key\napi_url = {}\n",
+            mock_server_uri
+        ),
+    )
+    .unwrap();
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64();
+    let queue_path = home.path().join(".chronova").join("queue.db");
+    let queue = Queue::with_path(queue_path.clone()).unwrap();
+    queue
+        .add_batch(
+            (0..count)
+                .map(|i| {
+                    Heartbeat::builder(format!("/tmp/backlog_{}.rs", i), now - i as f64).build()
+                })
+                .collect(),
+        )
+        .unwrap();
+    drop(queue);
+
+    let output = Command::cargo_bin("chronova-cli")
+        .unwrap()
+        .env("HOME", home.path())
+        .arg("--config")
+        .arg(&config_path)
+        .args(["--sync-offline-activity", "1000"])
+        .args(extra_args)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let left = Queue::with_path(queue_path).unwrap().count().unwrap();
+    (String::from_utf8_lossy(&output.stdout).into_owned(), left)
+}
+
+#[tokio::test]
+async fn test_long_retry_after_ends_sync_with_queue_pending() {
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "3600"))
+        .mount(&mock_server)
+        .await;
+
+    let started = std::time::Instant::now();
+    let (_, left) = sync_seeded_queue(&mock_server.uri(), 3, &[]);
+
+    // The hour-long backoff is left to a later run instead of being slept through
+    assert!(started.elapsed() < std::time::Duration::from_secs(30));
+    assert_eq!(left, 3);
+}
+
+#[tokio::test]
+async fn test_catch_up_uses_fewer_requests_than_normal_sync() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    // Without a bulk endpoint, --catch-up would fall back to these batches
+    let normal_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/users/current/heartbeats"))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&normal_server)
+        .await;
+
+    let bulk_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/users/current/heartbeats.bulk"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "max_batch_size": 1000 })),
+        )
+        .mount(&bulk_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/users/current/heartbeats.bulk"))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&bulk_server)
+        .await;
+
+    let (_, left) = sync_seeded_queue(&normal_server.uri(), 500, &[]);
+    assert_eq!(left, 0);
+    let normal_requests = normal_server.received_requests().await.unwrap().len();
+
+    let (stdout, left) = sync_seeded_queue(&bulk_server.uri(), 500, &["--catch-up"]);
+    assert_eq!(left, 0);
+    assert!(stdout.contains("Uploaded 500 heartbeats, 0 remaining"));
+    let catch_up_requests = bulk_server.received_requests().await.unwrap();
+
+    assert_eq!(normal_requests, 10);
+    // One capability probe and one upload
+    assert_eq!(catch_up_requests.len(), 2);
+    let uploaded: Vec<serde_json::Value> =
+        serde_json::from_slice(&catch_up_requests[1].body).unwrap();
+    assert_eq!(uploaded.len(), 500);
+}
+
+#[tokio::test]
+async fn test_catch_up_falls_back_after_repeated_rate_limits() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/users/current/heartbeats.bulk"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "max_batch_size": 1000 })),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/users/current/heartbeats.bulk"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/users/current/heartbeats"))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&mock_server)
+        .await;
+
+    let (_, left) = sync_seeded_queue(&mock_server.uri(), 20, &["--catch-up"]);
+
+    // The bulk endpoint is given up on and regular sync uploads the batch
+    assert_eq!(left, 0);
+    let bulk_uploads = mock_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| {
+            request.method.as_str() == "POST" && request.url.path().ends_with(".bulk")
+        })
+        .count();
+    assert_eq!(bulk_uploads, 3);
+}